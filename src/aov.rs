@@ -0,0 +1,59 @@
+use crate::{color::Color, tuple::Tuple, util::Float, world::ObjectId};
+
+/// Auxiliary data about what the camera sees through the center of a pixel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AovSample {
+    /// Distance from the camera to the surface, infinite when nothing is hit.
+    pub depth: Float,
+    /// Unit world space normal of the surface.
+    pub normal: Option<Tuple>,
+    /// Unlit surface color.
+    pub albedo: Color,
+    /// Handle of the object, which stays the same as other objects are
+    /// added and removed.
+    pub object_id: Option<ObjectId>,
+}
+
+impl Default for AovSample {
+    fn default() -> Self {
+        Self {
+            depth: Float::INFINITY,
+            normal: None,
+            albedo: Color::black(),
+            object_id: None,
+        }
+    }
+}
+
+/// Arbitrary output variables rendered alongside the image, for compositing
+/// or to guide a denoiser.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aovs {
+    width: usize,
+    height: usize,
+    pub pixels: Vec<AovSample>,
+}
+
+impl Aovs {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![AovSample::default(); width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn at(&self, x: usize, y: usize) -> AovSample {
+        assert!(x < self.width);
+        assert!(y < self.height);
+        self.pixels[x + y * self.width]
+    }
+}
@@ -1,5 +1,3 @@
-use std::f64::consts::{FRAC_PI_2, PI};
-
 use ray_tracer::{
     camera::Camera,
     color::Color,
@@ -7,10 +5,11 @@ use ray_tracer::{
     pattern::Pattern,
     transformations::{rot_x, rot_y, scaling, translation, view_transform},
     tuple::{point, vector},
+    util::consts::{FRAC_PI_2, PI},
     world::World,
 };
 
-fn main() {
+fn main() -> std::io::Result<()> {
     let mut world = World::ch7_default();
     let floor = Shape::plane();
 
@@ -42,12 +41,12 @@ fn main() {
     left.material.diffuse = 0.7;
     left.material.specular = 0.3;
 
-    world.objects.clear();
-    world.objects.push(left);
-    world.objects.push(middle);
-    world.objects.push(right);
-    world.objects.push(floor);
-    world.objects.push(backdrop);
+    world.clear_objects();
+    world.add(left);
+    world.add(middle);
+    world.add(right);
+    world.add(floor);
+    world.add(backdrop);
 
     let mut camera = Camera::new(1920, 1080, PI / 3.0);
     camera.set_transform(view_transform(
@@ -56,5 +55,5 @@ fn main() {
         vector(0.0, 1.0, 0.0),
     ));
     let image = camera.render(world);
-    image.save_ppm("ch10.ppm");
+    image.save_ppm("ch10.ppm")
 }
@@ -1,15 +1,14 @@
-use std::f64::consts::{FRAC_PI_2, PI};
-
 use ray_tracer::{
     camera::Camera,
     color::Color,
     object::Shape,
     transformations::{scaling, translation, view_transform},
     tuple::{point, vector},
+    util::consts::{FRAC_PI_2, PI},
     world::World,
 };
 
-fn main() {
+fn main() -> std::io::Result<()> {
     let mut world = World::ch7_default();
     let mut floor = Shape::sphere();
     floor.set_transform(scaling(10.0, 0.01, 10.0));
@@ -23,7 +22,7 @@ fn main() {
             .rot_y(-PI / 4.0)
             .translation(0.0, 0.0, 5.0),
     );
-    left_wall.material = floor.material;
+    left_wall.material = floor.material.clone();
 
     let mut right_wall = Shape::sphere();
     right_wall.set_transform(
@@ -32,7 +31,7 @@ fn main() {
             .rot_y(PI / 4.0)
             .translation(0.0, 0.0, 5.0),
     );
-    right_wall.material = floor.material;
+    right_wall.material = floor.material.clone();
 
     let mut middle = Shape::sphere();
     middle.set_transform(translation(-0.5, 1.0, 0.5));
@@ -57,13 +56,13 @@ fn main() {
         point(0.0, 1.0, 0.0),
         vector(0.0, 1.0, 0.0),
     ));
-    world.objects.clear();
-    world.objects.push(left);
-    world.objects.push(middle);
-    world.objects.push(right);
-    world.objects.push(left_wall);
-    world.objects.push(floor);
-    world.objects.push(right_wall);
+    world.clear_objects();
+    world.add(left);
+    world.add(middle);
+    world.add(right);
+    world.add(left_wall);
+    world.add(floor);
+    world.add(right_wall);
     let image = camera.render(world);
-    image.save_ppm("end_ch7.ppm");
+    image.save_ppm("end_ch7.ppm")
 }
@@ -1,5 +1,3 @@
-use std::f64::consts::{FRAC_PI_2, PI};
-
 use ray_tracer::{
     camera::Camera,
     color::Color,
@@ -7,10 +5,11 @@ use ray_tracer::{
     pattern::Pattern,
     transformations::{rot_x, rot_y, scaling, translation, view_transform},
     tuple::{point, vector},
+    util::consts::{FRAC_PI_2, PI},
     world::World,
 };
 
-fn main() {
+fn main() -> std::io::Result<()> {
     let mut world = World::ch7_default();
     let mut floor = Shape::plane();
     floor.material.shininess = 20.0;
@@ -46,12 +45,12 @@ fn main() {
     left.material.diffuse = 0.7;
     left.material.specular = 0.3;
 
-    world.objects.clear();
-    world.objects.push(left);
-    world.objects.push(middle);
-    world.objects.push(right);
-    world.objects.push(floor);
-    world.objects.push(backdrop);
+    world.clear_objects();
+    world.add(left);
+    world.add(middle);
+    world.add(right);
+    world.add(floor);
+    world.add(backdrop);
 
     let mut camera = Camera::new(500, 250, PI / 3.0);
     camera.set_transform(view_transform(
@@ -60,5 +59,5 @@ fn main() {
         vector(0.0, 1.0, 0.0),
     ));
     let image = camera.render(world);
-    image.save_ppm("reflection.ppm");
+    image.save_ppm("reflection.ppm")
 }
@@ -0,0 +1,213 @@
+use crate::{
+    matrix::Mat4,
+    ray::Ray,
+    tuple::{point, Tuple},
+    util::Float,
+};
+
+/// Axis aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min: Tuple,
+    pub max: Tuple,
+}
+
+impl Bounds {
+    pub fn new(min: Tuple, max: Tuple) -> Self {
+        Self { min, max }
+    }
+
+    pub fn infinite() -> Self {
+        Self {
+            min: point(
+                Float::NEG_INFINITY,
+                Float::NEG_INFINITY,
+                Float::NEG_INFINITY,
+            ),
+            max: point(Float::INFINITY, Float::INFINITY, Float::INFINITY),
+        }
+    }
+
+    pub fn is_infinite(&self) -> bool {
+        [
+            self.min.x, self.min.y, self.min.z, self.max.x, self.max.y, self.max.z,
+        ]
+        .iter()
+        .any(|v| v.is_infinite())
+    }
+
+    pub fn corners(&self) -> [Tuple; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            point(a.x, a.y, a.z),
+            point(a.x, a.y, b.z),
+            point(a.x, b.y, a.z),
+            point(a.x, b.y, b.z),
+            point(b.x, a.y, a.z),
+            point(b.x, a.y, b.z),
+            point(b.x, b.y, a.z),
+            point(b.x, b.y, b.z),
+        ]
+    }
+
+    /// Box containing nothing, neutral element of [`Bounds::merge`].
+    pub fn empty() -> Self {
+        Self {
+            min: point(Float::INFINITY, Float::INFINITY, Float::INFINITY),
+            max: point(
+                Float::NEG_INFINITY,
+                Float::NEG_INFINITY,
+                Float::NEG_INFINITY,
+            ),
+        }
+    }
+
+    pub fn merge(&self, other: &Bounds) -> Self {
+        Self {
+            min: point(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            max: point(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        }
+    }
+
+    pub fn center(&self) -> Tuple {
+        point(
+            (self.min.x + self.max.x) / 2.0,
+            (self.min.y + self.max.y) / 2.0,
+            (self.min.z + self.max.z) / 2.0,
+        )
+    }
+
+    /// Slab test: whether the ray crosses the box in front of its origin.
+    pub fn intersects(&self, r: Ray) -> bool {
+        let mut tmin = Float::NEG_INFINITY;
+        let mut tmax = Float::INFINITY;
+        for (origin, direction, min, max) in [
+            (r.origin.x, r.direction.x, self.min.x, self.max.x),
+            (r.origin.y, r.direction.y, self.min.y, self.max.y),
+            (r.origin.z, r.direction.z, self.min.z, self.max.z),
+        ] {
+            let t1 = (min - origin) / direction;
+            let t2 = (max - origin) / direction;
+            // NaN happens when the origin lies on a slab the ray is parallel
+            // to; Float::min/max skip it, which keeps the test conservative
+            tmin = tmin.max(t1.min(t2));
+            tmax = tmax.min(t1.max(t2));
+        }
+        tmin <= tmax && tmax >= 0.0
+    }
+
+    /// Whether the two boxes share at least a point, touching included.
+    pub fn overlaps(&self, other: &Bounds) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+            && self.min.z <= other.max.z
+            && other.min.z <= self.max.z
+    }
+
+    /// Distance from `p` to the nearest point of the box, 0 inside.
+    pub fn distance_to(&self, p: Tuple) -> Float {
+        let outside = |v: Float, min: Float, max: Float| (min - v).max(v - max).max(0.0);
+        let dx = outside(p.x, self.min.x, self.max.x);
+        let dy = outside(p.y, self.min.y, self.max.y);
+        let dz = outside(p.z, self.min.z, self.max.z);
+        (dx * dx + dy * dy + dz * dz).sqrt()
+    }
+
+    /// Smallest box containing all the corners of this one once transformed.
+    /// Unbounded boxes stay unbounded.
+    pub fn transform(&self, t: Mat4) -> Self {
+        if self.is_infinite() {
+            return Self::infinite();
+        }
+        let corners = self.corners().map(|c| t * c);
+        let mut min = corners[0];
+        let mut max = corners[0];
+        for c in &corners[1..] {
+            min = point(min.x.min(c.x), min.y.min(c.y), min.z.min(c.z));
+            max = point(max.x.max(c.x), max.y.max(c.y), max.z.max(c.z));
+        }
+        Self { min, max }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        transformations::{rot_y, scaling},
+        tuple::vector,
+        util::consts::FRAC_PI_4,
+    };
+
+    use super::*;
+
+    #[test]
+    fn overlapping_boxes_and_distances() {
+        let b = Bounds::new(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0));
+        assert!(b.overlaps(&Bounds::new(point(1.0, 0.0, 0.0), point(2.0, 2.0, 2.0))));
+        assert!(!b.overlaps(&Bounds::new(point(1.5, 0.0, 0.0), point(2.0, 2.0, 2.0))));
+        assert!(b.overlaps(&Bounds::infinite()));
+        assert_eq!(b.distance_to(point(0.5, 0.0, 0.0)), 0.0);
+        assert_eq!(b.distance_to(point(4.0, 5.0, 0.0)), 5.0);
+    }
+
+    #[test]
+    fn transforming_a_box_with_a_translation_and_scaling() {
+        let b = Bounds::new(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0));
+        let t = scaling(2.0, 1.0, 1.0).translation(0.0, 5.0, 0.0);
+        assert_eq!(
+            b.transform(t),
+            Bounds::new(point(-2.0, 4.0, -1.0), point(2.0, 6.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn transforming_a_rotated_box_grows_it() {
+        let b = Bounds::new(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0));
+        let r = b.transform(rot_y(FRAC_PI_4));
+        let d = Float::sqrt(2.0);
+        assert_eq!(r, Bounds::new(point(-d, -1.0, -d), point(d, 1.0, d)));
+    }
+
+    #[test]
+    fn merging_boxes() {
+        let a = Bounds::new(point(-1.0, 0.0, 0.0), point(1.0, 1.0, 1.0));
+        let b = Bounds::new(point(0.0, -2.0, 0.5), point(3.0, 0.5, 0.5));
+        assert_eq!(
+            a.merge(&b),
+            Bounds::new(point(-1.0, -2.0, 0.0), point(3.0, 1.0, 1.0))
+        );
+        assert_eq!(Bounds::empty().merge(&a), a);
+    }
+
+    #[test]
+    fn ray_box_intersection() {
+        let b = Bounds::new(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0));
+        let hit = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let miss = Ray::new(point(0.0, 2.0, -5.0), vector(0.0, 0.0, 1.0));
+        let behind = Ray::new(point(0.0, 0.0, 5.0), vector(0.0, 0.0, 1.0));
+        let inside = Ray::new(point(0.0, 0.0, 0.0), vector(1.0, 1.0, 0.0).norm());
+        assert!(b.intersects(hit));
+        assert!(!b.intersects(miss));
+        assert!(!b.intersects(behind));
+        assert!(b.intersects(inside));
+    }
+
+    #[test]
+    fn infinite_boxes_stay_infinite() {
+        let b = Bounds::new(
+            point(Float::NEG_INFINITY, 0.0, Float::NEG_INFINITY),
+            point(Float::INFINITY, 0.0, Float::INFINITY),
+        );
+        assert!(b.transform(rot_y(FRAC_PI_4)).is_infinite());
+    }
+}
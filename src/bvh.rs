@@ -0,0 +1,243 @@
+use std::borrow::Borrow;
+
+use crate::{bounds::Bounds, object::Shape, ray::Ray};
+
+/// Objects per leaf before a node gets split.
+const MAX_LEAF_SIZE: usize = 2;
+
+/// Size of the traversal stack.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone)]
+enum Node {
+    Leaf {
+        bounds: Bounds,
+        objects: Vec<usize>,
+    },
+    Inner {
+        bounds: Bounds,
+        left: usize,
+        right: usize,
+    },
+}
+
+impl Node {
+    fn bounds(&self) -> &Bounds {
+        match self {
+            Node::Leaf { bounds, .. } | Node::Inner { bounds, .. } => bounds,
+        }
+    }
+}
+
+/// Bounding volume hierarchy over the objects of a world, referenced by their
+/// index. Unbounded shapes (planes) can't be sorted in boxes and are always
+/// reported as candidates.
+#[derive(Debug, Clone)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    unbounded: Vec<usize>,
+    /// Generation of the world it was built for, see [`World::bvh_over`].
+    ///
+    /// [`World::bvh_over`]: crate::world::World::bvh_over
+    generation: Option<u64>,
+}
+
+impl Bvh {
+    /// Builds the hierarchy over `objects[i]` for every `i` of `indices`.
+    pub fn build(objects: &[impl Borrow<Shape>], indices: &[usize]) -> Self {
+        let mut bvh = Self {
+            nodes: vec![],
+            unbounded: vec![],
+            generation: None,
+        };
+        let mut bounded = vec![];
+        for &i in indices {
+            let b = objects[i].borrow().bounds();
+            if b.is_infinite() {
+                bvh.unbounded.push(i);
+            } else {
+                bounded.push((i, b));
+            }
+        }
+        if !bounded.is_empty() {
+            bvh.build_node(&mut bounded);
+        }
+        bvh
+    }
+
+    /// Builds the subtree holding `items` and returns its index.
+    fn build_node(&mut self, items: &mut [(usize, Bounds)]) -> usize {
+        let bounds = items
+            .iter()
+            .fold(Bounds::empty(), |acc, (_, b)| acc.merge(b));
+        if items.len() <= MAX_LEAF_SIZE {
+            self.nodes.push(Node::Leaf {
+                bounds,
+                objects: items.iter().map(|(i, _)| *i).collect(),
+            });
+            return self.nodes.len() - 1;
+        }
+
+        // split at the median along the axis where the centers spread most
+        let centers = items.iter().fold(Bounds::empty(), |acc, (_, b)| {
+            acc.merge(&Bounds::new(b.center(), b.center()))
+        });
+        let extent = centers.max - centers.min;
+        let axis = |b: &Bounds| {
+            let c = b.center();
+            if extent.x >= extent.y && extent.x >= extent.z {
+                c.x
+            } else if extent.y >= extent.z {
+                c.y
+            } else {
+                c.z
+            }
+        };
+        items.sort_by(|(_, a), (_, b)| axis(a).total_cmp(&axis(b)));
+        let (left_items, right_items) = items.split_at_mut(items.len() / 2);
+
+        let index = self.nodes.len();
+        self.nodes.push(Node::Leaf {
+            bounds,
+            objects: vec![],
+        });
+        let left = self.build_node(left_items);
+        let right = self.build_node(right_items);
+        self.nodes[index] = Node::Inner {
+            bounds,
+            left,
+            right,
+        };
+        index
+    }
+
+    pub(crate) fn stamped(mut self, generation: u64) -> Self {
+        self.generation = Some(generation);
+        self
+    }
+
+    /// Generation of the world it was built for, none when it was built
+    /// over a bare slice of objects.
+    pub fn generation(&self) -> Option<u64> {
+        self.generation
+    }
+
+    /// Pushes into `out` the index of every object whose bounds are crossed by
+    /// `r`, plus all the unbounded objects.
+    pub fn candidates(&self, r: Ray, out: &mut Vec<usize>) {
+        self.for_each_candidate(r, |i| out.push(i));
+    }
+
+    /// Calls `f` with the index of every object whose bounds are crossed by
+    /// `r`, plus all the unbounded objects. Does not allocate.
+    pub fn for_each_candidate(&self, r: Ray, mut f: impl FnMut(usize)) {
+        self.any_candidate(r, |i| {
+            f(i);
+            false
+        });
+    }
+
+    /// Calls `f` on the candidates like [`Bvh::for_each_candidate`], stopping
+    /// at the first one for which it returns `true`.
+    pub fn any_candidate(&self, r: Ray, f: impl FnMut(usize) -> bool) -> bool {
+        self.any_in(|b| b.intersects(r), f)
+    }
+
+    /// Calls `f` with the index of every object whose bounds overlap
+    /// `region`, plus all the unbounded objects.
+    pub fn for_each_overlapping(&self, region: &Bounds, mut f: impl FnMut(usize)) {
+        self.any_in(
+            |b| b.overlaps(region),
+            |i| {
+                f(i);
+                false
+            },
+        );
+    }
+
+    /// Walks down the nodes whose bounds pass `enter`, calling `f` on the
+    /// objects of their leaves until it returns `true`.
+    fn any_in(&self, enter: impl Fn(&Bounds) -> bool, mut f: impl FnMut(usize) -> bool) -> bool {
+        if self.unbounded.iter().any(|&i| f(i)) {
+            return true;
+        }
+        if self.nodes.is_empty() {
+            return false;
+        }
+        // median splits keep the tree balanced, so its depth is about
+        // log2(objects) and this can't overflow
+        let mut stack = [0; MAX_DEPTH];
+        let mut top = 1;
+        while top > 0 {
+            top -= 1;
+            let node = &self.nodes[stack[top]];
+            if !enter(node.bounds()) {
+                continue;
+            }
+            match node {
+                Node::Leaf { objects, .. } => {
+                    if objects.iter().any(|&i| f(i)) {
+                        return true;
+                    }
+                }
+                Node::Inner { left, right, .. } => {
+                    stack[top] = *left;
+                    stack[top + 1] = *right;
+                    top += 2;
+                }
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        transformations::translation,
+        tuple::{point, vector},
+        util::Float,
+    };
+
+    use super::*;
+
+    fn row_of_spheres(n: usize) -> Vec<Shape> {
+        (0..n)
+            .map(|i| Shape::sphere().with_transform(translation(3.0 * i as Float, 0.0, 0.0)))
+            .collect()
+    }
+
+    #[test]
+    fn ray_only_visits_the_boxes_it_crosses() {
+        let objects = row_of_spheres(10);
+        let bvh = Bvh::build(&objects, &(0..10).collect::<Vec<_>>());
+        let r = Ray::new(point(9.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let mut candidates = vec![];
+        bvh.candidates(r, &mut candidates);
+        // the sphere sharing its leaf can come along
+        assert!(candidates.contains(&3));
+        assert!(candidates.len() <= MAX_LEAF_SIZE);
+    }
+
+    #[test]
+    fn unbounded_objects_are_always_candidates() {
+        let mut objects = row_of_spheres(4);
+        objects.push(Shape::plane());
+        let bvh = Bvh::build(&objects, &[0, 1, 2, 3, 4]);
+        let r = Ray::new(point(100.0, 5.0, 0.0), vector(0.0, -1.0, 0.0));
+        let mut candidates = vec![];
+        bvh.candidates(r, &mut candidates);
+        assert_eq!(candidates, vec![4]);
+    }
+
+    #[test]
+    fn building_over_a_subset() {
+        let objects = row_of_spheres(6);
+        let bvh = Bvh::build(&objects, &[1, 4]);
+        let r = Ray::new(point(-5.0, 0.0, 0.0), vector(1.0, 0.0, 0.0));
+        let mut candidates = vec![];
+        bvh.candidates(r, &mut candidates);
+        candidates.sort();
+        assert_eq!(candidates, vec![1, 4]);
+    }
+}
@@ -1,28 +1,113 @@
+use std::{io, path::Path};
+
 use crate::{
+    aov::{AovSample, Aovs},
+    bounds::Bounds,
+    bvh::Bvh,
     canvas::Canvas,
+    checkpoint::{self, Checkpoint},
+    clip::ClipPlane,
+    color::Color,
+    denoise::Denoiser,
+    intersection::Intersectable,
     matrix::{Mat4, MatBase},
     ray::Ray,
-    tuple::point,
-    util::MAX_REFLECTIONS,
+    render_buffer::{Radiance, RenderBuffer},
+    sampler::{Rng, Sampler},
+    stats,
+    transformations::view_transform,
+    tuple::{point, vector, Tuple},
+    util::{Float, EPSILON},
     world::World,
 };
 
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
+/// Algorithm estimating the color seen by each camera ray.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Integrator {
+    /// Whitted style ray tracing: [`World::color_at`].
+    #[default]
+    Whitted,
+    /// Monte Carlo path tracing: [`World::trace_path`]. Noisy unless many
+    /// samples are taken per pixel.
+    PathTracing,
+}
+
+/// Work measured per pixel by [`RenderMode::Heatmap`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CostMetric {
+    IntersectionTests,
+    /// Camera, reflected, refracted and path rays, see [`stats::RayStats`].
+    Rays,
+}
+
+/// What [`Camera::render`] draws.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RenderMode {
+    /// The shaded image, computed by the camera's [`Integrator`].
+    #[default]
+    Shaded,
+    /// The cost of each pixel instead of its color, from blue for the
+    /// cheapest to red for the most expensive pixel of the image.
+    Heatmap(CostMetric),
+    /// World space normal of the first surface hit, mapped from `[-1, 1]` to
+    /// `[0, 1]`.
+    Normals,
+    /// Distance to the first surface hit, white for the nearest and black
+    /// for the farthest in the image.
+    Depth,
+    /// Unlit surface color of the first hit.
+    Albedo,
+}
+
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Camera {
     hsize: usize,
     vsize: usize,
-    fov: f64,
+    fov: Float,
     pub transform: Mat4,
     pub transform_inverse: Mat4,
-    pixel_size: f64,
-    half_width: f64,
-    half_height: f64,
+    pixel_size: Float,
+    half_width: Float,
+    half_height: Float,
+    sampler: Sampler,
+    samples: usize,
+    integrator: Integrator,
+    mode: RenderMode,
+    seed: u64,
+    near: Float,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default = "unbounded", skip_serializing_if = "is_unbounded")
+    )]
+    far: Float,
+    alpha: bool,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    wireframe: Option<Color>,
+}
+
+// JSON has no infinity, an unbounded far distance is left out instead
+#[cfg(feature = "serde")]
+fn unbounded() -> Float {
+    Float::INFINITY
+}
+
+#[cfg(feature = "serde")]
+fn is_unbounded(far: &Float) -> bool {
+    far.is_infinite()
 }
 
 impl Camera {
-    pub fn new(hsize: usize, vsize: usize, fov: f64) -> Self {
+    pub fn new(hsize: usize, vsize: usize, fov: Float) -> Self {
         let (half_height, half_width, pixel_size) = Self::pixel_size(hsize, vsize, fov);
         Self {
             hsize,
@@ -30,15 +115,84 @@ impl Camera {
             fov,
             transform: Mat4::identity(),
             transform_inverse: Mat4::identity(),
-            pixel_size: pixel_size,
+            pixel_size,
             half_height,
             half_width,
+            sampler: Sampler::default(),
+            samples: 1,
+            integrator: Integrator::default(),
+            mode: RenderMode::default(),
+            seed: 0,
+            near: 0.0,
+            far: Float::INFINITY,
+            alpha: false,
+            wireframe: None,
         }
     }
 
-    fn pixel_size(hsize: usize, vsize: usize, fov: f64) -> (f64, f64, f64) {
-        let half_view = f64::tan(fov / 2.0);
-        let aspect_ratio = hsize as f64 / vsize as f64;
+    /// Anti-aliasing: shoot `samples` rays per pixel, placed by `sampler`.
+    pub fn with_sampling(mut self, sampler: Sampler, samples: usize) -> Self {
+        assert!(samples > 0);
+        self.sampler = sampler;
+        self.samples = samples;
+        self
+    }
+
+    pub fn with_integrator(mut self, integrator: Integrator) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    pub fn with_render_mode(mut self, mode: RenderMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Draws the edges of the triangles over the rendered image in `color`,
+    /// whatever the render mode.
+    pub fn with_wireframe(mut self, color: Color) -> Self {
+        self.wireframe = Some(color);
+        self
+    }
+
+    /// Makes the pixels where the camera rays miss every object transparent
+    /// in the rendered image, so it can be composited over a background.
+    pub fn with_alpha(mut self, alpha: bool) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Cuts away the geometry closer to the eye than `near` or farther than
+    /// `far`, measured along the view direction.
+    pub fn with_clip_distances(mut self, near: Float, far: Float) -> Self {
+        assert!(near < far);
+        self.near = near;
+        self.far = far;
+        self
+    }
+
+    /// Seed of every random choice made while rendering. Two renders with the
+    /// same seed and settings give the same image.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Same view drawn with `hsize` by `vsize` pixels, e.g. for a quick
+    /// preview at a lower resolution.
+    pub fn with_resolution(mut self, hsize: usize, vsize: usize) -> Self {
+        let (half_height, half_width, pixel_size) = Self::pixel_size(hsize, vsize, self.fov);
+        self.hsize = hsize;
+        self.vsize = vsize;
+        self.half_height = half_height;
+        self.half_width = half_width;
+        self.pixel_size = pixel_size;
+        self
+    }
+
+    fn pixel_size(hsize: usize, vsize: usize, fov: Float) -> (Float, Float, Float) {
+        let half_view = Float::tan(fov / 2.0);
+        let aspect_ratio = hsize as Float / vsize as Float;
         let half_width;
         let half_height;
 
@@ -49,7 +203,38 @@ impl Camera {
             half_width = half_view * aspect_ratio;
             half_height = half_view;
         }
-        (half_height, half_width, (half_width * 2.0) / hsize as f64)
+        (half_height, half_width, (half_width * 2.0) / hsize as Float)
+    }
+
+    /// Moves the camera along its view direction until the objects of
+    /// `world` with finite bounds fit in the image, centered. The camera
+    /// keeps its orientation.
+    pub fn frame(mut self, world: &World) -> Self {
+        let bounds = world.finite_bounds();
+        if bounds.min.x > bounds.max.x {
+            return self;
+        }
+        let center = bounds.center();
+        // the bounding sphere of the box fits in the narrowest angle
+        let radius = ((bounds.max - bounds.min).mag() / 2.0).max(EPSILON);
+        let half_angle = self.half_width.min(self.half_height).atan();
+        let forward = (self.transform_inverse * vector(0.0, 0.0, -1.0)).norm();
+        let up = self.transform_inverse * vector(0.0, 1.0, 0.0);
+        let from = center - forward * (radius / half_angle.sin());
+        self.set_transform(view_transform(from, center, up));
+        self
+    }
+
+    pub fn hsize(&self) -> usize {
+        self.hsize
+    }
+
+    pub fn vsize(&self) -> usize {
+        self.vsize
+    }
+
+    pub fn fov(&self) -> Float {
+        self.fov
     }
 
     pub fn set_transform(&mut self, transform: Mat4) {
@@ -58,10 +243,15 @@ impl Camera {
     }
 
     pub fn ray_for_pixel(&self, x: usize, y: usize) -> Ray {
-        let x = x as f64;
-        let y = y as f64;
-        let offset_x = (x + 0.5) * self.pixel_size;
-        let offset_y = (y + 0.5) * self.pixel_size;
+        self.ray_for_sample(x, y, 0.5, 0.5)
+    }
+
+    /// Ray going through the point `(dx, dy)` of the pixel, both in `[0, 1)`.
+    pub fn ray_for_sample(&self, x: usize, y: usize, dx: Float, dy: Float) -> Ray {
+        let x = x as Float;
+        let y = y as Float;
+        let offset_x = (x + dx) * self.pixel_size;
+        let offset_y = (y + dy) * self.pixel_size;
 
         let world_x = self.half_width - offset_x;
         let world_y = self.half_height - offset_y;
@@ -69,40 +259,412 @@ impl Camera {
         let pixel = (self.transform_inverse) * point(world_x, world_y, -1.0);
         let origin = (self.transform_inverse) * point(0.0, 0.0, 0.0);
         let direction = (pixel - origin).norm();
-        Ray::new(origin, direction)
+        Ray::new(origin, direction).with_spread(self.pixel_size)
+    }
+
+    /// Inward normals of the four side planes of the view frustum, in world
+    /// space. All planes go through the eye.
+    fn frustum_planes(&self) -> [Tuple; 4] {
+        let corners = [
+            vector(self.half_width, self.half_height, -1.0),
+            vector(-self.half_width, self.half_height, -1.0),
+            vector(-self.half_width, -self.half_height, -1.0),
+            vector(self.half_width, -self.half_height, -1.0),
+        ];
+        let forward = vector(0.0, 0.0, -1.0);
+        let to_world = self.transform.transpose();
+        [0, 1, 2, 3].map(|i| {
+            let mut n = corners[i].cross(corners[(i + 1) % 4]);
+            if (n ^ forward) < 0.0 {
+                n = -n;
+            }
+            let mut n = to_world * n;
+            n.w = 0.0;
+            n.norm()
+        })
+    }
+
+    /// Whether some part of `bounds` may be seen by the camera.
+    pub fn sees(&self, bounds: &Bounds) -> bool {
+        if bounds.is_infinite() {
+            return true;
+        }
+        let eye = self.transform_inverse * point(0.0, 0.0, 0.0);
+        self.frustum_planes().iter().all(|n| {
+            let pick = |n: Float, min: Float, max: Float| if n >= 0.0 { max } else { min };
+            let farthest = point(
+                pick(n.x, bounds.min.x, bounds.max.x),
+                pick(n.y, bounds.min.y, bounds.max.y),
+                pick(n.z, bounds.min.z, bounds.max.z),
+            );
+            ((farthest - eye) ^ *n) >= -EPSILON
+        })
+    }
+
+    /// Indices of the objects of `world` that are not wholly outside the view
+    /// frustum nor hidden from the camera. Culled objects still cast shadows
+    /// and show in reflections.
+    pub fn visible_objects(&self, world: &World) -> Vec<usize> {
+        world
+            .objects()
+            .iter()
+            .enumerate()
+            .filter(|(_, o)| o.visible_to_camera && self.sees(&o.bounds()))
+            .map(|(i, _)| i)
+            .collect()
     }
 
     pub fn render(&self, world: World) -> Canvas {
-        let colors = (0..self.vsize)
-            .into_par_iter()
-            .map(|y| {
-                // reserve a vec that can hold the row
-                let mut row = Vec::with_capacity(self.hsize);
-                for x in 0..self.hsize {
-                    let r = self.ray_for_pixel(x, y);
-                    row.push(world.color_at(r, MAX_REFLECTIONS))
+        let edges = self
+            .wireframe
+            .map(|color| (color, self.edges(world.clone())));
+        let mut canvas = match self.mode {
+            RenderMode::Shaded => self.render_buffer(world).to_canvas(),
+            RenderMode::Heatmap(metric) => self.render_heatmap(world, metric),
+            RenderMode::Normals | RenderMode::Depth | RenderMode::Albedo => {
+                self.render_diagnostic(world)
+            }
+        };
+        if let Some((color, edges)) = edges {
+            for (i, _) in edges.iter().enumerate().filter(|(_, on_edge)| **on_edge) {
+                canvas.write_pixel(i % self.hsize, i / self.hsize, color);
+            }
+        }
+        canvas
+    }
+
+    /// Which pixels see an edge of a triangle through their center. A pixel
+    /// is about `t * pixel_size` wide at distance `t`, the edges are drawn
+    /// about a pixel wide.
+    fn edges(&self, mut world: World) -> Vec<bool> {
+        self.prepare(&mut world);
+        let visible = self.primary_bvh(&world);
+        let mut edges = vec![false; self.hsize * self.vsize];
+        for_each_row(&mut edges, self.hsize, |y, row| {
+            for (x, on_edge) in row.iter_mut().enumerate() {
+                let r = self.ray_for_pixel(x, y);
+                *on_edge = world.first_hit_bvh(r, &visible).is_some_and(|(id, t)| {
+                    world.objects()[id]
+                        .edge_distance(r.position(t))
+                        .is_some_and(|d| d < 0.5 * t * self.pixel_size)
+                });
+            }
+        });
+        edges
+    }
+
+    /// Draws one of the auxiliary buffers for the diagnostic render modes.
+    /// Nothing is lit, so it is much faster than a shaded render.
+    fn render_diagnostic(&self, mut world: World) -> Canvas {
+        self.prepare(&mut world);
+        let aovs = self.aovs(&world);
+        let depths = aovs
+            .pixels
+            .iter()
+            .map(|a| a.depth)
+            .filter(|d| d.is_finite());
+        let (near, far): (Float, Float) =
+            depths.fold((Float::INFINITY, 0.0), |(n, f), d| (n.min(d), f.max(d)));
+        let range = (far - near).max(EPSILON);
+        let pixels = aovs
+            .pixels
+            .iter()
+            .map(|a| match self.mode {
+                RenderMode::Normals => a.normal.map_or(Color::black(), |n| {
+                    Color::new(n.x + 1.0, n.y + 1.0, n.z + 1.0) * 0.5
+                }),
+                RenderMode::Depth if a.depth.is_finite() => {
+                    let v = 1.0 - (a.depth - near) / range;
+                    Color::new(v, v, v)
                 }
-                row
+                RenderMode::Albedo => a.albedo,
+                _ => Color::black(),
             })
-            .flatten()
-            .collect::<Vec<_>>();
-        Canvas::new_with_colors(self.hsize, self.vsize, colors)
+            .collect();
+        Canvas::new_with_colors(self.hsize, self.vsize, pixels)
+    }
+
+    /// Renders the image, coloring each pixel by the work its samples took
+    /// instead of by their color.
+    pub fn render_heatmap(&self, mut world: World, metric: CostMetric) -> Canvas {
+        self.prepare(&mut world);
+        let visible = self.primary_bvh(&world);
+        let mut costs = vec![0.0; self.hsize * self.vsize];
+        for_each_row(&mut costs, self.hsize, |y, row| {
+            for (x, cost) in row.iter_mut().enumerate() {
+                // a pixel is traced on a single thread, so the counters of
+                // that thread only moved for it
+                let before = stats::current();
+                self.sample_pixel(&world, &visible, x, y, &mut Radiance::default());
+                let work = stats::current().since(before);
+                *cost = match metric {
+                    CostMetric::IntersectionTests => work.intersection_tests,
+                    CostMetric::Rays => work.rays,
+                } as Float;
+            }
+        });
+        let min = costs.iter().copied().fold(Float::INFINITY, Float::min);
+        let max = costs.iter().copied().fold(0.0, Float::max);
+        let range = (max - min).max(1.0);
+        Canvas::new_with_colors(
+            self.hsize,
+            self.vsize,
+            costs.iter().map(|c| heat((c - min) / range)).collect(),
+        )
+    }
+
+    /// Renders the linear radiance seen by the camera, before it is turned
+    /// into an image.
+    pub fn render_buffer(&self, mut world: World) -> RenderBuffer {
+        self.prepare(&mut world);
+        self.shade_buffer(&world)
+    }
+
+    fn shade_buffer(&self, world: &World) -> RenderBuffer {
+        let visible = self.primary_bvh(world);
+        let mut buffer = RenderBuffer::new(self.hsize, self.vsize);
+        for_each_row(&mut buffer.pixels, self.hsize, |y, row| {
+            self.render_row(world, &visible, y, row)
+        });
+        buffer
+    }
+
+    /// Renders the radiance like [`Camera::render_buffer`], along with depth,
+    /// normal, albedo and object ID buffers.
+    pub fn render_with_aovs(&self, mut world: World) -> (RenderBuffer, Aovs) {
+        self.prepare(&mut world);
+        (self.shade_buffer(&world), self.aovs(&world))
+    }
+
+    fn aovs(&self, world: &World) -> Aovs {
+        let visible = self.primary_bvh(world);
+        let mut aovs = Aovs::new(self.hsize, self.vsize);
+        for_each_row(&mut aovs.pixels, self.hsize, |y, row| {
+            for (x, aov) in row.iter_mut().enumerate() {
+                *aov = self.aov_sample(world, &visible, x, y)
+            }
+        });
+        aovs
+    }
+
+    /// Renders the image and cleans it with `denoiser` before building the
+    /// canvas.
+    pub fn render_denoised(&self, world: World, denoiser: &dyn Denoiser) -> Canvas {
+        let (buffer, aovs) = self.render_with_aovs(world);
+        denoiser.denoise(&buffer, &aovs).to_canvas()
+    }
+
+    fn aov_sample(&self, world: &World, visible: &Bvh, x: usize, y: usize) -> AovSample {
+        let r = self.ray_for_pixel(x, y);
+        match world.first_hit_bvh(r, visible) {
+            None => AovSample::default(),
+            Some((id, t)) => {
+                let shape = &world.objects()[id];
+                let p = r.position(t);
+                AovSample {
+                    depth: t,
+                    normal: Some(shape.normal_at(&p)),
+                    albedo: shape.material.color_at(shape, p),
+                    object_id: Some(world.id_at(id)),
+                }
+            }
+        }
+    }
+
+    /// Renders like [`Camera::render`], saving the samples taken so far to
+    /// `checkpoint` every `rows_per_checkpoint` rows. If `checkpoint` already
+    /// holds samples of the same scene seen by this camera, only the missing
+    /// samples are traced: the pixels left unfinished, or the extra samples
+    /// when the camera takes more of them than before. The checkpoint file
+    /// is removed once the image is complete.
+    ///
+    /// Only shaded renders without a wireframe can be resumed, and samples
+    /// can only be added with a progressive [`Sampler`].
+    pub fn render_resumable(
+        &self,
+        mut world: World,
+        checkpoint: impl AsRef<Path>,
+        rows_per_checkpoint: usize,
+    ) -> io::Result<Canvas> {
+        assert!(rows_per_checkpoint > 0);
+        if self.mode != RenderMode::Shaded || self.wireframe.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "only shaded renders without a wireframe can be resumed",
+            ));
+        }
+        let path = checkpoint.as_ref();
+        let fingerprint = self.fingerprint(&world);
+        let mut state = match Checkpoint::load(path) {
+            Ok(c) if c.fingerprint() == fingerprint => c,
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "checkpoint was saved for another scene or camera",
+                ))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                Checkpoint::new(self.hsize, self.vsize, fingerprint)
+            }
+            Err(e) => return Err(e),
+        };
+        let extended = state.buffer().pixels.iter().any(|p| {
+            let samples = p.samples() as usize;
+            samples > 0 && samples < self.samples
+        });
+        if extended && !self.sampler.is_progressive() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "samples can only be added with a progressive sampler",
+            ));
+        }
+        self.prepare(&mut world);
+        let visible = self.primary_bvh(&world);
+        for chunk in state.missing_rows(self.samples).chunks(rows_per_checkpoint) {
+            let buffer = state.buffer_mut();
+            let mut pixels = chunk
+                .iter()
+                .flat_map(|&y| (0..self.hsize).map(move |x| (x, y)))
+                .map(|(x, y)| buffer.radiance_at(x, y))
+                .collect::<Vec<_>>();
+            for_each_row(&mut pixels, self.hsize, |i, row| {
+                self.render_row(&world, &visible, chunk[i], row)
+            });
+            for (&y, row) in chunk.iter().zip(pixels.chunks(self.hsize)) {
+                buffer.pixels[y * self.hsize..(y + 1) * self.hsize].copy_from_slice(row);
+            }
+            state.save(path)?;
+        }
+        std::fs::remove_file(path)?;
+        Ok(state.to_canvas())
+    }
+
+    /// Hash of the camera and `world`, telling whether a [`Checkpoint`] was
+    /// saved for their render. The sample count is left out, so that more
+    /// samples can be added to a finished render.
+    pub fn fingerprint(&self, world: &World) -> u64 {
+        checkpoint::fingerprint(&[
+            &Camera {
+                samples: 0,
+                ..*self
+            },
+            &world.fingerprint(),
+        ])
+    }
+
+    /// Adds the near and far planes to the clip planes of `world` and builds
+    /// its hierarchy.
+    fn prepare(&self, world: &mut World) {
+        let eye = self.transform_inverse * point(0.0, 0.0, 0.0);
+        let forward = (self.transform_inverse * vector(0.0, 0.0, -1.0)).norm();
+        if self.near > 0.0 {
+            world.add_clip_plane(ClipPlane::new(eye + forward * self.near, -forward));
+        }
+        if self.far.is_finite() {
+            world.add_clip_plane(ClipPlane::new(eye + forward * self.far, forward));
+        }
+        world.build_bvh();
+    }
+
+    /// Hierarchy over the visible objects, for the camera rays.
+    fn primary_bvh(&self, world: &World) -> Bvh {
+        world.bvh_over(&self.visible_objects(world))
     }
+
+    fn render_row(&self, world: &World, visible: &Bvh, y: usize, row: &mut [Radiance]) {
+        for (x, pixel) in row.iter_mut().enumerate() {
+            self.sample_pixel(world, visible, x, y, pixel)
+        }
+    }
+
+    /// Adds to `acc` the samples of the pixel it does not have yet.
+    fn sample_pixel(&self, world: &World, visible: &Bvh, x: usize, y: usize, acc: &mut Radiance) {
+        let index = x + y * self.hsize;
+        let pixel = Rng::new(self.seed).split(index as u64);
+        let samples = self.sampler.samples(self.samples, &mut pixel.clone());
+        let done = acc.samples() as usize;
+        for (i, (dx, dy)) in samples.into_iter().enumerate().skip(done) {
+            // sample `i` draws from its own stream, the same whatever the
+            // sample count, so resuming does not replay earlier samples
+            let mut rng = pixel.split(i as u64);
+            let r = self.ray_for_sample(x, y, dx, dy);
+            let color = match self.integrator {
+                Integrator::Whitted => world.color_at_bvh(r, visible),
+                Integrator::PathTracing => world.trace_path(r, &mut rng, world.max_depth()),
+            };
+            let covered = !self.alpha || world.first_hit_bvh(r, visible).is_some();
+            acc.add_with_coverage(color, covered);
+        }
+    }
+}
+
+/// Blue to cyan, green, yellow and red as `t` goes from 0 to 1.
+fn heat(t: Float) -> Color {
+    const STOPS: [(Float, Float, Float); 5] = [
+        (0.0, 0.0, 1.0),
+        (0.0, 1.0, 1.0),
+        (0.0, 1.0, 0.0),
+        (1.0, 1.0, 0.0),
+        (1.0, 0.0, 0.0),
+    ];
+    let x = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as Float;
+    let i = (x as usize).min(STOPS.len() - 2);
+    let f = x - i as Float;
+    let (a, b) = (STOPS[i], STOPS[i + 1]);
+    Color::new(
+        a.0 + (b.0 - a.0) * f,
+        a.1 + (b.1 - a.1) * f,
+        a.2 + (b.2 - a.2) * f,
+    )
+}
+
+/// Calls `f` with the index and the pixels of every row of `pixels`, on all
+/// cores when the `parallel` feature is enabled. Each row is handed out to a
+/// single call, so no locking is needed.
+fn for_each_row<T, F>(pixels: &mut [T], width: usize, f: F)
+where
+    T: Send,
+    F: Fn(usize, &mut [T]) + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    pixels
+        .par_chunks_mut(width)
+        .enumerate()
+        .for_each(|(y, row)| f(y, row));
+    #[cfg(not(feature = "parallel"))]
+    pixels
+        .chunks_mut(width)
+        .enumerate()
+        .for_each(|(y, row)| f(y, row));
 }
 
 #[cfg(test)]
 mod test {
-    use std::f64::consts::{PI, SQRT_2};
-
     use crate::{
         color::Color,
-        transformations::{translation, view_transform},
+        denoise::BilateralDenoiser,
+        material::Material,
+        object::Shape,
+        transformations::{rot_y, scaling, translation, view_transform},
         tuple::{point, vector},
-        util::flt_eq,
+        util::{
+            consts::{PI, SQRT_2},
+            flt_eq, Float,
+        },
         world::World,
     };
 
-    use super::Camera;
+    use super::{Camera, Checkpoint, CostMetric, Integrator, RenderMode, Sampler};
+
+    #[test]
+    fn scenes_can_be_shared_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<World>();
+        assert_send_sync::<Shape>();
+        assert_send_sync::<crate::bvh::Bvh>();
+        assert_send_sync::<crate::intersection::Intersection>();
+        assert_send_sync::<Camera>();
+    }
 
     #[test]
     fn pixel_size_horizontal_canvas() {
@@ -132,6 +694,14 @@ mod test {
         assert_eq!(r.direction, vector(0.66519, 0.33259, -0.66851));
     }
 
+    #[test]
+    fn camera_rays_widen_by_a_pixel_per_unit_of_distance() {
+        let c = Camera::new(200, 125, PI / 2.0);
+        let r = c.ray_for_pixel(100, 50);
+        assert!(flt_eq(r.spread, 0.01));
+        assert!(flt_eq(r.footprint(10.0), 0.1));
+    }
+
     #[test]
     fn ray_transformed_camera() {
         let mut c = Camera::new(201, 101, PI / 2.0);
@@ -152,4 +722,428 @@ mod test {
         let image = c.render(w);
         assert_eq!(image.pixel_at(5, 5), Color::new(0.38066, 0.47583, 0.2855))
     }
+
+    #[test]
+    fn ray_for_sample_at_pixel_center_matches_ray_for_pixel() {
+        let c = Camera::new(201, 101, PI / 2.0);
+        let r = c.ray_for_sample(0, 0, 0.5, 0.5);
+        assert_eq!(r.direction, c.ray_for_pixel(0, 0).direction);
+    }
+
+    #[test]
+    fn objects_outside_the_frustum_are_culled() {
+        let mut w = World::ch7_default();
+        w.add(Shape::sphere().with_transform(translation(0.0, 0.0, 10.0)));
+        w.add(Shape::sphere().with_transform(translation(20.0, 0.0, -20.0)));
+        w.add(Shape::plane());
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(view_transform(
+            point(0.0, 0.0, -5.0),
+            point(0.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+        ));
+        assert_eq!(c.visible_objects(&w), vec![0, 1, 2, 4]);
+    }
+
+    #[test]
+    fn culled_objects_still_cast_shadows() {
+        let mut w = World::ch7_default();
+        // behind the camera, between the light and the scene
+        w.add(Shape::sphere().with_transform(scaling(3.0, 3.0, 3.0).translation(-6.0, 6.0, -6.0)));
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(view_transform(
+            point(0.0, 0.0, -5.0),
+            point(0.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+        ));
+        assert_eq!(c.visible_objects(&w), vec![0, 1]);
+        let image = c.render(w);
+        assert_eq!(image.pixel_at(5, 5), Color::new(0.08, 0.1, 0.06));
+    }
+
+    #[test]
+    fn framing_centers_the_finite_objects() {
+        let mut w = World::new();
+        w.add(Shape::sphere().with_transform(translation(10.0, 0.0, 0.0)));
+        w.add(Shape::plane());
+        let c = Camera::new(11, 11, PI / 2.0).frame(&w);
+        let center = c.ray_for_pixel(5, 5);
+        assert_eq!(center.direction, vector(0.0, 0.0, -1.0));
+        assert!(w.cast_ray(center).is_some());
+        // the whole sphere is in view, with room around it
+        let corner = c.ray_for_pixel(0, 0);
+        assert!(w.objects()[0].hit_times(corner).as_slice().is_empty());
+        assert!(c.visible_objects(&w).contains(&0));
+    }
+
+    #[test]
+    fn resuming_a_render_only_traces_missing_rows() {
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(view_transform(
+            point(0.0, 0.0, -5.0),
+            point(0.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+        ));
+        let expected = c.render(World::ch7_default());
+
+        // pretend a previous run died halfway through row 5
+        let path = std::env::temp_dir().join("resuming_a_render.rtchk");
+        let mut state = Checkpoint::new(11, 11, c.fingerprint(&World::ch7_default()));
+        let marker = Color::new(1.0, 0.0, 1.0);
+        for i in 0..(5 * 11 + 6) {
+            state.buffer_mut().add_sample(i % 11, i / 11, marker);
+        }
+        state.save(&path).unwrap();
+
+        let image = c.render_resumable(World::ch7_default(), &path, 2).unwrap();
+        assert!(!path.exists());
+        assert_eq!(image.pixel_at(5, 0), marker);
+        assert_eq!(image.pixel_at(5, 5), marker);
+        assert_eq!(image.pixel_at(6, 5), expected.pixel_at(6, 5));
+        assert_eq!(image.pixel_at(3, 10), expected.pixel_at(3, 10));
+    }
+
+    #[test]
+    fn resumed_renders_match_uninterrupted_ones() {
+        let mut c = Camera::new(6, 6, PI / 2.0)
+            .with_integrator(Integrator::PathTracing)
+            .with_sampling(Sampler::Halton, 4);
+        c.set_transform(view_transform(
+            point(0.0, 0.0, -5.0),
+            point(0.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+        ));
+        let full = c.render(World::ch7_default());
+        let fingerprint = c.fingerprint(&World::ch7_default());
+        let path = std::env::temp_dir().join("resumed_renders_match.rtchk");
+
+        // interrupted after 3 rows
+        let mut state = Checkpoint::new(6, 6, fingerprint);
+        let done = c.render_buffer(World::ch7_default());
+        state.buffer_mut().pixels[..18].copy_from_slice(&done.pixels[..18]);
+        state.save(&path).unwrap();
+        let resumed = c.render_resumable(World::ch7_default(), &path, 2).unwrap();
+
+        // finished with 2 samples, then taken to 4
+        let mut state = Checkpoint::new(6, 6, fingerprint);
+        *state.buffer_mut() = c
+            .with_sampling(Sampler::Halton, 2)
+            .render_buffer(World::ch7_default());
+        state.save(&path).unwrap();
+        let extended = c.render_resumable(World::ch7_default(), &path, 2).unwrap();
+
+        for (x, y) in (0..6).flat_map(|y| (0..6).map(move |x| (x, y))) {
+            assert_eq!(resumed.pixel_at(x, y), full.pixel_at(x, y));
+            assert_eq!(extended.pixel_at(x, y), full.pixel_at(x, y));
+        }
+    }
+
+    #[test]
+    fn renders_that_cannot_be_resumed_are_refused() {
+        let c = Camera::new(4, 4, PI / 2.0).with_sampling(Sampler::Stratified, 4);
+        let path = std::env::temp_dir().join("renders_that_cannot_be_resumed.rtchk");
+        let mut state = Checkpoint::new(4, 4, c.fingerprint(&World::ch7_default()));
+        *state.buffer_mut() = c
+            .with_sampling(Sampler::Stratified, 2)
+            .render_buffer(World::ch7_default());
+        state.save(&path).unwrap();
+        // the stratified grid of 2 samples does not extend to 4
+        let err = c
+            .render_resumable(World::ch7_default(), &path, 1)
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        std::fs::remove_file(&path).unwrap();
+        for c in [
+            c.with_render_mode(RenderMode::Depth),
+            c.with_wireframe(Color::white()),
+        ] {
+            let err = c
+                .render_resumable(World::ch7_default(), &path, 1)
+                .unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn checkpoints_of_other_renders_are_refused() {
+        let c = Camera::new(4, 4, PI / 2.0);
+        let path = std::env::temp_dir().join("checkpoints_of_other_renders.rtchk");
+        let mut w = World::ch7_default();
+        Checkpoint::new(4, 4, c.fingerprint(&w))
+            .save(&path)
+            .unwrap();
+        let other = w.id_at(0);
+        w.get_mut(other).unwrap().material.color = Color::white();
+        let err = c.render_resumable(w, &path, 1).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        // more samples can be added to the same render
+        assert_eq!(
+            c.fingerprint(&World::ch7_default()),
+            c.with_sampling(c.sampler, 4)
+                .fingerprint(&World::ch7_default())
+        );
+    }
+
+    #[test]
+    fn path_traced_render_of_an_emissive_object() {
+        let mut w = World::new();
+        w.add(Shape::sphere().with_material(Material {
+            diffuse: 0.0,
+            specular: 0.0,
+            emissive: Color::new(0.2, 0.4, 0.6),
+            ..Default::default()
+        }));
+        let mut c = Camera::new(5, 5, PI / 8.0)
+            .with_sampling(Sampler::Random, 4)
+            .with_integrator(Integrator::PathTracing);
+        c.set_transform(view_transform(
+            point(0.0, 0.0, -3.0),
+            point(0.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+        ));
+        let image = c.render(w);
+        assert_eq!(image.pixel_at(2, 2), Color::new(0.2, 0.4, 0.6));
+    }
+
+    #[test]
+    fn renders_are_reproducible_for_a_given_seed() {
+        let render = |seed| {
+            let mut c = Camera::new(6, 6, PI / 2.0)
+                .with_sampling(Sampler::Random, 4)
+                .with_seed(seed);
+            c.set_transform(view_transform(
+                point(0.0, 0.0, -5.0),
+                point(0.0, 0.0, 0.0),
+                vector(0.0, 1.0, 0.0),
+            ));
+            c.render(World::ch7_default())
+        };
+        assert_eq!(render(1).pixels, render(1).pixels);
+        assert_ne!(render(1).pixels, render(2).pixels);
+    }
+
+    #[test]
+    fn render_buffer_counts_the_samples_of_each_pixel() {
+        let mut c = Camera::new(4, 4, PI / 2.0).with_sampling(Sampler::Stratified, 9);
+        c.set_transform(view_transform(
+            point(0.0, 0.0, -5.0),
+            point(0.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+        ));
+        let buffer = c.render_buffer(World::ch7_default());
+        assert_eq!(buffer.radiance_at(3, 1).samples(), 9);
+        assert_eq!(
+            buffer.to_canvas().pixel_at(2, 2),
+            c.render(World::ch7_default()).pixel_at(2, 2)
+        );
+    }
+
+    #[test]
+    fn rendering_auxiliary_buffers() {
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(view_transform(
+            point(0.0, 0.0, -5.0),
+            point(0.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+        ));
+        let (buffer, aovs) = c.render_with_aovs(World::ch7_default());
+        assert_eq!(buffer.pixel_at(5, 5), Color::new(0.38066, 0.47583, 0.2855));
+        let center = aovs.at(5, 5);
+        assert!(flt_eq(center.depth, 4.0));
+        assert_eq!(center.normal, Some(vector(0.0, 0.0, -1.0)));
+        assert_eq!(center.albedo, Color::new(0.8, 1.0, 0.6));
+        assert_eq!(center.object_id, Some(World::ch7_default().id_at(0)));
+        let corner = aovs.at(0, 0);
+        assert_eq!(corner.object_id, None);
+        assert!(corner.depth.is_infinite());
+    }
+
+    #[test]
+    fn object_ids_survive_removals() {
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(view_transform(
+            point(0.0, 0.0, -5.0),
+            point(0.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+        ));
+        let mut w = World::ch7_default();
+        let (outer, inner) = (w.id_at(0), w.id_at(1));
+        w.remove(outer);
+        let (_, aovs) = c.render_with_aovs(w);
+        // the inner sphere moved to the front of the objects
+        assert_eq!(aovs.at(5, 5).object_id, Some(inner));
+    }
+
+    #[test]
+    fn heatmap_shows_where_rays_bounce() {
+        let mut w = World::ch7_default();
+        w.get_mut(w.id_at(0)).unwrap().material = w.objects()[0].material.clone().reflective(0.5);
+        let mut c =
+            Camera::new(11, 11, PI / 2.0).with_render_mode(RenderMode::Heatmap(CostMetric::Rays));
+        c.set_transform(view_transform(
+            point(0.0, 0.0, -5.0),
+            point(0.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+        ));
+        let image = c.render(w);
+        // the corner ray misses everything, the center one is reflected
+        assert_eq!(image.pixel_at(0, 0), Color::new(0.0, 0.0, 1.0));
+        assert_eq!(image.pixel_at(5, 5), Color::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn diagnostic_modes_skip_lighting() {
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(view_transform(
+            point(0.0, 0.0, -5.0),
+            point(0.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+        ));
+        let normals = c
+            .with_render_mode(RenderMode::Normals)
+            .render(World::ch7_default());
+        assert_eq!(normals.pixel_at(5, 5), Color::new(0.5, 0.5, 0.0));
+        assert_eq!(normals.pixel_at(0, 0), Color::black());
+        let depth = c
+            .with_render_mode(RenderMode::Depth)
+            .render(World::ch7_default());
+        assert_eq!(depth.pixel_at(5, 5), Color::white());
+        let albedo = c
+            .with_render_mode(RenderMode::Albedo)
+            .render(World::ch7_default());
+        assert_eq!(albedo.pixel_at(5, 5), Color::new(0.8, 1.0, 0.6));
+    }
+
+    #[test]
+    fn wireframes_outline_the_triangles() {
+        let mut c = Camera::new(21, 21, PI / 2.0);
+        c.set_transform(view_transform(
+            point(0.0, 0.0, -1.0),
+            point(0.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+        ));
+        // a right triangle filling the lower left half of the view
+        let mut w = World::default();
+        w.add(Shape::triangle(
+            point(1.0, -1.0, 0.0),
+            point(-1.0, -1.0, 0.0),
+            point(-1.0, 1.0, 0.0),
+        ));
+        let wireframe = c
+            .with_render_mode(RenderMode::Albedo)
+            .with_wireframe(Color::new(1.0, 0.0, 0.0))
+            .render(w);
+        let red = Color::new(1.0, 0.0, 0.0);
+        // the diagonal and the borders of the image are edges
+        assert_eq!(wireframe.pixel_at(10, 10), red);
+        assert_eq!(wireframe.pixel_at(5, 5), red);
+        assert_eq!(wireframe.pixel_at(0, 10), red);
+        assert_eq!(wireframe.pixel_at(10, 20), red);
+        // the inside keeps its color, the outside stays empty
+        assert_eq!(wireframe.pixel_at(5, 15), Color::white());
+        assert_eq!(wireframe.pixel_at(15, 5), Color::black());
+    }
+
+    #[test]
+    fn near_clip_distance_cuts_away_the_front_of_the_scene() {
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(view_transform(
+            point(0.0, 0.0, -5.0),
+            point(0.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+        ));
+        let albedo = c.with_render_mode(RenderMode::Albedo);
+        let full = albedo.render(World::ch7_default());
+        let cut = albedo
+            .with_clip_distances(4.25, Float::INFINITY)
+            .render(World::ch7_default());
+        // the outer sphere is opened, the inner one is seen through the cut
+        assert_eq!(full.pixel_at(5, 5), Color::new(0.8, 1.0, 0.6));
+        assert_eq!(cut.pixel_at(5, 5), Color::white());
+        let far = albedo
+            .with_clip_distances(0.0, 3.0)
+            .render(World::ch7_default());
+        assert_eq!(far.pixel_at(5, 5), Color::black());
+    }
+
+    #[test]
+    fn missed_pixels_are_transparent() {
+        let mut c = Camera::new(11, 11, PI / 2.0).with_sampling(Sampler::Stratified, 4);
+        c.set_transform(view_transform(
+            point(0.0, 0.0, -5.0),
+            point(0.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+        ));
+        assert!(!c.render(World::ch7_default()).has_alpha());
+        let image = c.with_alpha(true).render(World::ch7_default());
+        assert_eq!(image.alpha_at(0, 0), 0.0);
+        assert_eq!(image.alpha_at(5, 5), 1.0);
+        let mut edge = (0..11).map(|x| image.alpha_at(x, 5));
+        assert!(edge.any(|a| a > 0.0 && a < 1.0));
+    }
+
+    #[test]
+    fn denoising_a_noisy_path_traced_render() {
+        let mut c = Camera::new(16, 16, PI / 3.0)
+            .with_sampling(Sampler::Random, 2)
+            .with_integrator(Integrator::PathTracing);
+        c.set_transform(view_transform(
+            point(0.0, 1.0, -5.0),
+            point(0.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+        ));
+        let noisy = c.render(World::ch7_default());
+        let clean = c.render_denoised(World::ch7_default(), &BilateralDenoiser::default());
+        assert_eq!(noisy.pixel_at(0, 0), clean.pixel_at(0, 0));
+        assert_ne!(noisy.pixels, clean.pixels);
+    }
+
+    #[test]
+    fn supersampling_a_flat_colored_object() {
+        let mut w = World::ch7_default();
+        w.get_mut(w.id_at(0)).unwrap().material = Material {
+            color: Color::new(0.2, 0.4, 0.6),
+            ambient: 1.0,
+            diffuse: 0.0,
+            specular: 0.0,
+            ..Default::default()
+        };
+        let mut c = Camera::new(5, 5, PI / 8.0).with_sampling(Sampler::Halton, 4);
+        c.set_transform(view_transform(
+            point(0.0, 0.0, -3.0),
+            point(0.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+        ));
+        let image = c.render(w);
+        assert_eq!(image.pixel_at(2, 2), Color::new(0.2, 0.4, 0.6));
+        assert_eq!(image.pixel_at(0, 4), Color::new(0.2, 0.4, 0.6));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn cameras_round_trip_through_serde() {
+        let c = Camera::new(160, 120, PI / 3.0)
+            .with_sampling(Sampler::Halton, 4)
+            .with_render_mode(RenderMode::Heatmap(CostMetric::Rays));
+        let json = serde_json::to_string(&c).unwrap();
+        assert!(!json.contains("far"), "{json}");
+        let back: Camera = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.far, Float::INFINITY);
+        assert_eq!(serde_json::to_string(&back).unwrap(), json);
+    }
+
+    #[test]
+    fn changing_the_resolution_keeps_the_view() {
+        let mut c = Camera::new(200, 100, PI / 2.0);
+        c.set_transform(rot_y(PI / 4.0) * translation(0.0, -2.0, 5.0));
+        let preview = c.with_resolution(50, 25);
+        assert_eq!((preview.hsize(), preview.vsize()), (50, 25));
+        let (a, b) = (
+            preview.ray_for_pixel(25, 12),
+            c.ray_for_sample(102, 50, 0.0, 0.0),
+        );
+        assert_eq!(a.origin, b.origin);
+        assert_eq!(a.direction, b.direction);
+    }
 }
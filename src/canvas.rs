@@ -1,12 +1,341 @@
-use std::io::{BufWriter, Write};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
 
-use crate::color::Color;
+use crate::{color::Color, util::Float};
 
-#[derive(Debug, Clone)]
+/// Shared exponent encoding of a color, negative channels are dropped.
+fn to_rgbe(c: Color) -> [u8; 4] {
+    let (r, g, b) = (c.r().max(0.0), c.g().max(0.0), c.b().max(0.0));
+    let v = r.max(g).max(b);
+    if v < 1e-32 {
+        return [0; 4];
+    }
+    // v = m * 2^e with m in [0.5, 1)
+    let mut e = v.log2().floor() as i32 + 1;
+    if v / Float::powi(2.0, e) >= 1.0 {
+        e += 1;
+    }
+    let scale = 256.0 / Float::powi(2.0, e);
+    [
+        (r * scale) as u8,
+        (g * scale) as u8,
+        (b * scale) as u8,
+        (e + 128) as u8,
+    ]
+}
+
+/// Quality of the JPEG files written by [`Canvas::save`].
+#[cfg(feature = "jpeg")]
+const JPEG_QUALITY: u8 = 90;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads a P3 or P6 PPM image.
+fn parse_ppm(data: &[u8]) -> io::Result<Canvas> {
+    let mut pos = 2;
+    // next whitespace separated token, skipping comments
+    let mut token = || -> io::Result<usize> {
+        loop {
+            match data.get(pos) {
+                Some(b'#') => {
+                    while data.get(pos).is_some_and(|&c| c != b'\n') {
+                        pos += 1;
+                    }
+                }
+                Some(c) if c.is_ascii_whitespace() => pos += 1,
+                Some(_) => break,
+                None => return Err(invalid("truncated PPM file")),
+            }
+        }
+        let start = pos;
+        while data.get(pos).is_some_and(|c| !c.is_ascii_whitespace()) {
+            pos += 1;
+        }
+        std::str::from_utf8(&data[start..pos])
+            .ok()
+            .and_then(|t| t.parse().ok())
+            .ok_or_else(|| invalid("bad number in PPM file"))
+    };
+    let width = token()?;
+    let height = token()?;
+    let max = token()?;
+    if max == 0 || max > u16::MAX as usize {
+        return Err(invalid("bad maximum value in PPM file"));
+    }
+    let count = width
+        .checked_mul(height)
+        .and_then(|n| n.checked_mul(3))
+        .ok_or_else(|| invalid("PPM image too large"))?;
+    let values = if &data[..2] == b"P3" {
+        (0..count)
+            .map(|_| token())
+            .collect::<io::Result<Vec<_>>>()?
+    } else {
+        // a single whitespace separates the header from the samples
+        let start = pos + 1;
+        let size = if max < 256 { 1 } else { 2 };
+        let end = count
+            .checked_mul(size)
+            .and_then(|n| n.checked_add(start))
+            .ok_or_else(|| invalid("PPM image too large"))?;
+        let samples = data
+            .get(start..end)
+            .ok_or_else(|| invalid("truncated PPM file"))?;
+        samples
+            .chunks(size)
+            .map(|s| s.iter().fold(0, |v, &b| v << 8 | b as usize))
+            .collect()
+    };
+    if values.iter().any(|&v| v > max) {
+        return Err(invalid("sample above the maximum value in PPM file"));
+    }
+    let scale = 1.0 / max as Float;
+    let pixels = values
+        .chunks(3)
+        .map(|c| {
+            Color::new(
+                c[0] as Float * scale,
+                c[1] as Float * scale,
+                c[2] as Float * scale,
+            )
+        })
+        .collect();
+    Ok(Canvas::new_with_colors(width, height, pixels))
+}
+
+/// Reads a PNG image, of any color type and bit depth.
+#[cfg(feature = "png")]
+fn decode_png(data: &[u8]) -> io::Result<Canvas> {
+    let mut decoder = png::Decoder::new(data);
+    // palettes and low bit depths are expanded to 8 bits, 16 bits are kept
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(io::Error::other)?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(io::Error::other)?;
+    let channels = info.color_type.samples();
+    let data = &buffer[..info.buffer_size()];
+    let values: Vec<Float> = if info.bit_depth == png::BitDepth::Sixteen {
+        data.chunks(2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]) as Float / 65535.0)
+            .collect()
+    } else {
+        data.iter().map(|&b| b as Float / 255.0).collect()
+    };
+    let pixels = values
+        .chunks(channels)
+        .map(|p| match channels {
+            1 | 2 => Color::new(p[0], p[0], p[0]),
+            _ => Color::new(p[0], p[1], p[2]),
+        })
+        .collect();
+    let canvas = Canvas::new_with_colors(info.width as usize, info.height as usize, pixels);
+    Ok(match channels {
+        2 | 4 => canvas.with_alpha(values.chunks(channels).map(|p| p[channels - 1]).collect()),
+        _ => canvas,
+    })
+}
+
+/// Longest line allowed in a PPM file.
+const MAX_PPM_LINE: usize = 70;
+
+/// Appends the samples of a row to a plain PPM, wrapping long lines.
+fn push_ppm_row(ppm: &mut String, row: &[u8]) {
+    let mut line_len = 0;
+    for value in row {
+        let value = value.to_string();
+        if line_len > 0 && line_len + 1 + value.len() > MAX_PPM_LINE {
+            ppm.push('\n');
+            line_len = 0;
+        }
+        if line_len > 0 {
+            ppm.push(' ');
+            line_len += 1;
+        }
+        ppm.push_str(&value);
+        line_len += value.len();
+    }
+    ppm.push('\n');
+}
+
+/// 8-bit value of a channel in `[0, 1]`.
+fn to_byte(v: Float) -> u8 {
+    (v * 255.0).round() as u8
+}
+
+/// 16-bit value of a channel in `[0, 1]`, as big endian bytes.
+fn to_word(v: Float) -> [u8; 2] {
+    ((v * 65535.0).round() as u16).to_be_bytes()
+}
+
+/// How [`Canvas::resize`] computes the new pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+    /// Copies the closest source pixel, keeps hard edges.
+    Nearest,
+    /// Blends the four closest source pixels.
+    #[default]
+    Bilinear,
+}
+
+/// Resamples a `width` by `height` grid of values to `new_width` by
+/// `new_height`, lining up the pixel centers.
+fn resample<T: Copy>(
+    values: &[T],
+    (width, height): (usize, usize),
+    (new_width, new_height): (usize, usize),
+    filter: Filter,
+    lerp: impl Fn(T, T, Float) -> T,
+) -> Vec<T> {
+    // source coordinate of the center of destination pixel `i`
+    let source =
+        |i: usize, new: usize, old: usize| (i as Float + 0.5) * old as Float / new as Float;
+    let at = |x: usize, y: usize| values[x + y * width];
+    let mut out = Vec::with_capacity(new_width * new_height);
+    for y in 0..new_height {
+        let sy = source(y, new_height, height);
+        for x in 0..new_width {
+            let sx = source(x, new_width, width);
+            out.push(match filter {
+                Filter::Nearest => at((sx as usize).min(width - 1), (sy as usize).min(height - 1)),
+                Filter::Bilinear => {
+                    let fx = (sx - 0.5).clamp(0.0, (width - 1) as Float);
+                    let fy = (sy - 0.5).clamp(0.0, (height - 1) as Float);
+                    let (x0, y0) = (fx as usize, fy as usize);
+                    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+                    let (tx, ty) = (fx - x0 as Float, fy - y0 as Float);
+                    lerp(
+                        lerp(at(x0, y0), at(x1, y0), tx),
+                        lerp(at(x0, y1), at(x1, y1), tx),
+                        ty,
+                    )
+                }
+            });
+        }
+    }
+    out
+}
+
+/// Rectangle of pixels, its top left corner at `(x, y)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// Curve applied to the linear colors when they are quantized to 8 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransferFunction {
+    /// Values are written as they are.
+    #[default]
+    Linear,
+    /// `v^(1 / gamma)`, usually with a gamma of 2.2.
+    Gamma(Float),
+    /// The piecewise sRGB curve, what most image viewers expect.
+    Srgb,
+}
+
+impl TransferFunction {
+    /// Encoded value of the linear value `v`, clamped to `[0, 1]`.
+    pub fn encode(&self, v: Float) -> Float {
+        let v = v.clamp(0.0, 1.0);
+        match self {
+            TransferFunction::Linear => v,
+            TransferFunction::Gamma(gamma) => v.powf(1.0 / gamma),
+            TransferFunction::Srgb if v <= 0.0031308 => v * 12.92,
+            TransferFunction::Srgb => 1.055 * v.powf(1.0 / 2.4) - 0.055,
+        }
+    }
+
+    /// Linear value of the encoded value `v`, inverse of
+    /// [`TransferFunction::encode`].
+    pub fn decode(&self, v: Float) -> Float {
+        let v = v.clamp(0.0, 1.0);
+        match self {
+            TransferFunction::Linear => v,
+            TransferFunction::Gamma(gamma) => v.powf(*gamma),
+            TransferFunction::Srgb if v <= 0.04045 => v / 12.92,
+            TransferFunction::Srgb => ((v + 0.055) / 1.055).powf(2.4),
+        }
+    }
+}
+
+/// Noise added to the colors before they are rounded to 8 bits, trading
+/// the banding of dark gradients for fine grain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Dither {
+    #[default]
+    None,
+    /// 4x4 Bayer matrix, a regular pattern.
+    Ordered,
+    /// Interleaved gradient noise, irregular with most of its energy in high
+    /// frequencies like blue noise.
+    Noise,
+}
+
+/// 4x4 Bayer matrix, thresholds out of 16.
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+impl Dither {
+    /// Offset in `[-0.5, 0.5)`, in 8-bit steps, added at pixel `(x, y)`.
+    pub fn offset(&self, x: usize, y: usize) -> Float {
+        match self {
+            Dither::None => 0.0,
+            Dither::Ordered => (BAYER[y % 4][x % 4] as Float + 0.5) / 16.0 - 0.5,
+            Dither::Noise => {
+                let f = (0.06711056 * x as f64 + 0.00583715 * y as f64).fract();
+                ((52.9829189 * f).fract() - 0.5) as Float
+            }
+        }
+    }
+}
+
+/// 8-bit RGB of the color of pixel `(x, y)`.
+fn quantize(
+    c: Color,
+    transfer: TransferFunction,
+    dither: Dither,
+    (x, y): (usize, usize),
+) -> [u8; 3] {
+    let offset = dither.offset(x, y);
+    let byte = |v: Float| {
+        (transfer.encode(v) * 255.0 + offset)
+            .round()
+            .clamp(0.0, 255.0) as u8
+    };
+    [byte(c.r()), byte(c.g()), byte(c.b())]
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Canvas {
     width: usize,
     height: usize,
     pub pixels: Vec<Color>,
+    /// Encoding of the 8-bit formats.
+    transfer: TransferFunction,
+    dither: Dither,
+    /// Opacity of every pixel, fully opaque when missing.
+    alpha: Option<Vec<Float>>,
 }
 
 impl Canvas {
@@ -15,6 +344,9 @@ impl Canvas {
             width,
             height,
             pixels: vec![Color::default(); width * height],
+            transfer: TransferFunction::default(),
+            dither: Dither::default(),
+            alpha: None,
         }
     }
 
@@ -23,6 +355,119 @@ impl Canvas {
             width,
             height,
             pixels: colors,
+            transfer: TransferFunction::default(),
+            dither: Dither::default(),
+            alpha: None,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Linear colors of the pixels, row by row.
+    pub fn as_slice(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    /// Pixels as 8-bit RGBA, row by row, the colors encoded with the transfer
+    /// function of the canvas. Ready to upload to most texture and window
+    /// APIs.
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        self.to_rgb8()
+            .chunks(3)
+            .enumerate()
+            .flat_map(|(i, rgb)| [rgb[0], rgb[1], rgb[2], to_byte(self.alpha_of(i))])
+            .collect()
+    }
+
+    /// Pixels as linear, unclamped `f32` RGBA, row by row.
+    // the cast does nothing with the `f32` feature
+    #[allow(clippy::unnecessary_cast)]
+    pub fn to_rgba_f32(&self) -> Vec<f32> {
+        self.pixels
+            .iter()
+            .enumerate()
+            .flat_map(|(i, c)| [c.r(), c.g(), c.b(), self.alpha_of(i)].map(|v| v as f32))
+            .collect()
+    }
+
+    fn alpha_of(&self, index: usize) -> Float {
+        self.alpha
+            .as_ref()
+            .map_or(1.0, |a| a[index].clamp(0.0, 1.0))
+    }
+
+    /// Sets the curve the colors go through when saved to an 8-bit format.
+    pub fn with_transfer(mut self, transfer: TransferFunction) -> Self {
+        self.transfer = transfer;
+        self
+    }
+
+    pub fn set_transfer(&mut self, transfer: TransferFunction) {
+        self.transfer = transfer;
+    }
+
+    pub fn transfer(&self) -> TransferFunction {
+        self.transfer
+    }
+
+    /// Sets the dithering applied when the colors are rounded to 8 bits.
+    pub fn with_dither(mut self, dither: Dither) -> Self {
+        self.dither = dither;
+        self
+    }
+
+    pub fn set_dither(&mut self, dither: Dither) {
+        self.dither = dither;
+    }
+
+    pub fn dither(&self) -> Dither {
+        self.dither
+    }
+
+    /// Adds an alpha channel, one value in `[0, 1]` per pixel. Only the PNG
+    /// output keeps it.
+    pub fn with_alpha(mut self, alpha: Vec<Float>) -> Self {
+        assert_eq!(alpha.len(), self.pixels.len());
+        self.alpha = Some(alpha);
+        self
+    }
+
+    pub fn has_alpha(&self) -> bool {
+        self.alpha.is_some()
+    }
+
+    pub fn alpha_at(&self, x: usize, y: usize) -> Float {
+        assert!(x < self.width);
+        assert!(y < self.height);
+        self.alpha_of(self.to_xy(x, y))
+    }
+
+    /// Copy of the canvas scaled to `width` by `height`, alpha included.
+    pub fn resize(&self, width: usize, height: usize, filter: Filter) -> Self {
+        assert!(
+            self.width > 0 && self.height > 0,
+            "cannot resize an empty canvas"
+        );
+        let from = (self.width, self.height);
+        let to = (width, height);
+        Self {
+            width,
+            height,
+            pixels: resample(&self.pixels, from, to, filter, |a, b, t| {
+                a * (1.0 - t) + b * t
+            }),
+            transfer: self.transfer,
+            dither: self.dither,
+            alpha: self
+                .alpha
+                .as_ref()
+                .map(|alpha| resample(alpha, from, to, filter, |a, b, t| a * (1.0 - t) + b * t)),
         }
     }
 
@@ -37,12 +482,90 @@ impl Canvas {
         self.pixels[coords] = color
     }
 
+    /// Copy of the canvas built by picking, for every pixel of a `width` by
+    /// `height` canvas, the index of the source pixel.
+    fn remap(&self, width: usize, height: usize, source: impl Fn(usize, usize) -> usize) -> Self {
+        let indices: Vec<usize> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| source(x, y))
+            .collect();
+        Self {
+            width,
+            height,
+            pixels: indices.iter().map(|&i| self.pixels[i]).collect(),
+            transfer: self.transfer,
+            dither: self.dither,
+            alpha: self
+                .alpha
+                .as_ref()
+                .map(|alpha| indices.iter().map(|&i| alpha[i]).collect()),
+        }
+    }
+
+    /// Copy of the canvas upside down, for images drawn with y pointing up.
+    pub fn flip_vertical(&self) -> Self {
+        self.remap(self.width, self.height, |x, y| {
+            self.to_xy(x, self.height - 1 - y)
+        })
+    }
+
+    /// Copy of the canvas turned a quarter turn clockwise.
+    pub fn rotate90(&self) -> Self {
+        self.remap(self.height, self.width, |x, y| {
+            self.to_xy(y, self.height - 1 - x)
+        })
+    }
+
+    /// Copy of the part of the canvas inside `rect`.
+    pub fn crop(&self, rect: Rect) -> Self {
+        assert!(rect.x + rect.width <= self.width);
+        assert!(rect.y + rect.height <= self.height);
+        self.remap(rect.width, rect.height, |x, y| {
+            self.to_xy(rect.x + x, rect.y + y)
+        })
+    }
+
+    /// Sets every pixel to `color`.
+    pub fn fill(&mut self, color: Color) {
+        self.pixels.fill(color);
+    }
+
+    /// Resets the canvas to opaque black.
+    pub fn clear(&mut self) {
+        self.fill(Color::black());
+        self.alpha = None;
+    }
+
+    /// Copies `src` with its top left corner at `(x, y)`, the part falling
+    /// outside the canvas is dropped. Alpha is copied too.
+    pub fn blit(&mut self, src: &Canvas, x: usize, y: usize) {
+        let columns = src.width.min(self.width.saturating_sub(x));
+        let rows = src.height.min(self.height.saturating_sub(y));
+        if columns == 0 || rows == 0 {
+            return;
+        }
+        if src.alpha.is_some() && self.alpha.is_none() {
+            self.alpha = Some(vec![1.0; self.pixels.len()]);
+        }
+        for row in 0..rows {
+            let from = row * src.width;
+            let to = self.to_xy(x, y + row);
+            self.pixels[to..to + columns].copy_from_slice(&src.pixels[from..from + columns]);
+            if let Some(alpha) = &mut self.alpha {
+                match &src.alpha {
+                    Some(a) => alpha[to..to + columns].copy_from_slice(&a[from..from + columns]),
+                    None => alpha[to..to + columns].fill(1.0),
+                }
+            }
+        }
+    }
+
     pub fn write_full_row_unchecked(&mut self, row: usize, data: &[Color]) {
         assert!(row < self.height);
         self.pixels.copy_from_slice(data);
     }
 
-    pub fn write_pixel_f(&mut self, x: f64, y: f64, color: Color) {
+    pub fn write_pixel_f(&mut self, x: Float, y: Float, color: Color) {
         assert!((x as usize) < self.width);
         assert!((y as usize) < self.height);
         let coords = self.to_xy(x as usize, y as usize);
@@ -55,39 +578,297 @@ impl Canvas {
         self.pixels[self.to_xy(x, y)]
     }
 
-    pub fn save_ppm(&self, filename: &str) {
-        let image = std::fs::File::create(filename).expect("wtf");
-        let mut image = BufWriter::new(image);
-        image.write("P3\n".as_bytes()).unwrap();
-        image
-            .write(format!("{} {}\n", self.width, self.height).as_bytes())
-            .unwrap();
-        image.write("255\n".as_bytes()).unwrap();
-
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let c = self.pixel_at(x, y);
-                image
-                    .write(
-                        format!(
-                            "{} {} {}\n",
-                            (c.r() * 255.0).floor(),
-                            (c.g() * 255.0).floor(),
-                            (c.b() * 255.0).floor()
-                        )
-                        .as_bytes(),
-                    )
-                    .unwrap();
+    /// Pixels as 8-bit RGB triplets, row by row, encoded with the transfer
+    /// function and dithering of the canvas.
+    fn to_rgb8(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .enumerate()
+            .flat_map(|(i, c)| {
+                let xy = (i % self.width, i / self.width);
+                quantize(*c, self.transfer, self.dither, xy)
+            })
+            .collect()
+    }
+
+    /// Big endian 16-bit samples, with the alpha channel if asked.
+    fn to_rgb16(&self, alpha: bool) -> Vec<u8> {
+        let word = |v: Float| to_word(self.transfer.encode(v));
+        self.pixels
+            .iter()
+            .enumerate()
+            .flat_map(|(i, c)| {
+                let a = alpha.then(|| to_word(self.alpha_of(i)));
+                [word(c.r()), word(c.g()), word(c.b())]
+                    .into_iter()
+                    .chain(a)
+                    .flatten()
+            })
+            .collect()
+    }
+
+    /// Writes the image as an 8-bit PNG file, RGBA when the canvas has an
+    /// alpha channel and RGB otherwise.
+    #[cfg(feature = "png")]
+    pub fn save_png(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let out = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(out, self.width as u32, self.height as u32);
+        let data = if self.has_alpha() {
+            encoder.set_color(png::ColorType::Rgba);
+            self.to_rgba8()
+        } else {
+            encoder.set_color(png::ColorType::Rgb);
+            self.to_rgb8()
+        };
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer.write_image_data(&data).map_err(io::Error::other)?;
+        writer.finish().map_err(io::Error::other)
+    }
+
+    /// Writes the image as a 16-bit PNG file, so smooth gradients keep
+    /// their precision when graded later.
+    #[cfg(feature = "png")]
+    pub fn save_png16(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let out = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(out, self.width as u32, self.height as u32);
+        encoder.set_color(if self.has_alpha() {
+            png::ColorType::Rgba
+        } else {
+            png::ColorType::Rgb
+        });
+        encoder.set_depth(png::BitDepth::Sixteen);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer
+            .write_image_data(&self.to_rgb16(self.has_alpha()))
+            .map_err(io::Error::other)?;
+        writer.finish().map_err(io::Error::other)
+    }
+
+    /// Writes the image as a binary PPM file with a maximum value of 65535.
+    pub fn save_ppm16(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        write!(out, "P6\n{} {}\n65535\n", self.width, self.height)?;
+        out.write_all(&self.to_rgb16(false))?;
+        out.flush()
+    }
+
+    /// Writes the image as a binary (P6) PPM file, much smaller and faster to
+    /// read than the plain text one.
+    pub fn save_ppm_binary(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        write!(out, "P6\n{} {}\n255\n", self.width, self.height)?;
+        out.write_all(&self.to_rgb8())?;
+        out.flush()
+    }
+
+    /// Loads a PPM (plain or binary) or, with the `png` feature, a PNG file.
+    /// The format is detected from the content of the file.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let data = std::fs::read(path)?;
+        match data.get(..2) {
+            Some(b"P3" | b"P6") => parse_ppm(&data),
+            #[cfg(feature = "png")]
+            Some(b"\x89P") => decode_png(&data),
+            _ => Err(invalid("unsupported image format")),
+        }
+    }
+
+    /// Saves the image in the format given by the extension of `path`: `ppm`
+    /// (binary), `bmp`, `hdr`, `png` and `jpg`/`jpeg` when the matching
+    /// features are enabled.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("ppm") => self.save_ppm_binary(path),
+            Some("bmp") => self.save_bmp(path),
+            Some("hdr") => self.save_hdr(path),
+            #[cfg(feature = "png")]
+            Some("png") => self.save_png(path),
+            #[cfg(feature = "jpeg")]
+            Some("jpg" | "jpeg") => self.save_jpeg(path, JPEG_QUALITY),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("no encoder for {}", path.display()),
+            )),
+        }
+    }
+
+    /// Writes the image as an uncompressed 24-bit BMP file.
+    pub fn save_bmp(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        // rows are stored bottom up, padded to a multiple of 4 bytes
+        let row_size = (self.width * 3).div_ceil(4) * 4;
+        let image_size = row_size * self.height;
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(b"BM")?;
+        out.write_all(&((54 + image_size) as u32).to_le_bytes())?;
+        out.write_all(&0u32.to_le_bytes())?;
+        out.write_all(&54u32.to_le_bytes())?;
+        out.write_all(&40u32.to_le_bytes())?;
+        out.write_all(&(self.width as i32).to_le_bytes())?;
+        out.write_all(&(self.height as i32).to_le_bytes())?;
+        out.write_all(&1u16.to_le_bytes())?;
+        out.write_all(&24u16.to_le_bytes())?;
+        out.write_all(&0u32.to_le_bytes())?;
+        out.write_all(&(image_size as u32).to_le_bytes())?;
+        // 72 dpi
+        out.write_all(&2835u32.to_le_bytes())?;
+        out.write_all(&2835u32.to_le_bytes())?;
+        out.write_all(&[0; 8])?;
+        let rgb = self.to_rgb8();
+        let mut row = vec![0u8; row_size];
+        for line in rgb.chunks(self.width * 3).rev() {
+            for (bgr, px) in row.chunks_mut(3).zip(line.chunks(3)) {
+                bgr.copy_from_slice(&[px[2], px[1], px[0]]);
             }
+            out.write_all(&row)?;
+        }
+        out.flush()
+    }
+
+    /// Writes the image as a JPEG file, `quality` going from 1 to 100.
+    #[cfg(feature = "jpeg")]
+    pub fn save_jpeg(&self, path: impl AsRef<Path>, quality: u8) -> io::Result<()> {
+        let too_big = || io::Error::new(io::ErrorKind::InvalidInput, "image too big for JPEG");
+        let width = u16::try_from(self.width).map_err(|_| too_big())?;
+        let height = u16::try_from(self.height).map_err(|_| too_big())?;
+        jpeg_encoder::Encoder::new_file(path, quality)
+            .and_then(|e| e.encode(&self.to_rgb8(), width, height, jpeg_encoder::ColorType::Rgb))
+            .map_err(io::Error::other)
+    }
+
+    /// Writes the unclamped colors to a Radiance HDR (RGBE) file, keeping
+    /// the highlights an 8-bit format would clip, for grading and tone
+    /// mapping in other tools.
+    pub fn save_hdr(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        write!(
+            out,
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+            self.height, self.width
+        )?;
+        for c in &self.pixels {
+            out.write_all(&to_rgbe(*c))?;
+        }
+        out.flush()
+    }
+
+    /// Plain text (P3) PPM encoding of the image. Colors are clamped and
+    /// rounded to 8 bits, lines are wrapped to stay within 70 characters.
+    pub fn to_ppm(&self) -> String {
+        let mut ppm = format!("P3\n{} {}\n255\n", self.width, self.height);
+        for row in self.to_rgb8().chunks(self.width * 3) {
+            push_ppm_row(&mut ppm, row);
+        }
+        ppm
+    }
+
+    /// Saves the canvas as a plain text (P3) PPM file.
+    pub fn save_ppm(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_ppm())
+    }
+}
+
+/// Writes a PPM image row by row, as the rows are rendered, so the whole
+/// image never has to be held in memory.
+pub struct PpmWriter<W: Write> {
+    out: W,
+    width: usize,
+    height: usize,
+    rows: usize,
+    binary: bool,
+    transfer: TransferFunction,
+    dither: Dither,
+}
+
+impl PpmWriter<BufWriter<File>> {
+    /// Creates the plain (P3) PPM file at `path`.
+    pub fn create(path: impl AsRef<Path>, width: usize, height: usize) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?), width, height)
+    }
+}
+
+impl<W: Write> PpmWriter<W> {
+    /// Plain (P3) PPM writer, the header is written right away.
+    pub fn new(out: W, width: usize, height: usize) -> io::Result<Self> {
+        Self::with_format(out, width, height, false)
+    }
+
+    /// Binary (P6) PPM writer, a third of the size of the plain format.
+    pub fn new_binary(out: W, width: usize, height: usize) -> io::Result<Self> {
+        Self::with_format(out, width, height, true)
+    }
+
+    fn with_format(mut out: W, width: usize, height: usize, binary: bool) -> io::Result<Self> {
+        let magic = if binary { "P6" } else { "P3" };
+        write!(out, "{magic}\n{width} {height}\n255\n")?;
+        Ok(Self {
+            out,
+            width,
+            height,
+            rows: 0,
+            binary,
+            transfer: TransferFunction::default(),
+            dither: Dither::default(),
+        })
+    }
+
+    /// Sets the curve the colors go through before being quantized.
+    pub fn with_transfer(mut self, transfer: TransferFunction) -> Self {
+        self.transfer = transfer;
+        self
+    }
+
+    /// Sets the dithering applied when the colors are rounded.
+    pub fn with_dither(mut self, dither: Dither) -> Self {
+        self.dither = dither;
+        self
+    }
+
+    /// Writes the next row, top to bottom.
+    pub fn write_row(&mut self, row: &[Color]) -> io::Result<()> {
+        assert_eq!(row.len(), self.width);
+        if self.rows == self.height {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "all the rows of the PPM image are written",
+            ));
+        }
+        let bytes: Vec<u8> = row
+            .iter()
+            .enumerate()
+            .flat_map(|(x, c)| quantize(*c, self.transfer, self.dither, (x, self.rows)))
+            .collect();
+        if self.binary {
+            self.out.write_all(&bytes)?;
+        } else {
+            let mut line = String::new();
+            push_ppm_row(&mut line, &bytes);
+            self.out.write_all(line.as_bytes())?;
         }
-        image.write("\n".as_bytes()).unwrap();
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Checks every row was written and flushes the output.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.rows < self.height {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "missing rows in the PPM image",
+            ));
+        }
+        self.out.flush()?;
+        Ok(self.out)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use std::f64::consts::PI;
-
     use crate::{
         color::Color,
         intersection::Intersectable,
@@ -97,9 +878,10 @@ mod test {
         object::Shape,
         ray::Ray,
         tuple::{point, vector},
+        util::{consts::PI, Float},
     };
 
-    use super::Canvas;
+    use super::{parse_ppm, Canvas, Dither, Filter, PpmWriter, Rect, TransferFunction};
 
     #[test]
     fn test_coords() {
@@ -109,7 +891,235 @@ mod test {
         dbg!(&canvas);
         assert!(canvas.pixel_at(0, 0) == red);
 
-        canvas.save_ppm("place_pixel.ppm");
+        canvas.save_ppm("place_pixel.ppm").unwrap();
+    }
+
+    #[test]
+    fn constructing_the_ppm_header() {
+        let c = Canvas::new(5, 3);
+        let ppm = c.to_ppm();
+        assert_eq!(
+            ppm.lines().take(3).collect::<Vec<_>>(),
+            ["P3", "5 3", "255"]
+        );
+    }
+
+    #[test]
+    fn constructing_the_ppm_pixel_data() {
+        let mut c = Canvas::new(5, 3);
+        c.write_pixel(0, 0, Color::new(1.5, 0.0, 0.0));
+        c.write_pixel(2, 1, Color::new(0.0, 0.5, 0.0));
+        c.write_pixel(4, 2, Color::new(-0.5, 0.0, 1.0));
+        let ppm = c.to_ppm();
+        assert_eq!(
+            ppm.lines().skip(3).collect::<Vec<_>>(),
+            [
+                "255 0 0 0 0 0 0 0 0 0 0 0 0 0 0",
+                "0 0 0 0 0 0 0 128 0 0 0 0 0 0 0",
+                "0 0 0 0 0 0 0 0 0 0 0 0 0 0 255",
+            ]
+        );
+    }
+
+    #[test]
+    fn splitting_long_lines_in_ppm_files() {
+        let c = Canvas::new_with_colors(10, 2, vec![Color::new(1.0, 0.8, 0.6); 20]);
+        let ppm = c.to_ppm();
+        assert_eq!(
+            ppm.lines().skip(3).collect::<Vec<_>>(),
+            [
+                "255 204 153 255 204 153 255 204 153 255 204 153 255 204 153 255 204",
+                "153 255 204 153 255 204 153 255 204 153 255 204 153",
+                "255 204 153 255 204 153 255 204 153 255 204 153 255 204 153 255 204",
+                "153 255 204 153 255 204 153 255 204 153 255 204 153",
+            ]
+        );
+    }
+
+    #[test]
+    fn ppm_files_are_terminated_by_a_newline() {
+        assert!(Canvas::new(5, 3).to_ppm().ends_with('\n'));
+    }
+
+    #[test]
+    fn saving_an_hdr_file_keeps_bright_values() {
+        let c = Canvas::new_with_colors(
+            2,
+            1,
+            vec![Color::new(1.0, 0.5, 0.25), Color::new(6.0, 0.0, -1.0)],
+        );
+        let path = std::env::temp_dir().join("saving_an_hdr_file.hdr");
+        c.save_hdr(&path).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut expected = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n".to_vec();
+        // 6 = 0.75 * 2^3
+        expected.extend([128, 64, 32, 129, 192, 0, 0, 131]);
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn saving_a_bmp() {
+        let mut c = Canvas::new(2, 2);
+        c.write_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+        c.write_pixel(1, 1, Color::new(0.0, 0.0, 1.0));
+        let path = std::env::temp_dir().join("saving_a_bmp.bmp");
+        c.save(&path).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        // two rows of 6 bytes padded to 8, bottom row first, in BGR order
+        assert_eq!(data.len(), 54 + 16);
+        assert_eq!(&data[0..2], b"BM");
+        assert_eq!(&data[54..62], &[0, 0, 0, 255, 0, 0, 0, 0]);
+        assert_eq!(&data[62..70], &[0, 0, 255, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn saving_to_an_unknown_format_fails() {
+        let err = Canvas::new(1, 1).save("image.xyz").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+    }
+
+    #[cfg(feature = "jpeg")]
+    #[test]
+    fn saving_a_jpeg() {
+        let path = std::env::temp_dir().join("saving_a_jpeg.jpg");
+        Canvas::new(8, 8).save(&path).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&data[0..2], &[0xFF, 0xD8]);
+    }
+
+    #[test]
+    fn reading_a_plain_ppm_with_comments() {
+        let path = std::env::temp_dir().join("reading_a_plain_ppm.ppm");
+        std::fs::write(
+            &path,
+            "P3\n# a comment\n2 1\n# another\n100\n100 0 50  0 0 100\n",
+        )
+        .unwrap();
+        let c = Canvas::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((c.width, c.height), (2, 1));
+        assert_eq!(c.pixel_at(0, 0), Color::new(1.0, 0.0, 0.5));
+        assert_eq!(c.pixel_at(1, 0), Color::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn binary_ppm_round_trip() {
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(2, 1, Color::new(1.0, 0.2, 0.6));
+        let path = std::env::temp_dir().join("binary_ppm_round_trip.ppm");
+        c.save_ppm_binary(&path).unwrap();
+        let loaded = Canvas::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.pixels, c.pixels);
+    }
+
+    #[test]
+    fn reading_a_truncated_ppm_fails() {
+        let path = std::env::temp_dir().join("reading_a_truncated_ppm.ppm");
+        std::fs::write(&path, "P3\n2 2\n255\n0 0 0\n").unwrap();
+        let err = Canvas::from_file(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn reading_a_ppm_with_a_bad_header_fails() {
+        let huge = format!("P6\n{} {}\n255\n", usize::MAX / 2, 3);
+        let above_max = "P3\n1 1\n100\n100 101 0\n".to_string();
+        for ppm in [huge, above_max] {
+            let err = parse_ppm(ppm.as_bytes()).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_round_trip() {
+        let mut c = Canvas::new(2, 2);
+        c.write_pixel(1, 0, Color::new(1.0, 0.2, 0.6));
+        let path = std::env::temp_dir().join("png_round_trip.png");
+        c.save_png(&path).unwrap();
+        let loaded = Canvas::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.pixels, c.pixels);
+    }
+
+    #[test]
+    fn transfer_functions_brighten_mid_tones() {
+        let c = Canvas::new_with_colors(1, 1, vec![Color::new(0.5, 0.002, 1.0)]);
+        assert!(c.to_ppm().ends_with("128 1 255\n"));
+        let srgb = c.clone().with_transfer(TransferFunction::Srgb);
+        assert!(srgb.to_ppm().ends_with("188 7 255\n"));
+        let gamma = c.with_transfer(TransferFunction::Gamma(2.2));
+        assert!(gamma.to_ppm().ends_with("186 15 255\n"));
+    }
+
+    #[test]
+    fn srgb_decoding_inverts_encoding() {
+        for v in [0.0, 0.001, 0.2, 0.5, 1.0] {
+            let t = TransferFunction::Srgb;
+            assert!((t.decode(t.encode(v)) - v).abs() < 8.0 * Float::EPSILON);
+        }
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_keeps_the_alpha_channel() {
+        let c = Canvas::new(2, 1).with_alpha(vec![0.0, 1.0]);
+        let path = std::env::temp_dir().join("png_keeps_the_alpha_channel.png");
+        c.save_png(&path).unwrap();
+        let loaded = Canvas::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.alpha_at(0, 0), 0.0);
+        assert_eq!(loaded.alpha_at(1, 0), 1.0);
+    }
+
+    #[test]
+    fn raw_pixel_buffers() {
+        let c = Canvas::new_with_colors(2, 1, vec![Color::new(1.0, 0.5, 2.0), Color::black()])
+            .with_alpha(vec![1.0, 0.0]);
+        assert_eq!((c.width(), c.height()), (2, 1));
+        assert_eq!(c.as_slice()[0], Color::new(1.0, 0.5, 2.0));
+        assert_eq!(c.to_rgba8(), vec![255, 128, 255, 255, 0, 0, 0, 0]);
+        assert_eq!(
+            c.to_rgba_f32(),
+            vec![1.0, 0.5, 2.0, 1.0, 0.0, 0.0, 0.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn saving_a_binary_ppm() {
+        let mut canvas = Canvas::new(2, 1);
+        canvas.write_pixel(1, 0, Color::new(1.0, 0.5, 0.0));
+        let path = std::env::temp_dir().join("saving_a_binary_ppm.ppm");
+        canvas.save_ppm_binary(&path).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut expected = b"P6\n2 1\n255\n".to_vec();
+        expected.extend([0, 0, 0, 255, 128, 0]);
+        assert_eq!(data, expected);
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn saving_a_png() {
+        let mut canvas = Canvas::new(3, 2);
+        canvas.write_pixel(0, 0, Color::new(1.5, 0.0, 0.0));
+        canvas.write_pixel(2, 1, Color::new(0.0, 0.5, -0.5));
+        let path = std::env::temp_dir().join("saving_a_png.png");
+        canvas.save_png(&path).unwrap();
+
+        let decoder = png::Decoder::new(std::fs::File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((info.width, info.height), (3, 2));
+        assert_eq!(&data[0..3], &[255, 0, 0]);
+        assert_eq!(&data[15..18], &[0, 128, 0]);
     }
 
     #[test]
@@ -126,9 +1136,9 @@ mod test {
             }
             velocity += wind + gravity;
             current += velocity;
-            canvas.write_pixel_f(current.x, 550.0 - current.y, Color::new(1.0, 0.0, 0.0));
+            canvas.write_pixel_f(current.x, current.y, Color::new(1.0, 0.0, 0.0));
         }
-        canvas.save_ppm("curves.ppm");
+        canvas.flip_vertical().save_ppm("curves.ppm").unwrap();
     }
 
     #[test]
@@ -138,28 +1148,32 @@ mod test {
         let mut canvas = Canvas::new(100, 100);
 
         for i in 0..12 {
-            let t = Mat4::identity().rot_y(PI / 6.0 * i as f64);
+            let t = Mat4::identity().rot_y(PI / 6.0 * i as Float);
 
             let ptw = t * (twelve);
             let scaling = 30.0;
-            let ptw =
-                ptw * scaling + point((canvas.width / 2) as f64, 0.0, (canvas.height / 2) as f64);
+            let ptw = ptw * scaling
+                + point(
+                    (canvas.width / 2) as Float,
+                    0.0,
+                    (canvas.height / 2) as Float,
+                );
             canvas.write_pixel_f(ptw.x, ptw.z, Color::new(1.0, 1.0, 0.0));
         }
         canvas.write_pixel_f(
-            center.x + (canvas.width / 2) as f64,
-            center.z + (canvas.height / 2) as f64,
+            center.x + (canvas.width / 2) as Float,
+            center.z + (canvas.height / 2) as Float,
             Color::new(1.0, 1.0, 0.0),
         );
-        canvas.save_ppm("clock.ppm");
+        canvas.save_ppm("clock.ppm").unwrap();
     }
 
     #[test]
     fn test_raycast_sphere() {
-        let nb_pixels = 50f64;
+        let nb_pixels: Float = 50.0;
         let mut canvas = Canvas::new(nb_pixels as usize, nb_pixels as usize);
         let ray_origin = point(0.0, 0.0, -5.0);
-        let wall_size = 7f64;
+        let wall_size: Float = 7.0;
         let wall_z = 10.0;
         let pixel_size = wall_size / nb_pixels;
         let mut s = Shape::sphere();
@@ -171,9 +1185,9 @@ mod test {
         // let red = Color::new(1.0, 0.0, 0.0);
         // s.set_transform(Mat4::identity().shearing(1.0, 0.0, 0.0, 0.0, 0.0, 0.0));
         for x in 0..canvas.width {
-            let world_x = -wall_size / 2.0 + pixel_size * x as f64;
+            let world_x = -wall_size / 2.0 + pixel_size * x as Float;
             for y in 0..canvas.height {
-                let world_y = wall_size / 2.0 - pixel_size * y as f64;
+                let world_y = wall_size / 2.0 - pixel_size * y as Float;
                 let target_position = point(world_x, world_y, wall_z);
 
                 let r = Ray::new(ray_origin, (target_position - ray_origin).norm());
@@ -187,7 +1201,7 @@ mod test {
                             y,
                             h.object.material.lighting(
                                 light,
-                                Shape::sphere(),
+                                &Shape::sphere(),
                                 p,
                                 eye,
                                 normal,
@@ -199,6 +1213,156 @@ mod test {
                 }
             }
         }
-        canvas.save_ppm("ray_sphere.ppm");
+        canvas.save_ppm("ray_sphere.ppm").unwrap();
+    }
+
+    #[test]
+    fn nearest_resize_repeats_pixels() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(1, 0, red);
+        let big = c.resize(4, 2, Filter::Nearest);
+        assert_eq!((big.width(), big.height()), (4, 2));
+        assert_eq!(big.pixel_at(1, 1), Color::black());
+        assert_eq!(big.pixel_at(2, 1), red);
+        let small = big.resize(2, 1, Filter::Nearest);
+        assert_eq!(small.as_slice(), c.as_slice());
+    }
+
+    #[test]
+    fn bilinear_resize_blends_neighbours() {
+        let c = Canvas::new_with_colors(2, 1, vec![Color::black(), Color::white()])
+            .with_alpha(vec![0.0, 1.0]);
+        let big = c.resize(4, 1, Filter::Bilinear);
+        assert_eq!(big.pixel_at(0, 0), Color::black());
+        assert_eq!(big.pixel_at(1, 0), Color::new(0.25, 0.25, 0.25));
+        assert_eq!(big.pixel_at(2, 0), Color::new(0.75, 0.75, 0.75));
+        assert_eq!(big.pixel_at(3, 0), Color::white());
+        assert_eq!(big.alpha_at(1, 0), 0.25);
+        let small = c.resize(1, 1, Filter::Bilinear);
+        assert_eq!(small.pixel_at(0, 0), Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn streamed_ppm_matches_the_canvas() {
+        let mut c = Canvas::new(20, 3);
+        c.write_pixel(3, 1, Color::new(0.5, 1.0, 0.25));
+        let mut w = PpmWriter::new(Vec::new(), 20, 3).unwrap();
+        for row in c.as_slice().chunks(20) {
+            w.write_row(row).unwrap();
+        }
+        assert!(w.write_row(&[Color::black(); 20]).is_err());
+        let ppm = w.finish().unwrap();
+        assert_eq!(String::from_utf8(ppm).unwrap(), c.to_ppm());
+    }
+
+    #[test]
+    fn streamed_binary_ppm_needs_every_row() {
+        let mut w = PpmWriter::new_binary(Vec::new(), 1, 2).unwrap();
+        w.write_row(&[Color::white()]).unwrap();
+        assert!(w.finish().is_err());
+        let mut w = PpmWriter::new_binary(Vec::new(), 1, 1).unwrap();
+        w.write_row(&[Color::white()]).unwrap();
+        assert_eq!(w.finish().unwrap(), b"P6\n1 1\n255\n\xff\xff\xff");
+    }
+
+    #[test]
+    fn filling_and_clearing() {
+        let mut c = Canvas::new(2, 2).with_alpha(vec![0.0; 4]);
+        c.fill(Color::white());
+        assert!(c.as_slice().iter().all(|p| *p == Color::white()));
+        c.clear();
+        assert!(c.as_slice().iter().all(|p| *p == Color::black()));
+        assert!(!c.has_alpha());
+    }
+
+    #[test]
+    fn blitting_a_tile() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let mut tile = Canvas::new(2, 2).with_alpha(vec![0.5; 4]);
+        tile.fill(red);
+        let mut c = Canvas::new(3, 3);
+        c.blit(&tile, 2, 1);
+        assert_eq!(c.pixel_at(2, 1), red);
+        assert_eq!(c.pixel_at(2, 2), red);
+        assert_eq!(c.pixel_at(1, 1), Color::black());
+        assert_eq!(c.alpha_at(2, 2), 0.5);
+        assert_eq!(c.alpha_at(0, 0), 1.0);
+        c.blit(&tile, 5, 5);
+    }
+
+    #[test]
+    fn sixteen_bit_ppm_keeps_fine_gradients() {
+        let c = Canvas::new_with_colors(
+            2,
+            1,
+            vec![Color::new(1000.0 / 65535.0, 0.0, 1.0), Color::black()],
+        );
+        let path = std::env::temp_dir().join("ray_tracer_16bit.ppm");
+        c.save_ppm16(&path).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        let loaded = Canvas::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(data.starts_with(b"P6\n2 1\n65535\n"));
+        assert_eq!(data.len(), 13 + 2 * 3 * 2);
+        assert_eq!(loaded.pixel_at(0, 0), c.pixel_at(0, 0));
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn sixteen_bit_png_round_trip() {
+        let c = Canvas::new_with_colors(1, 1, vec![Color::new(1000.0 / 65535.0, 0.25, 1.0)])
+            .with_alpha(vec![0.5]);
+        let path = std::env::temp_dir().join("ray_tracer_16bit.png");
+        c.save_png16(&path).unwrap();
+        let loaded = Canvas::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.pixel_at(0, 0), c.pixel_at(0, 0));
+        assert!((loaded.alpha_at(0, 0) - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn flipping_rotating_and_cropping() {
+        // 1 2 3
+        // 4 5 6
+        let grey = |v: Float| Color::new(v, v, v);
+        let c = Canvas::new_with_colors(3, 2, (1..=6).map(|v| grey(v as Float)).collect())
+            .with_alpha((1..=6).map(|v| v as Float / 10.0).collect());
+        let flipped = c.flip_vertical();
+        assert_eq!(flipped.pixel_at(0, 0), grey(4.0));
+        assert_eq!(flipped.pixel_at(2, 1), grey(3.0));
+        let rotated = c.rotate90();
+        assert_eq!((rotated.width(), rotated.height()), (2, 3));
+        // 4 1
+        // 5 2
+        // 6 3
+        assert_eq!(rotated.pixel_at(0, 0), grey(4.0));
+        assert_eq!(rotated.pixel_at(1, 2), grey(3.0));
+        assert_eq!(rotated.alpha_at(0, 2), 0.6);
+        let cropped = c.crop(Rect::new(1, 0, 2, 2));
+        assert_eq!(
+            cropped.as_slice(),
+            &[grey(2.0), grey(3.0), grey(5.0), grey(6.0)]
+        );
+    }
+
+    #[test]
+    fn dithering_breaks_up_bands() {
+        // halfway between two 8-bit steps
+        let c =
+            Canvas::new_with_colors(4, 4, vec![Color::new(10.5, 0.0, 255.0) * (1.0 / 255.0); 16]);
+        let plain = c.to_rgba8();
+        assert!(plain.chunks(4).all(|p| p[..3] == [11, 0, 255]));
+        for dither in [Dither::Ordered, Dither::Noise] {
+            let rgba = c.clone().with_dither(dither).to_rgba8();
+            let reds: Vec<u8> = rgba.chunks(4).map(|p| p[0]).collect();
+            assert!(reds.contains(&10) && reds.contains(&11));
+            // black and white stay exact
+            assert!(rgba.chunks(4).all(|p| p[1] == 0 && p[2] == 255));
+        }
+        let mean: Float = (0..4)
+            .flat_map(|y| (0..4).map(move |x| Dither::Ordered.offset(x, y)))
+            .sum();
+        assert!(mean.abs() < 1e-12);
     }
 }
@@ -0,0 +1,245 @@
+use std::{
+    fmt::{self, Debug, Write as _},
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use crate::{
+    canvas::Canvas,
+    color::Color,
+    render_buffer::{Radiance, RenderBuffer},
+    util::Float,
+};
+
+const MAGIC: &[u8; 6] = b"RTCHK2";
+/// Magic, width, height and fingerprint.
+const HEADER_LEN: u64 = 6 + 3 * 8;
+/// Sum of the samples, sample count and covered count.
+const PIXEL_LEN: u64 = 3 * 8 + 2 * 4;
+
+/// State of a partially completed render: the samples accumulated so far in
+/// every pixel, so that an interrupted render can pick up where it stopped,
+/// down to the pixel, or go on with more samples.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    /// Identifies the scene and camera the samples were taken of.
+    fingerprint: u64,
+    buffer: RenderBuffer,
+}
+
+impl Checkpoint {
+    pub fn new(width: usize, height: usize, fingerprint: u64) -> Self {
+        Self {
+            fingerprint,
+            buffer: RenderBuffer::new(width, height),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.buffer.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.buffer.height()
+    }
+
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    pub fn buffer(&self) -> &RenderBuffer {
+        &self.buffer
+    }
+
+    pub fn buffer_mut(&mut self) -> &mut RenderBuffer {
+        &mut self.buffer
+    }
+
+    /// Rows holding a pixel with fewer than `samples` samples.
+    pub fn missing_rows(&self, samples: usize) -> Vec<usize> {
+        (0..self.height())
+            .filter(|&y| {
+                (0..self.width())
+                    .any(|x| (self.buffer.radiance_at(x, y).samples() as usize) < samples)
+            })
+            .collect()
+    }
+
+    /// Whether every pixel has at least `samples` samples.
+    pub fn is_complete(&self, samples: usize) -> bool {
+        self.missing_rows(samples).is_empty()
+    }
+
+    /// Builds the image from the samples taken so far, pixels without any
+    /// are left black.
+    pub fn to_canvas(&self) -> Canvas {
+        self.buffer.to_canvas()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        // write to a temporary file first so a crash while saving does not
+        // destroy the previous checkpoint
+        let path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+        let mut out = BufWriter::new(File::create(&tmp)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&(self.width() as u64).to_le_bytes())?;
+        out.write_all(&(self.height() as u64).to_le_bytes())?;
+        out.write_all(&self.fingerprint.to_le_bytes())?;
+        for p in &self.buffer.pixels {
+            let sum = p.sum();
+            for v in [sum.r(), sum.g(), sum.b()] {
+                write_f64(&mut out, v)?;
+            }
+            out.write_all(&p.samples().to_le_bytes())?;
+            out.write_all(&p.covered().to_le_bytes())?;
+        }
+        out.flush()?;
+        drop(out);
+        std::fs::rename(tmp, path)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let mut input = BufReader::new(file);
+        let mut magic = [0u8; 6];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a render checkpoint"));
+        }
+        let width = read_u64(&mut input)?;
+        let height = read_u64(&mut input)?;
+        let fingerprint = read_u64(&mut input)?;
+        // nothing is allocated before the size is known to match the file
+        let expected = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(PIXEL_LEN))
+            .and_then(|n| n.checked_add(HEADER_LEN));
+        if expected != Some(len) {
+            return Err(invalid("checkpoint size does not match its header"));
+        }
+        let mut checkpoint = Self::new(width as usize, height as usize, fingerprint);
+        for p in checkpoint.buffer.pixels.iter_mut() {
+            let r = read_f64(&mut input)?;
+            let g = read_f64(&mut input)?;
+            let b = read_f64(&mut input)?;
+            let samples = read_u32(&mut input)?;
+            let covered = read_u32(&mut input)?;
+            if covered > samples {
+                return Err(invalid("more covered samples than samples"));
+            }
+            *p = Radiance::from_parts(Color::new(r, g, b), samples, covered);
+        }
+        Ok(checkpoint)
+    }
+}
+
+/// Hash of the debug output of `parts`, stable from one run to the next, to
+/// tell whether a checkpoint belongs to a render.
+pub fn fingerprint(parts: &[&dyn Debug]) -> u64 {
+    // FNV-1a
+    struct Hasher(u64);
+    impl fmt::Write for Hasher {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            for b in s.bytes() {
+                self.0 = (self.0 ^ b as u64).wrapping_mul(0x100_0000_01b3);
+            }
+            Ok(())
+        }
+    }
+    let mut h = Hasher(0xcbf2_9ce4_8422_2325);
+    for part in parts {
+        // writing to the hasher can't fail
+        let _ = write!(h, "{part:?};");
+    }
+    h.0
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_u64(input: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    input.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    input.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+/// Values are stored as `f64` whatever the precision of [`Float`].
+#[allow(clippy::unnecessary_cast)]
+fn write_f64(out: &mut impl Write, v: Float) -> io::Result<()> {
+    out.write_all(&(v as f64).to_le_bytes())
+}
+
+fn read_f64(input: &mut impl Read) -> io::Result<Float> {
+    let mut buf = [0u8; 8];
+    input.read_exact(&mut buf)?;
+    Ok(f64::from_le_bytes(buf) as Float)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkpoint_round_trip() {
+        let mut c = Checkpoint::new(2, 3, 42);
+        c.buffer_mut().add_sample(0, 1, Color::new(0.1, 0.2, 0.3));
+        c.buffer_mut().add_sample(1, 1, Color::new(1.5, 0.0, -0.25));
+        c.buffer_mut().add_sample(1, 1, Color::new(0.5, 0.0, 0.25));
+        c.buffer_mut().add_sample(0, 2, Color::white());
+        let path = std::env::temp_dir().join("checkpoint_round_trip.rtchk");
+        c.save(&path).unwrap();
+        let loaded = Checkpoint::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, c);
+        assert_eq!(loaded.fingerprint(), 42);
+        assert_eq!(loaded.buffer().radiance_at(1, 1).samples(), 2);
+        assert_eq!(loaded.missing_rows(1), vec![0, 2]);
+        assert_eq!(loaded.missing_rows(2), vec![0, 1, 2]);
+        assert!(!loaded.is_complete(1));
+    }
+
+    #[test]
+    fn loading_garbage_fails() {
+        let path = std::env::temp_dir().join("loading_garbage_fails.rtchk");
+        std::fs::write(&path, "P3\n1 1\n255\n").unwrap();
+        let err = Checkpoint::load(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn headers_are_checked_against_the_file_length() {
+        let path = std::env::temp_dir().join("headers_are_checked.rtchk");
+        Checkpoint::new(4, 4, 0).save(&path).unwrap();
+        let mut bytes = std::fs::read(&path).unwrap();
+        // a huge height, as a corrupt file may hold
+        bytes[14..22].copy_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        let err = Checkpoint::load(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        // a truncated file
+        Checkpoint::new(4, 4, 0).save(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        let err = Checkpoint::load(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn fingerprints_tell_renders_apart() {
+        let a = fingerprint(&[&1.0, &"sphere"]);
+        assert_eq!(a, fingerprint(&[&1.0, &"sphere"]));
+        assert_ne!(a, fingerprint(&[&1.5, &"sphere"]));
+    }
+}
@@ -0,0 +1,64 @@
+use crate::{color::Color, ray::Ray, tuple::Tuple, util::Float};
+
+/// Plane cutting away all the geometry on the side its normal points to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClipPlane {
+    pub point: Tuple,
+    pub normal: Tuple,
+    /// Flat color drawn where the plane cuts through a closed object. Without
+    /// it the object is left open.
+    pub cap: Option<Color>,
+}
+
+impl ClipPlane {
+    pub fn new(point: Tuple, normal: Tuple) -> Self {
+        Self {
+            point,
+            normal: normal.norm(),
+            cap: None,
+        }
+    }
+
+    pub fn with_cap(mut self, color: Color) -> Self {
+        self.cap = Some(color);
+        self
+    }
+
+    /// Whether `p` is cut away by this plane.
+    pub fn clips(&self, p: Tuple) -> bool {
+        ((p - self.point) ^ self.normal) > 0.0
+    }
+
+    /// Time at which `r` crosses the plane, if it is not parallel to it.
+    pub fn crossing(&self, r: Ray) -> Option<Float> {
+        let along = r.direction ^ self.normal;
+        if along.abs() < Float::EPSILON {
+            return None;
+        }
+        Some(((self.point - r.origin) ^ self.normal) / along)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tuple::{point, vector};
+
+    use super::*;
+
+    #[test]
+    fn clipping_keeps_the_back_side() {
+        let p = ClipPlane::new(point(0.0, 1.0, 0.0), vector(0.0, 2.0, 0.0));
+        assert!(p.clips(point(3.0, 1.5, 0.0)));
+        assert!(!p.clips(point(3.0, 0.5, 0.0)));
+    }
+
+    #[test]
+    fn crossing_a_clip_plane() {
+        let p = ClipPlane::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, -1.0));
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(p.crossing(r), Some(5.0));
+        let parallel = Ray::new(point(0.0, 0.0, -5.0), vector(1.0, 0.0, 0.0));
+        assert_eq!(p.crossing(parallel), None);
+    }
+}
@@ -1,9 +1,29 @@
 use std::ops::{Add, Mul, Sub};
 
-use crate::tuple::{vector, Tuple};
+use crate::{
+    tuple::{vector, Tuple},
+    util::Float,
+};
 #[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "[Float; 3]", into = "[Float; 3]")
+)]
 pub struct Color(Tuple);
 
+impl From<[Float; 3]> for Color {
+    fn from([r, g, b]: [Float; 3]) -> Self {
+        Color::new(r, g, b)
+    }
+}
+
+impl From<Color> for [Float; 3] {
+    fn from(c: Color) -> Self {
+        [c.r(), c.g(), c.b()]
+    }
+}
+
 impl PartialEq for Color {
     fn eq(&self, other: &Self) -> bool {
         self.0 == other.0
@@ -11,25 +31,30 @@ impl PartialEq for Color {
 }
 
 impl Color {
-    pub const fn new(r: f64, g: f64, b: f64) -> Self {
+    pub const fn new(r: Float, g: Float, b: Float) -> Self {
         Self(vector(r, g, b))
     }
 
     #[inline]
-    pub fn r(&self) -> f64 {
+    pub fn r(&self) -> Float {
         self.0.x
     }
 
     #[inline]
-    pub fn g(&self) -> f64 {
+    pub fn g(&self) -> Float {
         self.0.y
     }
 
     #[inline]
-    pub fn b(&self) -> f64 {
+    pub fn b(&self) -> Float {
         self.0.z
     }
 
+    /// Average of the channels, the brightness masks and maps go by.
+    pub fn mean(&self) -> Float {
+        (self.r() + self.g() + self.b()) / 3.0
+    }
+
     pub const fn black() -> Color {
         Self::new(0.0, 0.0, 0.0)
     }
@@ -54,9 +79,9 @@ impl Sub for Color {
     }
 }
 
-impl Mul<f64> for Color {
+impl Mul<Float> for Color {
     type Output = Self;
-    fn mul(self, rhs: f64) -> Self::Output {
+    fn mul(self, rhs: Float) -> Self::Output {
         Self(self.0 * rhs)
     }
 }
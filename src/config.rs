@@ -0,0 +1,123 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{camera::Camera, canvas::TransferFunction, sampler::Sampler, util::Float};
+
+/// Renderer defaults read from a `raytracer.toml`, so that they need not be
+/// given on every invocation:
+///
+/// ```toml
+/// output_dir = "renders"
+/// threads = 4
+/// samples = 16
+/// gamma = 2.2
+/// ```
+///
+/// Every field is optional. Command line flags are parsed into a `Config`
+/// too, and take precedence through [`Config::or`].
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Directory relative output paths are written to.
+    pub output_dir: Option<PathBuf>,
+    /// Number of render threads, all cores when left out.
+    pub threads: Option<usize>,
+    /// Camera rays per pixel, stratified.
+    pub samples: Option<usize>,
+    /// Gamma the colors are encoded with when saved.
+    pub gamma: Option<Float>,
+}
+
+impl Config {
+    /// Name of the file looked for in the working directory.
+    pub const FILE_NAME: &'static str = "raytracer.toml";
+
+    pub fn parse(source: &str) -> io::Result<Self> {
+        toml::from_str(source).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Reads the configuration at `path`, naming the file in errors.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        fs::read_to_string(path)
+            .and_then(|source| Self::parse(&source))
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))
+    }
+
+    /// The configuration of the working directory, the defaults if it has
+    /// none.
+    pub fn find() -> io::Result<Self> {
+        match Self::load(Self::FILE_NAME) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            config => config,
+        }
+    }
+
+    /// Fields of `self`, with the ones it leaves out taken from `fallback`.
+    pub fn or(self, fallback: Config) -> Config {
+        Config {
+            output_dir: self.output_dir.or(fallback.output_dir),
+            threads: self.threads.or(fallback.threads),
+            samples: self.samples.or(fallback.samples),
+            gamma: self.gamma.or(fallback.gamma),
+        }
+    }
+
+    /// Where an output named `path` goes.
+    pub fn output(&self, path: impl AsRef<Path>) -> PathBuf {
+        match &self.output_dir {
+            Some(dir) => dir.join(path),
+            None => path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// `camera` taking the configured number of samples.
+    pub fn camera(&self, camera: Camera) -> Camera {
+        match self.samples {
+            Some(n) if n > 1 => camera.with_sampling(Sampler::Stratified, n),
+            _ => camera,
+        }
+    }
+
+    pub fn transfer(&self) -> TransferFunction {
+        match self.gamma {
+            Some(gamma) => TransferFunction::Gamma(gamma),
+            None => TransferFunction::Linear,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_left_out_are_none() {
+        let config = Config::parse("samples = 4\ngamma = 2.2\n").unwrap();
+        assert_eq!(config.samples, Some(4));
+        assert_eq!(config.gamma, Some(2.2));
+        assert_eq!(config.threads, None);
+        assert_eq!(config.output("a.png"), Path::new("a.png"));
+    }
+
+    #[test]
+    fn unknown_fields_are_errors() {
+        let e = Config::parse("sample = 4").unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert!(e.to_string().contains("sample"), "{e}");
+    }
+
+    #[test]
+    fn flags_take_precedence_over_the_file() {
+        let file = Config::parse("output_dir = \"renders\"\nsamples = 16\n").unwrap();
+        let flags = Config {
+            samples: Some(4),
+            ..Config::default()
+        };
+        let config = flags.or(file);
+        assert_eq!(config.samples, Some(4));
+        assert_eq!(config.output("a.png"), Path::new("renders").join("a.png"));
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{
+    aov::{AovSample, Aovs},
+    color::Color,
+    render_buffer::RenderBuffer,
+    util::Float,
+};
+
+/// Post-process run on the linear radiance of a render, before it is turned
+/// into an image. The auxiliary buffers tell apart real edges from noise.
+///
+/// Implement it to plug an external denoiser in.
+pub trait Denoiser {
+    fn denoise(&self, buffer: &RenderBuffer, aovs: &Aovs) -> RenderBuffer;
+}
+
+/// Joint bilateral filter: averages neighbouring pixels, less and less as
+/// their color, normal or albedo get different from the filtered pixel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BilateralDenoiser {
+    /// Half size of the filtered window, in pixels.
+    pub radius: usize,
+    pub sigma_spatial: Float,
+    pub sigma_color: Float,
+    pub sigma_normal: Float,
+    pub sigma_albedo: Float,
+}
+
+impl Default for BilateralDenoiser {
+    fn default() -> Self {
+        Self {
+            radius: 3,
+            sigma_spatial: 2.0,
+            sigma_color: 0.3,
+            sigma_normal: 0.1,
+            sigma_albedo: 0.1,
+        }
+    }
+}
+
+fn distance2(a: Color, b: Color) -> Float {
+    let d = a - b;
+    d.r() * d.r() + d.g() * d.g() + d.b() * d.b()
+}
+
+fn gaussian(d2: Float, sigma: Float) -> Float {
+    Float::exp(-d2 / (2.0 * sigma * sigma))
+}
+
+impl BilateralDenoiser {
+    fn weight(
+        &self,
+        d2: Float,
+        (c, a): (Color, &AovSample),
+        (nc, na): (Color, &AovSample),
+    ) -> Float {
+        let normal = match (a.normal, na.normal) {
+            (Some(n), Some(nn)) => gaussian(1.0 - (n ^ nn), self.sigma_normal),
+            (None, None) => 1.0,
+            _ => 0.0,
+        };
+        gaussian(d2, self.sigma_spatial)
+            * gaussian(distance2(c, nc), self.sigma_color)
+            * gaussian(distance2(a.albedo, na.albedo), self.sigma_albedo)
+            * normal
+    }
+}
+
+impl Denoiser for BilateralDenoiser {
+    fn denoise(&self, buffer: &RenderBuffer, aovs: &Aovs) -> RenderBuffer {
+        let (width, height) = (buffer.width(), buffer.height());
+        assert_eq!(aovs.width(), width);
+        assert_eq!(aovs.height(), height);
+        let r = self.radius as isize;
+        let mut out = buffer.clone();
+        for y in 0..height {
+            for x in 0..width {
+                if buffer.radiance_at(x, y).samples() == 0 {
+                    continue;
+                }
+                let center = (buffer.pixel_at(x, y), &aovs.pixels[x + y * width]);
+                let mut sum = Color::black();
+                let mut total = 0.0;
+                for dy in -r..=r {
+                    for dx in -r..=r {
+                        let (nx, ny) = (x as isize + dx, y as isize + dy);
+                        if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize {
+                            continue;
+                        }
+                        let (nx, ny) = (nx as usize, ny as usize);
+                        // unsampled pixels are black for lack of samples
+                        if buffer.radiance_at(nx, ny).samples() == 0 {
+                            continue;
+                        }
+                        let c = buffer.pixel_at(nx, ny);
+                        let d2 = (dx * dx + dy * dy) as Float;
+                        let w = self.weight(d2, center, (c, &aovs.pixels[nx + ny * width]));
+                        sum = sum + c * w;
+                        total += w;
+                    }
+                }
+                // the center pixel always weighs 1, total can't be 0
+                out.pixels[x + y * width].set_mean(sum * (1.0 / total));
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tuple::vector;
+
+    use super::*;
+
+    fn flat_aovs(width: usize, height: usize) -> Aovs {
+        let mut aovs = Aovs::new(width, height);
+        for a in aovs.pixels.iter_mut() {
+            a.normal = Some(vector(0.0, 0.0, -1.0));
+            a.albedo = Color::white();
+        }
+        aovs
+    }
+
+    #[test]
+    fn smooths_noise_on_a_flat_surface() {
+        let mut buffer = RenderBuffer::new(5, 5);
+        for y in 0..5 {
+            for x in 0..5 {
+                let v = if (x + y) % 2 == 0 { 0.45 } else { 0.55 };
+                buffer.add_sample(x, y, Color::new(v, v, v));
+            }
+        }
+        let out = BilateralDenoiser::default().denoise(&buffer, &flat_aovs(5, 5));
+        let c = out.pixel_at(2, 2);
+        assert!((c.r() - 0.5).abs() < 0.02, "{:?}", c);
+        assert_eq!(out.radiance_at(2, 2).samples(), 1);
+    }
+
+    #[test]
+    fn keeps_edges_between_different_normals() {
+        let mut buffer = RenderBuffer::new(4, 1);
+        let mut aovs = flat_aovs(4, 1);
+        for x in 0..4 {
+            let v = if x < 2 { 0.0 } else { 1.0 };
+            buffer.add_sample(x, 0, Color::new(v, v, v));
+        }
+        aovs.pixels[2].normal = Some(vector(1.0, 0.0, 0.0));
+        aovs.pixels[3].normal = Some(vector(1.0, 0.0, 0.0));
+        let out = BilateralDenoiser::default().denoise(&buffer, &aovs);
+        assert!(out.pixel_at(1, 0).r() < 0.001);
+        assert!(out.pixel_at(2, 0).r() > 0.999);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{canvas::Canvas, color::Color, util::Float};
+
+/// Differences between two canvases, as returned by [`Canvas::diff`].
+/// Canvases of different sizes differ everywhere.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffReport {
+    width: usize,
+    height: usize,
+    /// Size of the canvas compared against.
+    other_size: (usize, usize),
+    /// Absolute difference of every channel, per pixel.
+    errors: Vec<Color>,
+    /// Largest channel difference.
+    pub max_error: Float,
+    /// Channel difference averaged over all the channels of all the pixels.
+    pub mean_error: Float,
+}
+
+impl DiffReport {
+    /// Whether both canvases have the same width and height.
+    pub fn same_size(&self) -> bool {
+        (self.width, self.height) == self.other_size
+    }
+
+    /// Pixels where a channel differs by more than `tolerance`.
+    pub fn mismatched_pixels(&self, tolerance: Float) -> usize {
+        if !self.same_size() {
+            return self.errors.len();
+        }
+        self.errors
+            .iter()
+            .filter(|e| e.r().max(e.g()).max(e.b()) > tolerance)
+            .count()
+    }
+
+    /// Whether no channel differs by more than `tolerance`.
+    pub fn within(&self, tolerance: Float) -> bool {
+        self.same_size() && self.max_error <= tolerance
+    }
+
+    /// Image of the absolute differences, black where the canvases match and
+    /// white everywhere when their sizes differ.
+    pub fn difference_image(&self) -> Canvas {
+        Canvas::new_with_colors(self.width, self.height, self.errors.clone())
+    }
+}
+
+impl Canvas {
+    /// Compares the linear colors with the ones of `other`, typically a
+    /// reference render. A reference of another size is reported as
+    /// different everywhere, with infinite errors.
+    pub fn diff(&self, other: &Canvas) -> DiffReport {
+        let other_size = (other.width(), other.height());
+        if (self.width(), self.height()) != other_size {
+            return DiffReport {
+                width: self.width(),
+                height: self.height(),
+                other_size,
+                errors: vec![Color::white(); self.width() * self.height()],
+                max_error: Float::INFINITY,
+                mean_error: Float::INFINITY,
+            };
+        }
+        let errors: Vec<Color> = self
+            .as_slice()
+            .iter()
+            .zip(other.as_slice())
+            .map(|(a, b)| {
+                let d = *a - *b;
+                Color::new(d.r().abs(), d.g().abs(), d.b().abs())
+            })
+            .collect();
+        let channels = errors.iter().flat_map(|e| [e.r(), e.g(), e.b()]);
+        let max_error = channels.clone().fold(0.0, Float::max);
+        let mean_error = if errors.is_empty() {
+            0.0
+        } else {
+            channels.sum::<Float>() / (errors.len() * 3) as Float
+        };
+        DiffReport {
+            width: self.width(),
+            height: self.height(),
+            other_size,
+            errors,
+            max_error,
+            mean_error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_canvases_have_no_difference() {
+        let c = Canvas::new_with_colors(2, 1, vec![Color::white(), Color::new(0.2, 0.4, 0.6)]);
+        let report = c.diff(&c.clone());
+        assert_eq!(report.max_error, 0.0);
+        assert!(report.within(0.0));
+        assert_eq!(report.mismatched_pixels(0.0), 0);
+    }
+
+    #[test]
+    fn reporting_differences() {
+        let a = Canvas::new_with_colors(2, 1, vec![Color::black(), Color::black()]);
+        let b = Canvas::new_with_colors(2, 1, vec![Color::new(0.0, 0.6, 0.0), Color::black()]);
+        let report = a.diff(&b);
+        assert_eq!(report.max_error, 0.6);
+        assert!((report.mean_error - 0.1).abs() < 1e-12);
+        assert!(!report.within(0.5));
+        assert_eq!(report.mismatched_pixels(0.5), 1);
+        assert_eq!(report.mismatched_pixels(0.7), 0);
+        assert_eq!(
+            report.difference_image().pixel_at(0, 0),
+            Color::new(0.0, 0.6, 0.0)
+        );
+    }
+
+    #[test]
+    fn canvases_of_different_sizes_differ_everywhere() {
+        let a = Canvas::new(2, 2);
+        let report = a.diff(&Canvas::new(3, 2));
+        assert!(!report.same_size());
+        assert!(!report.within(Float::INFINITY));
+        assert_eq!(report.mismatched_pixels(1.0), 4);
+        assert_eq!(report.max_error, Float::INFINITY);
+        assert_eq!(report.difference_image().pixel_at(1, 1), Color::white());
+    }
+}
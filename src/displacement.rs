@@ -0,0 +1,106 @@
+use crate::{
+    object::Shape,
+    pattern::Pattern,
+    tuple::{point, Tuple},
+    util::Float,
+};
+
+/// Heights read from a pattern and applied to the vertices of a tessellated
+/// surface when the scene is built. Unlike a [`crate::material::BumpMap`],
+/// the geometry really moves, so silhouettes and shadows follow the relief.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Displacement {
+    /// Height of each vertex is the mean of the pattern color at its rest
+    /// position, times `scale`.
+    pub height: Pattern,
+    pub scale: Float,
+    /// Longest edge of a tessellation cell. The number of cells follows the
+    /// size of the surface so the detail stays the same when it grows.
+    pub max_edge: Float,
+}
+
+impl Displacement {
+    pub fn new(height: Pattern, scale: Float) -> Self {
+        Self {
+            height,
+            scale,
+            max_edge: 0.1,
+        }
+    }
+
+    pub fn max_edge(mut self, max_edge: Float) -> Self {
+        assert!(max_edge > 0.0, "cells must have a size");
+        self.max_edge = max_edge;
+        self
+    }
+
+    fn displaced(&self, x: Float, z: Float) -> Tuple {
+        let rest = point(x, 0.0, z);
+        point(x, self.height.color_at(rest).mean() * self.scale, z)
+    }
+
+    /// Triangles of the `width` by `depth` patch of the xz plane centered on
+    /// the origin, raised by the height pattern.
+    pub fn plane(&self, width: Float, depth: Float) -> Vec<Shape> {
+        let columns = ((width / self.max_edge).ceil() as usize).max(1);
+        let rows = ((depth / self.max_edge).ceil() as usize).max(1);
+        let x_at = |i: usize| -width / 2.0 + width * i as Float / columns as Float;
+        let z_at = |j: usize| -depth / 2.0 + depth * j as Float / rows as Float;
+        let vertices: Vec<Vec<Tuple>> = (0..=rows)
+            .map(|j| {
+                (0..=columns)
+                    .map(|i| self.displaced(x_at(i), z_at(j)))
+                    .collect()
+            })
+            .collect();
+        let mut triangles = Vec::with_capacity(2 * rows * columns);
+        for j in 0..rows {
+            for i in 0..columns {
+                let (a, b) = (vertices[j][i], vertices[j][i + 1]);
+                let (c, d) = (vertices[j + 1][i], vertices[j + 1][i + 1]);
+                triangles.push(Shape::triangle(a, b, c));
+                triangles.push(Shape::triangle(b, d, c));
+            }
+        }
+        triangles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{color::Color, intersection::Intersectable, ray::Ray, tuple::vector};
+
+    use super::*;
+
+    #[test]
+    fn cell_count_follows_the_size_of_the_plane() {
+        let d = Displacement::new(Pattern::solid(Color::black()), 1.0).max_edge(0.5);
+        assert_eq!(d.plane(1.0, 1.0).len(), 8);
+        assert_eq!(d.plane(2.0, 1.0).len(), 16);
+    }
+
+    #[test]
+    fn flat_height_keeps_the_plane_flat() {
+        let d = Displacement::new(Pattern::solid(Color::black()), 1.0).max_edge(0.5);
+        for t in d.plane(2.0, 2.0) {
+            assert_eq!(t.normal_at(&point(0.0, 0.0, 0.0)), vector(0.0, 1.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn rays_hit_the_displaced_surface() {
+        let d = Displacement::new(Pattern::gradient(Color::black(), Color::white()), 2.0)
+            .max_edge(0.25);
+        let r = Ray::new(point(0.5, 5.0, 0.3), vector(0.0, -1.0, 0.0));
+        let times: Vec<Float> = d
+            .plane(2.0, 2.0)
+            .iter()
+            .flat_map(|t| t.intersects(r).data().clone())
+            .map(|i| i.time)
+            .collect();
+        // the gradient runs along x, so the surface sits at y = 2 * 0.5
+        assert!(!times.is_empty());
+        assert!(times.iter().all(|t| (t - 4.0).abs() < 1e-9));
+    }
+}
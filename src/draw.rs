@@ -0,0 +1,254 @@
+use crate::{canvas::Canvas, color::Color};
+
+/// Size of the glyphs of the built-in font, in pixels.
+pub const GLYPH_WIDTH: usize = 3;
+pub const GLYPH_HEIGHT: usize = 5;
+
+/// Rows of the 3x5 glyph of `c`, most significant bit on the left. Letters
+/// are drawn in upper case, unknown characters as `?`.
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ' ' => [0; GLYPH_HEIGHT],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '(' => [0b010, 0b100, 0b100, 0b100, 0b010],
+        ')' => [0b010, 0b001, 0b001, 0b001, 0b010],
+        _ => [0b111, 0b001, 0b010, 0b000, 0b010],
+    }
+}
+
+/// 2D drawing, for debug overlays and the demos. Coordinates are signed so
+/// shapes can be partly off the canvas, the pixels outside are skipped.
+impl Canvas {
+    fn plot(&mut self, x: isize, y: isize, color: Color) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width() && (y as usize) < self.height() {
+            self.write_pixel(x as usize, y as usize, color)
+        }
+    }
+
+    /// Bresenham line, both ends included.
+    pub fn draw_line(&mut self, (x0, y0): (isize, isize), (x1, y1): (isize, isize), color: Color) {
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (sx, sy) = ((x1 - x0).signum(), (y1 - y0).signum());
+        let (mut x, mut y) = (x0, y0);
+        let mut err = dx + dy;
+        loop {
+            self.plot(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Outline of the `width` by `height` rectangle whose top left corner
+    /// is at `(x, y)`.
+    pub fn draw_rect(&mut self, (x, y): (isize, isize), width: usize, height: usize, color: Color) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let (right, bottom) = (x + width as isize - 1, y + height as isize - 1);
+        self.draw_line((x, y), (right, y), color);
+        self.draw_line((x, bottom), (right, bottom), color);
+        self.draw_line((x, y), (x, bottom), color);
+        self.draw_line((right, y), (right, bottom), color);
+    }
+
+    /// Outline of the circle of the given center and radius (midpoint
+    /// algorithm).
+    pub fn draw_circle(&mut self, (cx, cy): (isize, isize), radius: usize, color: Color) {
+        let (mut x, mut y) = (radius as isize, 0);
+        let mut err = 1 - x;
+        while x >= y {
+            for (px, py) in [
+                (x, y),
+                (y, x),
+                (-y, x),
+                (-x, y),
+                (-x, -y),
+                (-y, -x),
+                (y, -x),
+                (x, -y),
+            ] {
+                self.plot(cx + px, cy + py, color);
+            }
+            y += 1;
+            if err < 0 {
+                err += 2 * y + 1;
+            } else {
+                x -= 1;
+                err += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// Writes `text` with the built-in font, its top left corner at `(x, y)`.
+    /// Glyphs are one pixel apart and `\n` starts a new line.
+    pub fn draw_text(&mut self, (x, y): (isize, isize), text: &str, color: Color) {
+        let (mut left, mut top) = (x, y);
+        for c in text.chars() {
+            if c == '\n' {
+                left = x;
+                top += GLYPH_HEIGHT as isize + 1;
+                continue;
+            }
+            for (dy, bits) in glyph(c).into_iter().enumerate() {
+                for dx in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - dx)) != 0 {
+                        self.plot(left + dx as isize, top + dy as isize, color);
+                    }
+                }
+            }
+            left += GLYPH_WIDTH as isize + 1;
+        }
+    }
+
+    /// Paints `color` over the region of same colored pixels around
+    /// `(x, y)`, going through edges but not corners.
+    pub fn flood_fill(&mut self, x: usize, y: usize, color: Color) {
+        let target = self.pixel_at(x, y);
+        if target == color {
+            return;
+        }
+        let (width, height) = (self.width(), self.height());
+        let mut stack = vec![(x, y)];
+        while let Some((x, y)) = stack.pop() {
+            if self.pixel_at(x, y) != target {
+                continue;
+            }
+            self.write_pixel(x, y, color);
+            if x > 0 {
+                stack.push((x - 1, y));
+            }
+            if x + 1 < width {
+                stack.push((x + 1, y));
+            }
+            if y > 0 {
+                stack.push((x, y - 1));
+            }
+            if y + 1 < height {
+                stack.push((x, y + 1));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color = Color::new(1.0, 0.0, 0.0);
+
+    fn lit(c: &Canvas) -> Vec<(usize, usize)> {
+        let mut points = vec![];
+        for y in 0..c.height() {
+            for x in 0..c.width() {
+                if c.pixel_at(x, y) != Color::black() {
+                    points.push((x, y));
+                }
+            }
+        }
+        points
+    }
+
+    #[test]
+    fn drawing_a_line() {
+        let mut c = Canvas::new(5, 3);
+        c.draw_line((0, 0), (4, 2), RED);
+        assert_eq!(lit(&c), vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 2)]);
+    }
+
+    #[test]
+    fn lines_are_clipped_to_the_canvas() {
+        let mut c = Canvas::new(3, 3);
+        c.draw_line((-2, 1), (5, 1), RED);
+        assert_eq!(lit(&c), vec![(0, 1), (1, 1), (2, 1)]);
+    }
+
+    #[test]
+    fn drawing_and_filling_a_rectangle() {
+        let mut c = Canvas::new(5, 5);
+        c.draw_rect((1, 1), 3, 3, RED);
+        assert_eq!(lit(&c).len(), 8);
+        assert_eq!(c.pixel_at(2, 2), Color::black());
+        c.flood_fill(2, 2, RED);
+        assert_eq!(c.pixel_at(2, 2), RED);
+        assert_eq!(c.pixel_at(0, 0), Color::black());
+    }
+
+    #[test]
+    fn drawing_a_circle() {
+        let mut c = Canvas::new(11, 11);
+        c.draw_circle((5, 5), 3, RED);
+        for (x, y) in [(8, 5), (2, 5), (5, 8), (5, 2)] {
+            assert_eq!(c.pixel_at(x, y), RED);
+        }
+        assert_eq!(c.pixel_at(5, 5), Color::black());
+    }
+
+    #[test]
+    fn drawing_text() {
+        let mut c = Canvas::new(8, 12);
+        c.draw_text((0, 0), "1t\n-", RED);
+        // the 1, then the T one pixel after it
+        assert_eq!(
+            lit(&c)[..6],
+            [(1, 0), (4, 0), (5, 0), (6, 0), (0, 1), (1, 1)]
+        );
+        assert_eq!(c.pixel_at(3, 0), Color::black());
+        // the dash on the second line
+        assert_eq!(c.pixel_at(0, 8), RED);
+        assert_eq!(c.pixel_at(2, 8), RED);
+    }
+}
@@ -0,0 +1,171 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::canvas::Canvas;
+
+enum Target {
+    Numbered {
+        directory: PathBuf,
+        prefix: String,
+    },
+    #[cfg(feature = "gif")]
+    Gif {
+        path: PathBuf,
+        /// Frame delay in hundredths of a second.
+        delay: u16,
+        /// Created with the size of the first frame.
+        encoder: Option<gif::Encoder<io::BufWriter<std::fs::File>>>,
+    },
+}
+
+/// Writes a sequence of canvases, as numbered images or as a looping
+/// animated GIF.
+pub struct FrameWriter {
+    target: Target,
+    frames: usize,
+    size: Option<(usize, usize)>,
+}
+
+impl FrameWriter {
+    fn new(target: Target) -> Self {
+        Self {
+            target,
+            frames: 0,
+            size: None,
+        }
+    }
+
+    /// Saves every frame to `directory` as `<prefix>0000.png`,
+    /// `<prefix>0001.png`...
+    pub fn numbered(directory: impl AsRef<Path>, prefix: &str) -> Self {
+        Self::new(Target::Numbered {
+            directory: directory.as_ref().to_path_buf(),
+            prefix: prefix.to_string(),
+        })
+    }
+
+    /// Writes the frames to an animated GIF at `path`, played at `fps`
+    /// frames per second. The file is created with the first frame.
+    #[cfg(feature = "gif")]
+    pub fn gif(path: impl AsRef<Path>, fps: f64) -> Self {
+        Self::new(Target::Gif {
+            path: path.as_ref().to_path_buf(),
+            delay: (100.0 / fps).round().clamp(1.0, u16::MAX as f64) as u16,
+            encoder: None,
+        })
+    }
+
+    /// Frames written so far.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Adds a frame, of the same size as the previous ones.
+    pub fn write_frame(&mut self, canvas: &Canvas) -> io::Result<()> {
+        let size = (canvas.width(), canvas.height());
+        if self.size.is_some_and(|s| s != size) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frames must all have the same size",
+            ));
+        }
+        match &mut self.target {
+            Target::Numbered { directory, prefix } => {
+                canvas.save(directory.join(format!("{prefix}{:04}.png", self.frames)))?
+            }
+            #[cfg(feature = "gif")]
+            Target::Gif {
+                path,
+                delay,
+                encoder,
+            } => {
+                let too_big =
+                    || io::Error::new(io::ErrorKind::InvalidInput, "image too big for GIF");
+                let width = u16::try_from(size.0).map_err(|_| too_big())?;
+                let height = u16::try_from(size.1).map_err(|_| too_big())?;
+                let encoder = match encoder {
+                    Some(encoder) => encoder,
+                    None => {
+                        let file = io::BufWriter::new(std::fs::File::create(&*path)?);
+                        let mut new = gif::Encoder::new(file, width, height, &[])
+                            .map_err(io::Error::other)?;
+                        new.set_repeat(gif::Repeat::Infinite)
+                            .map_err(io::Error::other)?;
+                        encoder.insert(new)
+                    }
+                };
+                let mut frame =
+                    gif::Frame::from_rgba_speed(width, height, &mut canvas.to_rgba8(), 10);
+                frame.delay = *delay;
+                encoder.write_frame(&frame).map_err(io::Error::other)?;
+            }
+        }
+        self.size = Some(size);
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Flushes the output, writing the end of the GIF.
+    pub fn finish(self) -> io::Result<()> {
+        match self.target {
+            Target::Numbered { .. } => Ok(()),
+            #[cfg(feature = "gif")]
+            Target::Gif { encoder, .. } => match encoder {
+                Some(encoder) => {
+                    use io::Write;
+                    encoder.into_inner()?.flush()
+                }
+                None => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "an animated GIF needs at least one frame",
+                )),
+            },
+        }
+    }
+}
+
+#[cfg(all(test, any(feature = "png", feature = "gif")))]
+mod tests {
+    use crate::{color::Color, util::Float};
+
+    use super::*;
+
+    fn frame(shade: Float) -> Canvas {
+        Canvas::new_with_colors(2, 2, vec![Color::new(shade, shade, shade); 4])
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn numbered_frames() {
+        let dir = std::env::temp_dir();
+        let mut w = FrameWriter::numbered(&dir, "ray_tracer_frame_");
+        w.write_frame(&frame(0.0)).unwrap();
+        w.write_frame(&frame(1.0)).unwrap();
+        assert_eq!(w.frames(), 2);
+        assert!(w.write_frame(&Canvas::new(3, 3)).is_err());
+        w.finish().unwrap();
+        let second = dir.join("ray_tracer_frame_0001.png");
+        assert_eq!(
+            Canvas::from_file(&second).unwrap().pixel_at(1, 1),
+            Color::white()
+        );
+        std::fs::remove_file(dir.join("ray_tracer_frame_0000.png")).unwrap();
+        std::fs::remove_file(second).unwrap();
+    }
+
+    #[cfg(feature = "gif")]
+    #[test]
+    fn animated_gif() {
+        let path = std::env::temp_dir().join("ray_tracer_frames.gif");
+        let mut w = FrameWriter::gif(&path, 25.0);
+        w.write_frame(&frame(0.0)).unwrap();
+        w.write_frame(&frame(1.0)).unwrap();
+        w.finish().unwrap();
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&data[..6], b"GIF89a");
+        assert_eq!(data.last(), Some(&0x3b));
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{material::Material, matrix::Mat4, object::Shape, ray::RayKind};
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Child {
+    /// Takes the material of the group, if it has one.
+    Inheriting(Shape),
+    /// Keeps its own material whatever the group says.
+    Overriding(Shape),
+    Group(Group),
+}
+
+/// Shapes sharing a transform, and optionally a material and visibility
+/// flags, so a whole model can be moved or painted at once. Groups are
+/// flattened into plain shapes when added to the world.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Group {
+    pub transform: Mat4,
+    pub material: Option<Material>,
+    hidden_from: Vec<RayKind>,
+    children: Vec<Child>,
+}
+
+impl Group {
+    pub fn new() -> Self {
+        Self {
+            transform: Mat4::identity(),
+            material: None,
+            hidden_from: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn with_transform(mut self, transform: Mat4) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Material given to every child that does not override it, nested
+    /// groups included.
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = Some(material);
+        self
+    }
+
+    /// Hides every child from the rays of the given kind.
+    pub fn hidden_from(mut self, kind: RayKind) -> Self {
+        self.hidden_from.push(kind);
+        self
+    }
+
+    /// Adds a shape that inherits the material of the group.
+    pub fn child(mut self, shape: Shape) -> Self {
+        self.children.push(Child::Inheriting(shape));
+        self
+    }
+
+    /// Adds a shape that keeps its own material.
+    pub fn child_with_own_material(mut self, shape: Shape) -> Self {
+        self.children.push(Child::Overriding(shape));
+        self
+    }
+
+    pub fn subgroup(mut self, group: Group) -> Self {
+        self.children.push(Child::Group(group));
+        self
+    }
+
+    /// Children as world space shapes, with the transform, material and
+    /// flags of the groups above them applied.
+    pub fn shapes(&self) -> Vec<Shape> {
+        let mut shapes = Vec::new();
+        self.flatten_into(Mat4::identity(), None, &[], &mut shapes);
+        shapes
+    }
+
+    fn flatten_into(
+        &self,
+        parent: Mat4,
+        inherited: Option<&Material>,
+        hidden_from: &[RayKind],
+        shapes: &mut Vec<Shape>,
+    ) {
+        let transform = parent * self.transform;
+        let material = self.material.as_ref().or(inherited);
+        let hidden_from = [hidden_from, &self.hidden_from].concat();
+        for child in &self.children {
+            let (shape, own_material) = match child {
+                Child::Inheriting(shape) => (shape, false),
+                Child::Overriding(shape) => (shape, true),
+                Child::Group(group) => {
+                    group.flatten_into(transform, material, &hidden_from, shapes);
+                    continue;
+                }
+            };
+            let mut shape = shape.clone().with_transform(transform * shape.transform);
+            if let (false, Some(material)) = (own_material, material) {
+                shape.set_material(material.clone());
+            }
+            for kind in &hidden_from {
+                shape = shape.hidden_from(*kind);
+            }
+            shapes.push(shape);
+        }
+    }
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        color::Color,
+        intersection::Intersectable,
+        transformations::{rot_y, scaling, translation},
+        tuple::{point, vector},
+        util::{consts::FRAC_PI_2, Float},
+    };
+
+    use super::*;
+
+    fn gold() -> Material {
+        Material::default().color(Color::new(1.0, 0.8, 0.0))
+    }
+
+    #[test]
+    fn children_inherit_the_material_of_the_group() {
+        let red = Material::default().color(Color::new(1.0, 0.0, 0.0));
+        let g = Group::new()
+            .with_material(gold())
+            .child(Shape::sphere().with_material(red.clone()))
+            .child_with_own_material(Shape::sphere().with_material(red.clone()));
+        let shapes = g.shapes();
+        assert_eq!(shapes[0].material, gold());
+        assert_eq!(shapes[1].material, red);
+    }
+
+    #[test]
+    fn nested_groups_inherit_unless_they_have_their_own() {
+        let silver = Material::default().color(Color::new(0.8, 0.8, 0.8));
+        let g = Group::new()
+            .with_material(gold())
+            .subgroup(Group::new().child(Shape::sphere()))
+            .subgroup(
+                Group::new()
+                    .with_material(silver.clone())
+                    .child(Shape::sphere()),
+            );
+        let shapes = g.shapes();
+        assert_eq!(shapes[0].material, gold());
+        assert_eq!(shapes[1].material, silver);
+    }
+
+    #[test]
+    fn children_keep_their_material_without_one_on_the_group() {
+        let s = Shape::sphere().with_material(gold());
+        assert_eq!(Group::new().child(s).shapes()[0].material, gold());
+    }
+
+    #[test]
+    fn group_transforms_apply_to_the_children() {
+        let g = Group::new()
+            .with_transform(translation(5.0, 0.0, 0.0))
+            .subgroup(
+                Group::new()
+                    .with_transform(scaling(2.0, 2.0, 2.0))
+                    .child(Shape::sphere().with_transform(translation(1.0, 0.0, 0.0))),
+            );
+        assert_eq!(
+            g.shapes()[0].transform,
+            translation(5.0, 0.0, 0.0) * scaling(2.0, 2.0, 2.0) * translation(1.0, 0.0, 0.0)
+        );
+    }
+
+    /// Sphere moved by 5 along x, scaled by `scale` then turned a quarter
+    /// around y by two nested groups.
+    fn nested_shape(scale: Mat4) -> Shape {
+        let g = Group::new().with_transform(rot_y(FRAC_PI_2)).subgroup(
+            Group::new()
+                .with_transform(scale)
+                .child(Shape::sphere().with_transform(translation(5.0, 0.0, 0.0))),
+        );
+        g.shapes().remove(0)
+    }
+
+    #[test]
+    fn converting_a_point_from_world_to_object_space() {
+        let p = nested_shape(scaling(2.0, 2.0, 2.0)).world_to_object(point(-2.0, 0.0, -10.0));
+        assert_eq!(p, point(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn converting_a_normal_from_object_to_world_space() {
+        let third = Float::sqrt(3.0) / 3.0;
+        let n = nested_shape(scaling(1.0, 2.0, 3.0)).normal_to_world(vector(third, third, third));
+        assert_eq!(n, vector(2.0 / 7.0, 3.0 / 7.0, -6.0 / 7.0));
+    }
+
+    #[test]
+    fn finding_the_normal_on_a_child_object() {
+        let root = Float::sqrt(3.0);
+        let p = point(root, 2.0 / root, -5.0 - 1.0 / root);
+        let n = nested_shape(scaling(1.0, 2.0, 3.0)).normal_at(&p);
+        assert_eq!(n, vector(2.0 / 7.0, 3.0 / 7.0, -6.0 / 7.0));
+    }
+
+    #[test]
+    fn hidden_groups_hide_their_children() {
+        let g = Group::new()
+            .hidden_from(RayKind::Camera)
+            .subgroup(Group::new().child(Shape::sphere()));
+        let s = &g.shapes()[0];
+        assert!(!s.visible_to(RayKind::Camera));
+        assert!(s.visible_to(RayKind::Reflection));
+    }
+}
@@ -0,0 +1,201 @@
+// The casts do nothing when `Float` already is the precision of the other
+// type.
+#![allow(clippy::unnecessary_cast)]
+
+use crate::{matrix::Mat4, util::Float};
+
+/// Entries of `m`, column by column, as graphics libraries store them.
+fn cols_array(m: &Mat4) -> [Float; 16] {
+    let mut cols = [0.0; 16];
+    for (i, value) in cols.iter_mut().enumerate() {
+        *value = m[(i % 4, i / 4)];
+    }
+    cols
+}
+
+fn from_cols_array(cols: [Float; 16]) -> Mat4 {
+    let mut m = Mat4::default();
+    for (i, value) in cols.into_iter().enumerate() {
+        m[(i % 4, i / 4)] = value;
+    }
+    m
+}
+
+/// glam has no points: its 3D vectors turn into vectors, and drop `w` the
+/// other way.
+#[cfg(feature = "glam")]
+mod glam_impls {
+    use super::{cols_array, from_cols_array};
+    use crate::{
+        matrix::Mat4,
+        tuple::{vector, Tuple},
+        util::Float,
+    };
+
+    impl From<Tuple> for glam::DVec3 {
+        fn from(t: Tuple) -> Self {
+            Self::new(t.x as f64, t.y as f64, t.z as f64)
+        }
+    }
+
+    impl From<glam::DVec3> for Tuple {
+        fn from(v: glam::DVec3) -> Self {
+            vector(v.x as Float, v.y as Float, v.z as Float)
+        }
+    }
+
+    impl From<Tuple> for glam::Vec3A {
+        fn from(t: Tuple) -> Self {
+            Self::new(t.x as f32, t.y as f32, t.z as f32)
+        }
+    }
+
+    impl From<glam::Vec3A> for Tuple {
+        fn from(v: glam::Vec3A) -> Self {
+            vector(v.x as Float, v.y as Float, v.z as Float)
+        }
+    }
+
+    impl From<Tuple> for glam::DVec4 {
+        fn from(t: Tuple) -> Self {
+            Self::new(t.x as f64, t.y as f64, t.z as f64, t.w as f64)
+        }
+    }
+
+    impl From<glam::DVec4> for Tuple {
+        fn from(v: glam::DVec4) -> Self {
+            Tuple::new(v.x as Float, v.y as Float, v.z as Float, v.w as Float)
+        }
+    }
+
+    impl From<Mat4> for glam::DMat4 {
+        fn from(m: Mat4) -> Self {
+            Self::from_cols_array(&cols_array(&m).map(|x| x as f64))
+        }
+    }
+
+    impl From<glam::DMat4> for Mat4 {
+        fn from(m: glam::DMat4) -> Self {
+            from_cols_array(m.to_cols_array().map(|x| x as Float))
+        }
+    }
+
+    impl From<Mat4> for glam::Mat4 {
+        fn from(m: Mat4) -> Self {
+            Self::from_cols_array(&cols_array(&m).map(|x| x as f32))
+        }
+    }
+
+    impl From<glam::Mat4> for Mat4 {
+        fn from(m: glam::Mat4) -> Self {
+            from_cols_array(m.to_cols_array().map(|x| x as Float))
+        }
+    }
+}
+
+/// nalgebra tells points from vectors, which set `w` accordingly.
+#[cfg(feature = "nalgebra")]
+mod nalgebra_impls {
+    use nalgebra::{Matrix4, Point3, Vector3, Vector4};
+
+    use super::{cols_array, from_cols_array};
+    use crate::{
+        matrix::Mat4,
+        tuple::{point, vector, Tuple},
+        util::Float,
+    };
+
+    impl From<Tuple> for Vector3<Float> {
+        fn from(t: Tuple) -> Self {
+            Self::new(t.x, t.y, t.z)
+        }
+    }
+
+    impl From<Vector3<Float>> for Tuple {
+        fn from(v: Vector3<Float>) -> Self {
+            vector(v.x, v.y, v.z)
+        }
+    }
+
+    impl From<Tuple> for Point3<Float> {
+        fn from(t: Tuple) -> Self {
+            Self::new(t.x, t.y, t.z)
+        }
+    }
+
+    impl From<Point3<Float>> for Tuple {
+        fn from(p: Point3<Float>) -> Self {
+            point(p.x, p.y, p.z)
+        }
+    }
+
+    impl From<Tuple> for Vector4<Float> {
+        fn from(t: Tuple) -> Self {
+            Self::new(t.x, t.y, t.z, t.w)
+        }
+    }
+
+    impl From<Vector4<Float>> for Tuple {
+        fn from(v: Vector4<Float>) -> Self {
+            Tuple::new(v.x, v.y, v.z, v.w)
+        }
+    }
+
+    impl From<Mat4> for Matrix4<Float> {
+        fn from(m: Mat4) -> Self {
+            Self::from_column_slice(&cols_array(&m))
+        }
+    }
+
+    impl From<Matrix4<Float>> for Mat4 {
+        fn from(m: Matrix4<Float>) -> Self {
+            let mut cols = [0.0; 16];
+            cols.copy_from_slice(m.as_slice());
+            from_cols_array(cols)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        transformations::{rot_y, translation},
+        tuple::{point, vector, Tuple},
+    };
+
+    use super::*;
+
+    fn transform() -> Mat4 {
+        translation(1.0, 2.0, 3.0) * rot_y(0.5)
+    }
+
+    #[cfg(feature = "glam")]
+    #[test]
+    fn glam_round_trips() {
+        let m = transform();
+        let p = point(4.0, -5.0, 6.0);
+        let gm = glam::DMat4::from(m);
+        assert_eq!(gm.w_axis, glam::DVec4::new(1.0, 2.0, 3.0, 1.0));
+        assert_eq!(Tuple::from(gm * glam::DVec4::from(p)), m * p);
+        assert_eq!(Mat4::from(gm), m);
+        assert_eq!(Mat4::from(glam::Mat4::from(m)), m);
+        let v = vector(1.0, 2.0, 3.0);
+        assert_eq!(Tuple::from(glam::DVec3::from(v)), v);
+        assert_eq!(Tuple::from(glam::Vec3A::from(v)), v);
+    }
+
+    #[cfg(feature = "nalgebra")]
+    #[test]
+    fn nalgebra_round_trips() {
+        use nalgebra::{Matrix4, Point3, Vector3};
+
+        let m = transform();
+        let p = point(4.0, -5.0, 6.0);
+        let nm = Matrix4::from(m);
+        assert_eq!(nm[(0, 3)], 1.0);
+        assert_eq!(Tuple::from(nm.transform_point(&Point3::from(p))), m * p);
+        assert_eq!(Mat4::from(nm), m);
+        let v = vector(1.0, 2.0, 3.0);
+        assert_eq!(Tuple::from(nm.transform_vector(&Vector3::from(v))), m * v);
+    }
+}
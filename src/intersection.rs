@@ -1,7 +1,50 @@
-use std::{fmt::Debug, ops::Index};
+use std::{
+    cell::RefCell,
+    fmt::Debug,
+    ops::{Deref, Index},
+    sync::Arc,
+};
+
+use crate::{
+    material::{BackFace, Material},
+    object::Shape,
+    ray::Ray,
+    tuple::Tuple,
+    util::{Float, EPSILON},
+};
+
+/// How far [`Computations::over_point`] and [`Computations::under_point`] are
+/// moved off the surface, so that it does not shadow or hide itself. Too
+/// small shows as acne on large scenes, too large as shadows detached from
+/// the objects of small ones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Bias {
+    /// The same distance everywhere.
+    Fixed(Float),
+    /// The distance times the largest coordinate of the point, at least the
+    /// distance itself, keeping up with the rounding errors of coordinates
+    /// in the hundreds.
+    Relative(Float),
+}
 
-use crate::{object::Shape, ray::Ray, tuple::Tuple, util::EPSILON};
+impl Default for Bias {
+    fn default() -> Self {
+        Bias::Fixed(EPSILON)
+    }
+}
+
+impl Bias {
+    /// Distance the points are moved at `p`.
+    pub fn at(&self, p: Tuple) -> Float {
+        match *self {
+            Bias::Fixed(bias) => bias,
+            Bias::Relative(bias) => bias * p.x.abs().max(p.y.abs()).max(p.z.abs()).max(1.0),
+        }
+    }
+}
 
+/// Intersections of a ray, always sorted by time.
 pub struct Intersections(Vec<Intersection>);
 
 impl Index<usize> for Intersections {
@@ -11,7 +54,14 @@ impl Index<usize> for Intersections {
     }
 }
 
-#[derive(Clone, Copy)]
+impl Deref for Intersections {
+    type Target = [Intersection];
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[derive(Clone)]
 pub struct Computations {
     pub i: Intersection,
     pub point: Tuple,
@@ -20,15 +70,41 @@ pub struct Computations {
     pub inside: bool,
     pub eye_v: Tuple,
     pub normal_v: Tuple,
-    pub dot_eyev_normal_v: f64,
+    pub dot_eyev_normal_v: Float,
     pub reflect_v: Tuple,
+    /// Width of the ray at the hit, over which textures get filtered.
+    pub footprint: Float,
 
     /// Refraction calculations
-    pub n: (f64, f64),
+    pub n: (Float, Float),
 }
 
 impl Computations {
-    pub fn schlick(&self) -> f64 {
+    /// Material of the side of the surface that was hit.
+    pub fn material(&self) -> &Material {
+        let m = &self.i.object.material;
+        match (&m.back_face, self.inside) {
+            (BackFace::Material(back), true) => back,
+            _ => m,
+        }
+    }
+
+    /// Direction of the ray refracted through the surface following Snell's
+    /// law, `None` under total internal reflection.
+    pub fn refracted_direction(&self) -> Option<Tuple> {
+        let (n1, n2) = self.n;
+        let n_ratio = n1 / n2;
+        let cos_i = self.eye_v ^ self.normal_v;
+        let sin2_t = n_ratio * n_ratio * (1.0 - (cos_i * cos_i));
+        if sin2_t > 1.0 {
+            None
+        } else {
+            let cos_t = Float::sqrt(1.0 - sin2_t);
+            Some(self.normal_v * (n_ratio * cos_i - cos_t) - self.eye_v * n_ratio)
+        }
+    }
+
+    pub fn schlick(&self) -> Float {
         let mut cos = self.dot_eyev_normal_v;
         let (n1, n2) = self.n;
 
@@ -38,7 +114,7 @@ impl Computations {
             if sin2_t > 1.0 {
                 return 1.0;
             }
-            cos = f64::sqrt(1.0 - sin2_t);
+            cos = Float::sqrt(1.0 - sin2_t);
         }
 
         let r0 = ((n1 - n2) / (n1 + n2)).powi(2);
@@ -47,10 +123,17 @@ impl Computations {
 }
 
 impl Intersections {
-    pub fn new(i: Vec<Intersection>) -> Intersections {
+    pub fn new(mut i: Vec<Intersection>) -> Intersections {
+        sort_by_time(&mut i);
         Self(i)
     }
 
+    /// Inserts `i` after the intersections with a lower or equal time.
+    pub fn add(&mut self, i: Intersection) {
+        let at = self.0.partition_point(|x| x.time <= i.time);
+        self.0.insert(at, i);
+    }
+
     pub fn new_none() -> Self {
         Self(vec![])
     }
@@ -64,66 +147,101 @@ impl Intersections {
     }
 
     pub fn hit(&self) -> Option<&Intersection> {
-        self.0
-            .iter()
-            .filter(|t| t.time > 0.0)
-            .min_by(|a, b| a.time.total_cmp(&b.time))
+        hit_sorted(&self.0)
+    }
+}
+
+impl Extend<Intersection> for Intersections {
+    /// Adds the intersections of another object. The sort used to merge them
+    /// in is linear when they come sorted, as primitives report them.
+    fn extend<T: IntoIterator<Item = Intersection>>(&mut self, iter: T) {
+        self.0.extend(iter);
+        sort_by_time(&mut self.0);
     }
 }
 
+/// Stable sort of `xs` by time.
+pub fn sort_by_time(xs: &mut [Intersection]) {
+    xs.sort_by(|a, b| a.time.total_cmp(&b.time));
+}
+
+/// Intersection with the lowest positive time among `xs`.
+pub fn hit(xs: &[Intersection]) -> Option<&Intersection> {
+    xs.iter()
+        .filter(|t| t.time > 0.0)
+        .min_by(|a, b| a.time.total_cmp(&b.time))
+}
+
+/// Same as [`hit`] for `xs` sorted by time, stopping at the first positive
+/// time.
+pub fn hit_sorted(xs: &[Intersection]) -> Option<&Intersection> {
+    debug_assert!(
+        xs.windows(2).all(|w| w[0].time <= w[1].time),
+        "intersections are not sorted by time"
+    );
+    xs.iter().find(|i| i.time > 0.0)
+}
+
 pub trait Intersectable: Debug + PartialEq + Sized {
     fn intersects(&self, r: Ray) -> Intersections;
 
     fn normal_at(&self, point: &Tuple) -> Tuple;
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Intersection {
-    pub time: f64,
-    pub object: Shape,
+    pub time: Float,
+    /// Shared with the world the object is in, so hits are cheap to copy.
+    pub object: Arc<Shape>,
+    /// Index of the object in the world it was found in, telling objects
+    /// apart without comparing whole shapes.
+    index: Option<usize>,
+}
+
+impl PartialEq for Intersection {
+    fn eq(&self, other: &Self) -> bool {
+        self.time == other.time && self.object == other.object
+    }
 }
 
 impl Intersection {
-    pub fn new(t: f64, s: Shape) -> Self {
-        Self { time: t, object: s }
-    }
-
-    pub fn prepare_computations(&self, r: Ray, xs: &Intersections) -> Computations {
-        let mut containers: Vec<Shape> = vec![];
-        let mut n1 = 1.0;
-        let mut n2 = 1.0;
-        for x in xs.0.iter() {
-            if self == x {
-                if containers.is_empty() {
-                    n1 = 1.0
-                } else {
-                    n1 = containers
-                        .last()
-                        .expect("containers can't be empty")
-                        .material
-                        .refractive_index;
-                }
-            }
-            if let Some(index) = containers.iter().position(|&s| x.object == s) {
-                containers.remove(index);
-            } else {
-                containers.push(x.object);
-            }
+    pub fn new(t: Float, s: impl Into<Arc<Shape>>) -> Self {
+        Self {
+            time: t,
+            object: s.into(),
+            index: None,
+        }
+    }
 
-            if self == x {
-                if containers.is_empty() {
-                    n2 = 1.0
-                } else {
-                    n2 = containers
-                        .last()
-                        .expect("containers can't be empty")
-                        .material
-                        .refractive_index;
-                }
-                break;
-            }
+    /// Same as [`Intersection::new`] for the object at `index` in a world.
+    pub fn with_index(t: Float, s: impl Into<Arc<Shape>>, index: usize) -> Self {
+        Self {
+            index: Some(index),
+            ..Self::new(t, s)
         }
+    }
+
+    /// Whether both intersections are with the same object.
+    fn same_object(&self, other: &Intersection) -> bool {
+        match (self.index, other.index) {
+            (Some(a), Some(b)) => a == b,
+            _ => self.object == other.object,
+        }
+    }
 
+    pub fn prepare_computations(&self, r: Ray, xs: &[Intersection]) -> Computations {
+        self.prepare_computations_with_bias(r, xs, Bias::default())
+    }
+
+    /// Same as [`Intersection::prepare_computations`], with the points on
+    /// either side of the surface moved off it by `bias`.
+    pub fn prepare_computations_with_bias(
+        &self,
+        r: Ray,
+        xs: &[Intersection],
+        bias: Bias,
+    ) -> Computations {
+        let (n1, n2) = self.refractive_indices(xs);
         let p = r.position(self.time);
         let mut normal_v = self.object.normal_at(&p);
         let eye_v = -r.direction;
@@ -134,43 +252,92 @@ impl Intersection {
             false
         };
         let reflect_v = r.direction.reflect(&normal_v);
+        let offset = normal_v * bias.at(p);
 
         Computations {
-            i: *self,
+            i: self.clone(),
             point: p,
             inside,
             eye_v,
             normal_v,
             dot_eyev_normal_v: eye_v ^ normal_v,
-            over_point: p + normal_v * EPSILON,
-            under_point: p - normal_v * EPSILON,
+            over_point: p + offset,
+            under_point: p - offset,
             reflect_v,
+            footprint: r.footprint(self.time),
             n: (n1, n2),
         }
     }
+
+    /// Refractive indices on both sides of the surface at this intersection,
+    /// from the objects the ray is inside of when it gets there.
+    fn refractive_indices(&self, xs: &[Intersection]) -> (Float, Float) {
+        // `self` usually is the hit taken out of `xs`, found by address
+        // without comparing whole shapes
+        let Some(at) = xs.iter().position(|x| std::ptr::eq(x, self)).or_else(|| {
+            xs.iter()
+                .position(|x| x.time == self.time && x.same_object(self))
+        }) else {
+            return (1.0, 1.0);
+        };
+        let xs = &xs[..=at];
+        // most scenes are opaque: nothing to track
+        if xs.iter().all(|x| x.object.material.refractive_index == 1.0) {
+            return (1.0, 1.0);
+        }
+        CONTAINERS.with(|containers| {
+            // positions in `xs` of the hits entering the objects the ray is
+            // in, innermost last
+            let mut containers = containers.borrow_mut();
+            containers.clear();
+            let index = |containers: &[usize]| {
+                containers
+                    .last()
+                    .map_or(1.0, |&c| xs[c].object.material.refractive_index)
+            };
+            let mut n1 = 1.0;
+            for (i, x) in xs.iter().enumerate() {
+                if i == at {
+                    n1 = index(&containers);
+                }
+                // the ray leaves the innermost objects first, search from
+                // the end
+                match containers.iter().rposition(|&c| xs[c].same_object(x)) {
+                    Some(c) => {
+                        containers.remove(c);
+                    }
+                    None => containers.push(i),
+                }
+            }
+            (n1, index(&containers))
+        })
+    }
+}
+
+thread_local! {
+    /// Buffer reused by [`Intersection::refractive_indices`] on the thread.
+    static CONTAINERS: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
 }
 
 #[cfg(test)]
 mod tests {
 
-    use std::f64::consts::SQRT_2;
-
     use crate::{
-        intersection::Intersections,
+        intersection::{hit, Intersections},
         object::Shape,
         ray::Ray,
         transformations::{scaling, translation},
         tuple::{point, vector},
-        util::{flt_eq, EPSILON},
+        util::{consts::SQRT_2, flt_eq, EPSILON},
     };
 
-    use super::{Intersectable, Intersection};
+    use super::{Bias, Intersectable, Intersection};
 
     #[test]
     fn aggregating_intersections() {
         let s = Shape::sphere();
-        let i1 = Intersection::new(1.0, s);
-        let i2 = Intersection::new(2.0, s);
+        let i1 = Intersection::new(1.0, s.clone());
+        let i2 = Intersection::new(2.0, s.clone());
         let xs = Intersections::new(vec![i1, i2]);
         assert_eq!(xs.data().len(), 2);
         assert_eq!(xs.data()[0].time, 1.0);
@@ -183,16 +350,16 @@ mod tests {
         let s = Shape::sphere();
         let xs = s.intersects(r);
         assert_eq!(xs.data().len(), 2);
-        assert_eq!(xs.data()[0].object, (s));
-        assert_eq!(xs.data()[1].object, (s));
+        assert_eq!(*xs.data()[0].object, (s));
+        assert_eq!(*xs.data()[1].object, (s));
     }
 
     #[test]
     fn hit_all_intersections_positive_t() {
         let s = Shape::sphere();
-        let i1 = Intersection::new(1.0, s);
-        let i2 = Intersection::new(2.0, s);
-        let xs = Intersections::new(vec![i1, i2]);
+        let i1 = Intersection::new(1.0, s.clone());
+        let i2 = Intersection::new(2.0, s.clone());
+        let xs = Intersections::new(vec![i1.clone(), i2]);
         assert_eq!(xs.hit(), Some(&i1))
     }
 
@@ -200,17 +367,17 @@ mod tests {
     fn hit_some_intersections_positive_t() {
         let s = Shape::sphere();
 
-        let i1 = Intersection::new(-1.0, s);
-        let i2 = Intersection::new(1.0, s);
-        let xs = Intersections::new(vec![i1, i2]);
+        let i1 = Intersection::new(-1.0, s.clone());
+        let i2 = Intersection::new(1.0, s.clone());
+        let xs = Intersections::new(vec![i1, i2.clone()]);
         assert_eq!(xs.hit(), Some(&i2))
     }
     #[test]
     fn hit_all_intersections_negative_t() {
         let s = Shape::sphere();
 
-        let i1 = Intersection::new(-2.0, s);
-        let i2 = Intersection::new(-1.0, s);
+        let i1 = Intersection::new(-2.0, s.clone());
+        let i2 = Intersection::new(-1.0, s.clone());
         let xs = Intersections::new(vec![i1, i2]);
         assert_eq!(xs.hit(), None)
     }
@@ -219,43 +386,73 @@ mod tests {
     fn hit_always_lowest_nonnegative_intersection() {
         let s = Shape::sphere();
 
-        let i1 = Intersection::new(5.0, s);
-        let i2 = Intersection::new(7.0, s);
-        let i3 = Intersection::new(-3.0, s);
-        let i4 = Intersection::new(2.0, s);
-        let xs = Intersections::new(vec![i1, i2, i3, i4]);
+        let i1 = Intersection::new(5.0, s.clone());
+        let i2 = Intersection::new(7.0, s.clone());
+        let i3 = Intersection::new(-3.0, s.clone());
+        let i4 = Intersection::new(2.0, s.clone());
+        let xs = Intersections::new(vec![i1, i2, i3, i4.clone()]);
         assert_eq!(xs.hit(), Some(&i4))
     }
 
+    #[test]
+    fn hit_scans_unsorted_slices() {
+        let s = Shape::sphere();
+        let xs = vec![
+            Intersection::new(5.0, s.clone()),
+            Intersection::new(-3.0, s.clone()),
+            Intersection::new(2.0, s.clone()),
+        ];
+        assert_eq!(hit(&xs).map(|i| i.time), Some(2.0));
+    }
+
+    #[test]
+    fn intersections_stay_sorted() {
+        let s = Shape::sphere();
+        let mut xs = Intersections::new(vec![
+            Intersection::new(5.0, s.clone()),
+            Intersection::new(-1.0, s.clone()),
+        ]);
+        xs.add(Intersection::new(2.0, s.clone()));
+        xs.extend([
+            Intersection::new(7.0, s.clone()),
+            Intersection::new(0.5, s.clone()),
+        ]);
+        let times = xs.iter().map(|i| i.time).collect::<Vec<_>>();
+        assert_eq!(times, vec![-1.0, 0.5, 2.0, 5.0, 7.0]);
+        assert_eq!(xs.hit().map(|i| i.time), Some(0.5));
+    }
+
     #[test]
     fn precomputing_state_of_intersection() {
         let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
         let s = Shape::sphere();
-        let i = Intersection::new(4.0, s);
-        let comps = i.prepare_computations(r, &Intersections::new(vec![i]));
-        assert_eq!(comps.i.object, s);
+        let i = Intersection::new(4.0, s.clone());
+        let comps = i.prepare_computations(r, &Intersections::new(vec![i.clone()]));
+        assert_eq!(*comps.i.object, s);
         assert_eq!(comps.point, point(0.0, 0.0, -1.0));
         assert_eq!(comps.eye_v, vector(0.0, 0.0, -1.0));
         assert_eq!(comps.normal_v, vector(0.0, 0.0, -1.0))
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn hit_intersection_outside() {
         let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
         let s = Shape::sphere();
 
-        let i = Intersection::new(4.0, s);
-        let comps = i.prepare_computations(r, &Intersections::new(vec![i]));
+        let i = Intersection::new(4.0, s.clone());
+        let comps = i.prepare_computations(r, &Intersections::new(vec![i.clone()]));
         assert_eq!(comps.inside, false);
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn hit_intersection_inside() {
         let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
         let s = Shape::sphere();
 
-        let i = Intersection::new(1.0, s);
-        let comps = i.prepare_computations(r, &Intersections::new(vec![i]));
+        let i = Intersection::new(1.0, s.clone());
+        let comps = i.prepare_computations(r, &Intersections::new(vec![i.clone()]));
         assert_eq!(comps.point, point(0.0, 0.0, 1.0));
         assert_eq!(comps.eye_v, vector(0.0, 0.0, -1.0));
         assert_eq!(comps.normal_v, vector(0.0, 0.0, -1.0));
@@ -266,12 +463,26 @@ mod tests {
     fn hit_should_offset_the_point() {
         let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
         let s = Shape::sphere().with_transform(translation(0.0, 0.0, 1.0));
-        let i = Intersection::new(5.0, s);
-        let comps = i.prepare_computations(r, &Intersections::new(vec![i]));
+        let i = Intersection::new(5.0, s.clone());
+        let comps = i.prepare_computations(r, &Intersections::new(vec![i.clone()]));
         assert!(comps.over_point.z < -EPSILON / 2.0);
         assert!(comps.point.z > comps.over_point.z);
     }
 
+    #[test]
+    fn the_offset_can_grow_with_the_coordinates() {
+        let r = Ray::new(point(0.0, 0.0, -500.0), vector(0.0, 0.0, 1.0));
+        let s = Shape::sphere().with_transform(translation(0.0, 0.0, 100.0));
+        let i = Intersection::new(599.0, s.clone());
+        let xs = Intersections::new(vec![i.clone()]);
+        let comps = i.prepare_computations_with_bias(r, &xs, Bias::Relative(1e-6));
+        assert!(flt_eq(comps.point.z - comps.over_point.z, 99e-6));
+        assert!(flt_eq(comps.under_point.z - comps.point.z, 99e-6));
+        assert_eq!(Bias::Relative(1e-6).at(point(0.1, 0.0, 0.0)), 1e-6);
+        let comps = i.prepare_computations_with_bias(r, &xs, Bias::Fixed(0.01));
+        assert!(flt_eq(comps.point.z - comps.over_point.z, 0.01));
+    }
+
     #[test]
     fn precomputing_the_reflection_vector() {
         let s = Shape::plane();
@@ -279,12 +490,13 @@ mod tests {
             point(0.0, 1.0, -1.0),
             vector(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
         );
-        let i = Intersection::new(SQRT_2, s);
-        let comps = i.prepare_computations(r, &Intersections::new(vec![i]));
+        let i = Intersection::new(SQRT_2, s.clone());
+        let comps = i.prepare_computations(r, &Intersections::new(vec![i.clone()]));
         assert_eq!(comps.reflect_v, vector(0.0, SQRT_2 / 2.0, SQRT_2 / 2.0));
     }
 
     #[test]
+    #[allow(clippy::useless_vec)]
     fn finding_n1_and_n2_at_various_intersections() {
         let cases = vec![
             (1.0, 1.5),
@@ -303,12 +515,12 @@ mod tests {
         c.material.refractive_index = 2.5;
         let r = Ray::new(point(0.0, 0.0, -4.0), vector(0.0, 0.0, 1.0));
         let intersections = vec![
-            Intersection::new(2.0, a),
-            Intersection::new(2.75, b),
-            Intersection::new(3.25, c),
-            Intersection::new(4.75, b),
-            Intersection::new(5.25, c),
-            Intersection::new(6.0, a),
+            Intersection::new(2.0, a.clone()),
+            Intersection::new(2.75, b.clone()),
+            Intersection::new(3.25, c.clone()),
+            Intersection::new(4.75, b.clone()),
+            Intersection::new(5.25, c.clone()),
+            Intersection::new(6.0, a.clone()),
         ];
         for (idx, x) in intersections.iter().enumerate() {
             let comps = x.prepare_computations(r, &Intersections::new(intersections.clone()));
@@ -318,12 +530,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn world_objects_are_told_apart_by_index() {
+        // two copies of the same sphere, one inside the other
+        let glass = Shape::glass_sphere();
+        let r = Ray::new(point(0.0, 0.0, -4.0), vector(0.0, 0.0, 1.0));
+        let xs = vec![
+            Intersection::with_index(3.0, glass.clone(), 0),
+            Intersection::with_index(3.0, glass.clone(), 1),
+            Intersection::with_index(5.0, glass.clone(), 1),
+            Intersection::with_index(5.0, glass.clone(), 0),
+        ];
+        assert_eq!(xs[1].prepare_computations(r, &xs).n, (1.5, 1.5));
+        assert_eq!(xs[2].prepare_computations(r, &xs).n, (1.5, 1.5));
+        assert_eq!(xs[3].prepare_computations(r, &xs).n, (1.5, 1.0));
+    }
+
     #[test]
     fn under_point_is_below_the_surface() {
         let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
         let s = Shape::glass_sphere().with_transform(translation(0.0, 0.0, 1.0));
-        let i = Intersection::new(5.0, s);
-        let xs = Intersections(vec![i]);
+        let i = Intersection::new(5.0, s.clone());
+        let xs = Intersections(vec![i.clone()]);
         let comps = i.prepare_computations(r, &xs);
         assert!(comps.under_point.z > EPSILON / 2.0);
         assert!(comps.point.z < comps.under_point.z);
@@ -334,8 +562,8 @@ mod tests {
         let s = Shape::glass_sphere();
         let r = Ray::new(point(0.0, 0.0, -SQRT_2 / 2.0), vector(0.0, 1.0, 0.0));
         let xs = Intersections::new(vec![
-            Intersection::new(-SQRT_2 / 2.0, s),
-            Intersection::new(SQRT_2 / 2.0, s),
+            Intersection::new(-SQRT_2 / 2.0, s.clone()),
+            Intersection::new(SQRT_2 / 2.0, s.clone()),
         ]);
         let comps = xs[1].prepare_computations(r, &xs);
         let reflectance = comps.schlick();
@@ -346,7 +574,10 @@ mod tests {
     fn schlick_with_a_perpendicular_viewing_angle() {
         let s = Shape::glass_sphere();
         let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 1.0, 0.0));
-        let xs = Intersections::new(vec![Intersection::new(-1.0, s), Intersection::new(1.0, s)]);
+        let xs = Intersections::new(vec![
+            Intersection::new(-1.0, s.clone()),
+            Intersection::new(1.0, s.clone()),
+        ]);
         let comps = xs[1].prepare_computations(r, &xs);
         let reflectance = comps.schlick();
         assert!(flt_eq(reflectance, 0.04));
@@ -356,7 +587,7 @@ mod tests {
     fn schlick_with_a_small_viewing_angle() {
         let s = Shape::glass_sphere();
         let r = Ray::new(point(0.0, 0.99, -2.0), vector(0.0, 0.0, 1.0));
-        let xs = Intersections::new(vec![Intersection::new(1.8589, s)]);
+        let xs = Intersections::new(vec![Intersection::new(1.8589, s.clone())]);
         let comps = xs[0].prepare_computations(r, &xs);
         let reflectance = comps.schlick();
         assert!(
@@ -1,16 +1,44 @@
+pub mod aov;
+pub mod bounds;
+pub mod bvh;
 pub mod camera;
 pub mod canvas;
+pub mod checkpoint;
+pub mod clip;
 pub mod color;
+#[cfg(feature = "scene")]
+pub mod config;
+pub mod denoise;
+pub mod diff;
+pub mod displacement;
+pub mod draw;
+pub mod frames;
+pub mod group;
+#[cfg(any(feature = "glam", feature = "nalgebra"))]
+mod interop;
 pub mod intersection;
 pub mod lights;
 pub mod material;
 pub mod matrix;
+pub mod medium;
+pub mod noise;
 pub mod object;
 pub mod pattern;
 pub mod plane;
+pub mod quaternion;
 pub mod ray;
+pub mod refractive_index;
+pub mod render_buffer;
+pub mod sampler;
+pub mod sampling;
+#[cfg(feature = "scene")]
+pub mod scene;
+pub mod scenes;
 pub mod sphere;
+pub mod stats;
+pub mod texture;
 pub mod transformations;
+pub mod triangle;
 pub mod tuple;
 pub mod util;
 pub mod world;
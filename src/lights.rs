@@ -1,6 +1,7 @@
 use crate::{color::Color, tuple::Tuple};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Light {
     pub position: Tuple,
     pub intensity: Color,
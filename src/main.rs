@@ -1,3 +1,162 @@
+#[cfg(feature = "scene")]
+use ray_tracer::{canvas::Canvas, config::Config, frames::FrameWriter, scene::Scene};
+
+#[cfg(feature = "scene")]
+use std::path::Path;
+
+#[cfg(feature = "scene")]
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+#[cfg(feature = "scene")]
 fn main() {
-    println!("Hello, world!");
+    if let Err(e) = run() {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(feature = "scene")]
+const USAGE: &str = "usage: ray-tracer [--watch] [--config <file>] [--output-dir <dir>] \
+[--threads <n>] [--samples <n>] [--gamma <g>] <scene.yml> [output]";
+
+#[cfg(feature = "scene")]
+fn run() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let (mut watch, mut config_file, mut flags) = (false, None, Config::default());
+    let mut positional = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--watch" => watch = true,
+            "--config" => config_file = Some(value::<String>(&mut args, &arg)?),
+            "--output-dir" => flags.output_dir = Some(value(&mut args, &arg)?),
+            "--threads" => flags.threads = Some(value(&mut args, &arg)?),
+            "--samples" => flags.samples = Some(value(&mut args, &arg)?),
+            "--gamma" => flags.gamma = Some(value(&mut args, &arg)?),
+            flag if flag.starts_with("--") => {
+                return Err(format!("unknown flag {flag}\n{USAGE}").into())
+            }
+            _ => positional.push(arg),
+        }
+    }
+    let (path, output) = match positional.as_slice() {
+        [scene] => (scene.as_str(), None),
+        [scene, output] => (scene.as_str(), Some(output.as_str())),
+        _ => return Err(USAGE.into()),
+    };
+    // flags override the configuration file, which overrides the defaults
+    let config = flags.or(match config_file {
+        Some(file) => Config::load(file)?,
+        None => Config::find()?,
+    });
+    #[cfg(feature = "parallel")]
+    if let Some(threads) = config.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()?;
+    }
+    if let Some(dir) = &config.output_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+    if watch {
+        return watch_scene(
+            path,
+            &config.output(output.unwrap_or("preview.png")),
+            &config,
+        );
+    }
+    let scene = Scene::load(path)?;
+    if scene.frames == 1 {
+        // a still image is saved to `output`, a file
+        render(scene, 1, &config)?.save(config.output(output.unwrap_or("scene.png")))?;
+        return Ok(());
+    }
+    // the frames of an animation go to `output`, a directory
+    let mut writer = FrameWriter::numbered(config.output(output.unwrap_or(".")), "frame");
+    for frame in 0..scene.frames {
+        eprintln!("frame {}/{}", frame + 1, scene.frames);
+        writer.write_frame(&render(Scene::load_frame(path, frame)?, 1, &config)?)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// Parses the value following `flag`.
+#[cfg(feature = "scene")]
+fn value<T: std::str::FromStr>(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<T> {
+    let value = args.next().ok_or_else(|| format!("{flag} needs a value"))?;
+    value
+        .parse()
+        .map_err(|_| format!("{flag}: invalid value `{value}`").into())
+}
+
+/// Renders the scene through its camera, `shrink` times smaller.
+#[cfg(feature = "scene")]
+fn render(scene: Scene, shrink: usize, config: &Config) -> Result<Canvas> {
+    let camera = scene.camera.ok_or("the scene has no camera")?;
+    let camera = config.camera(camera.with_resolution(
+        (camera.hsize() / shrink).max(1),
+        (camera.vsize() / shrink).max(1),
+    ));
+    Ok(camera.render(scene.world).with_transfer(config.transfer()))
+}
+
+/// Renders a preview of the scene each time a scene file next to it, or
+/// below, is saved.
+#[cfg(feature = "watch")]
+fn watch_scene(path: &str, output: &Path, config: &Config) -> Result<()> {
+    use std::{sync::mpsc, time::Duration, time::Instant};
+
+    use notify::{RecursiveMode, Watcher};
+
+    const PREVIEW_SHRINK: usize = 4;
+    let preview = || {
+        let start = Instant::now();
+        let rendered = Scene::load(path)
+            .map_err(Into::into)
+            .and_then(|scene| render(scene, PREVIEW_SHRINK, config))
+            .and_then(|canvas| Ok(canvas.save(output)?));
+        match rendered {
+            Ok(()) => eprintln!("{} rendered in {:.2?}", output.display(), start.elapsed()),
+            Err(e) => eprintln!("{e}"),
+        }
+    };
+    let is_scene = |p: &Path| {
+        matches!(
+            p.extension().and_then(|e| e.to_str()),
+            Some("yml" | "yaml" | "json" | "ron")
+        )
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    let directory = match Path::new(path).parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    watcher.watch(directory, RecursiveMode::Recursive)?;
+    preview();
+    for event in &rx {
+        let event = event?;
+        if !(event.kind.is_modify() || event.kind.is_create()) {
+            continue;
+        }
+        if !event.paths.iter().any(|p| is_scene(p)) {
+            continue;
+        }
+        // editors often write a file in several steps
+        while rx.recv_timeout(Duration::from_millis(100)).is_ok() {}
+        preview();
+    }
+    Ok(())
+}
+
+#[cfg(all(feature = "scene", not(feature = "watch")))]
+fn watch_scene(_: &str, _: &Path, _: &Config) -> Result<()> {
+    Err("watching scene files needs the `watch` feature".into())
+}
+
+#[cfg(not(feature = "scene"))]
+fn main() {
+    eprintln!("scene files need the `scene` feature");
+    std::process::exit(1);
 }
@@ -1,16 +1,145 @@
-use crate::{color::Color, lights::Light, object::Shape, pattern::Pattern, tuple::Tuple};
+use std::sync::Arc;
 
+use crate::{
+    color::Color,
+    lights::Light,
+    medium::Medium,
+    object::Shape,
+    pattern::Pattern,
+    refractive_index,
+    tuple::{vector, Tuple},
+    util::Float,
+};
+
+/// Bumps on a surface, faked by tilting its normal along the slopes of a
+/// height field instead of moving it.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BumpMap {
+    /// Height at each point of the surface: the brightness of the pattern,
+    /// procedural or an image wrapped with [`Pattern::texture_map`].
+    pub height: Pattern,
+    /// How much a unit slope of the height tilts the normal.
+    pub strength: Float,
+}
+
+impl BumpMap {
+    pub fn new(height: Pattern, strength: Float) -> Self {
+        Self { height, strength }
+    }
+
+    /// `normal`, the normal of `shape` at `point` in world space, tilted
+    /// against the slope of the height there.
+    pub fn perturb(&self, shape: &Shape, point: Tuple, normal: Tuple) -> Tuple {
+        const H: Float = 1e-4;
+        let height = |d: Tuple| self.height.pattern_at_shape(shape, point + d).mean();
+        let slope = |d: Tuple| (height(d) - height(-d)) / (2.0 * H);
+        let gradient = vector(
+            slope(vector(H, 0.0, 0.0)),
+            slope(vector(0.0, H, 0.0)),
+            slope(vector(0.0, 0.0, H)),
+        );
+        // only the part of the gradient along the surface tilts it
+        let along_surface = gradient - normal * (gradient ^ normal);
+        (normal - along_surface * self.strength).norm()
+    }
+}
+
+/// How a material treats the back of its surface, the side its normal
+/// points away from, seen from inside closed shapes or behind open ones.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BackFace {
+    /// Shaded like the front, facing the viewer.
+    #[default]
+    Same,
+    /// Invisible: rays go through the back of the surface. It still casts
+    /// shadows.
+    Cull,
+    /// Shaded with another material.
+    Material(Arc<Material>),
+}
+
+/// What a material is at a given point of a surface, once its pattern and
+/// maps are looked up.
 #[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Surface {
+    pub color: Color,
+    pub ambient: Float,
+    pub diffuse: Float,
+    pub specular: Float,
+    pub shininess: Float,
+    pub reflective: Float,
+    pub transparency: Float,
+    pub emissive: Color,
+}
+
+impl Surface {
+    /// `self` moved a share `t` of the way towards `other`.
+    pub fn mix(&self, other: &Surface, t: Float) -> Surface {
+        let lerp = |a: Float, b: Float| a + (b - a) * t;
+        Surface {
+            color: self.color + (other.color - self.color) * t,
+            ambient: lerp(self.ambient, other.ambient),
+            diffuse: lerp(self.diffuse, other.diffuse),
+            specular: lerp(self.specular, other.specular),
+            shininess: lerp(self.shininess, other.shininess),
+            reflective: lerp(self.reflective, other.reflective),
+            transparency: lerp(self.transparency, other.transparency),
+            emissive: self.emissive + (other.emissive - self.emissive) * t,
+        }
+    }
+}
+
+/// A material laid over another one, see [`Material::blend_with`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Layer {
+    pub material: Material,
+    /// Share of the layer at each point: the brightness of the pattern.
+    pub mask: Pattern,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Material {
     pub color: Color,
-    pub ambient: f64,
-    pub diffuse: f64,
-    pub specular: f64,
-    pub shininess: f64,
-    pub reflective: f64,
-    pub transparency: f64,
-    pub refractive_index: f64,
+    pub ambient: Float,
+    pub diffuse: Float,
+    pub specular: Float,
+    pub shininess: Float,
+    pub reflective: Float,
+    pub transparency: Float,
+    pub refractive_index: Float,
     pub pattern: Option<Pattern>,
+    /// Light given off by the surface, whatever lights it: added as is by
+    /// the shading and picked up by the path tracer.
+    pub emissive: Color,
+    /// Makes the shape a volume: its inside is filled with this medium and
+    /// its surface is not shaded.
+    pub medium: Option<Medium>,
+    pub bump_map: Option<BumpMap>,
+    /// Share of the light hitting the back of the surface that comes
+    /// through diffusely, for thin objects like leaves and lampshades.
+    pub translucency: Float,
+    /// How much of the light the shape stops when it casts a shadow, from
+    /// 0 (no shadow) to 1, whatever its transparency.
+    pub shadow_opacity: Float,
+    /// Scale `specular`, `shininess` and `transparency` by their brightness
+    /// at each point, e.g. for shiny wet patches on a dull floor.
+    pub specular_map: Option<Pattern>,
+    pub shininess_map: Option<Pattern>,
+    pub transparency_map: Option<Pattern>,
+    pub back_face: BackFace,
+    /// Another material showing through this one where its mask is bright.
+    /// Only the shading attributes blend, the surface keeps the shape,
+    /// refractive index and flags of the base material.
+    pub layer: Option<Arc<Layer>>,
+    /// Share of the light absorbed per unit of distance travelled inside
+    /// the shape, per channel, following Beer's law: thick glass gets
+    /// darker and colored liquids deeper. Black for clear materials.
+    pub absorption: Color,
 }
 
 impl Default for Material {
@@ -24,29 +153,119 @@ impl Default for Material {
             pattern: None,
             reflective: 0.0,
             transparency: 0.0,
-            refractive_index: 1.0,
+            refractive_index: refractive_index::VACUUM,
+            emissive: Color::black(),
+            medium: None,
+            bump_map: None,
+            translucency: 0.0,
+            shadow_opacity: 1.0,
+            specular_map: None,
+            shininess_map: None,
+            transparency_map: None,
+            back_face: BackFace::Same,
+            layer: None,
+            absorption: Color::black(),
         }
     }
 }
 
 impl Material {
+    /// Surface color at `point`, from the pattern if there is one.
+    pub fn color_at(&self, shape: &Shape, point: Tuple) -> Color {
+        self.filtered_color_at(shape, point, 0.0)
+    }
+
+    /// Surface color averaged over a `footprint` wide area around `point`,
+    /// as far as the pattern supports it.
+    pub fn filtered_color_at(&self, shape: &Shape, point: Tuple, footprint: Float) -> Color {
+        match &self.pattern {
+            Some(p) => p.filtered_at_shape(shape, point, footprint),
+            None => self.color,
+        }
+    }
+
+    /// The material at `point`, its pattern and maps filtered over a
+    /// `footprint` wide area.
+    pub fn surface_at(&self, shape: &Shape, point: Tuple, footprint: Float) -> Surface {
+        let mapped = |value: Float, map: &Option<Pattern>| match map {
+            Some(m) => value * m.filtered_at_shape(shape, point, footprint).mean(),
+            None => value,
+        };
+        let surface = Surface {
+            color: self.filtered_color_at(shape, point, footprint),
+            ambient: self.ambient,
+            diffuse: self.diffuse,
+            specular: mapped(self.specular, &self.specular_map),
+            shininess: mapped(self.shininess, &self.shininess_map),
+            reflective: self.reflective,
+            transparency: mapped(self.transparency, &self.transparency_map),
+            emissive: self.emissive,
+        };
+        match &self.layer {
+            Some(layer) => {
+                let t = layer.mask.filtered_at_shape(shape, point, footprint).mean();
+                if t <= 0.0 {
+                    return surface;
+                }
+                surface.mix(&layer.material.surface_at(shape, point, footprint), t)
+            }
+            None => surface,
+        }
+    }
+
+    /// Transparency at `point`, after the transparency map and layers.
+    pub fn transparency_at(&self, shape: &Shape, point: Tuple) -> Float {
+        if self.transparency_map.is_none() && self.layer.is_none() {
+            return self.transparency;
+        }
+        self.surface_at(shape, point, 0.0).transparency
+    }
+
+    /// Even mix of `a` and `b`, with a share `t` of `b`.
+    pub fn blend(a: Material, b: Material, t: Float) -> Material {
+        Self::blend_with(a, b, Pattern::solid(Color::new(t, t, t)))
+    }
+
+    /// `b` laid over `a` where `mask` is bright, e.g. rust spreading over
+    /// clean metal along a noise pattern.
+    pub fn blend_with(a: Material, b: Material, mask: Pattern) -> Material {
+        Material {
+            layer: Some(Arc::new(Layer { material: b, mask })),
+            ..a
+        }
+    }
+
     pub fn lighting(
         &self,
         light: Light,
-        shape: Shape,
+        shape: &Shape,
         point: Tuple,
         eyev: Tuple,
         normalv: Tuple,
         in_shadow: bool,
+    ) -> Color {
+        let surface = self.surface_at(shape, point, 0.0);
+        let visibility = if in_shadow { 0.0 } else { 1.0 };
+        self.shade(light, &surface, point, eyev, normalv, visibility)
+    }
+
+    /// Phong shading of the material at a point, looked up by the caller
+    /// with [`Material::surface_at`]. `visibility` is the fraction of the
+    /// light getting to the point, 0 in full shadow.
+    pub fn shade(
+        &self,
+        light: Light,
+        surface: &Surface,
+        point: Tuple,
+        eyev: Tuple,
+        normalv: Tuple,
+        visibility: Float,
     ) -> Color {
         // combine light and material color
-        let effective_color = match self.pattern {
-            Some(p) => p.pattern_at_shape(shape, point),
-            None => self.color,
-        } * light.intensity;
+        let effective_color = surface.color * light.intensity;
         // find direction to the light source
-        let ambient = effective_color * self.ambient;
-        if in_shadow {
+        let ambient = effective_color * surface.ambient + surface.emissive;
+        if visibility <= 0.0 {
             return ambient;
         }
         let lightv = (light.position - point).norm();
@@ -54,35 +273,36 @@ impl Material {
         let diffuse;
         let specular;
         if ligtht_dot_normal < 0.0 {
-            diffuse = Color::black();
+            // light wrapping around from the far side
+            diffuse = effective_color * self.translucency * -ligtht_dot_normal;
             specular = Color::black();
         } else {
-            diffuse = effective_color * self.diffuse * ligtht_dot_normal;
+            diffuse = effective_color * surface.diffuse * ligtht_dot_normal;
 
             let reflect_v = (-lightv).reflect(&normalv);
             let relect_dot_eye = reflect_v ^ eyev;
             if relect_dot_eye <= 0.0 {
                 specular = Color::black();
             } else {
-                let factor = relect_dot_eye.powf(self.shininess);
-                specular = light.intensity * self.specular * factor;
+                let factor = relect_dot_eye.powf(surface.shininess);
+                specular = light.intensity * surface.specular * factor;
             }
         }
 
-        ambient + diffuse + specular
+        ambient + (diffuse + specular) * visibility
     }
 
-    pub fn reflective(mut self, reflective: f64) -> Self {
+    pub fn reflective(mut self, reflective: Float) -> Self {
         self.reflective = reflective;
         self
     }
 
-    pub fn transparency(mut self, transparency: f64) -> Self {
+    pub fn transparency(mut self, transparency: Float) -> Self {
         self.transparency = transparency;
         self
     }
 
-    pub fn refractive_index(mut self, refractive_index: f64) -> Self {
+    pub fn refractive_index(mut self, refractive_index: Float) -> Self {
         self.refractive_index = refractive_index;
         self
     }
@@ -92,20 +312,92 @@ impl Material {
         self
     }
 
-    pub fn ambient(mut self, ambiant: f64) -> Self {
+    pub fn ambient(mut self, ambiant: Float) -> Self {
         self.ambient = ambiant;
         self
     }
+
+    pub fn diffuse(mut self, diffuse: Float) -> Self {
+        self.diffuse = diffuse;
+        self
+    }
+
+    pub fn specular(mut self, specular: Float) -> Self {
+        self.specular = specular;
+        self
+    }
+
+    pub fn shininess(mut self, shininess: Float) -> Self {
+        self.shininess = shininess;
+        self
+    }
+
+    pub fn pattern(mut self, pattern: Pattern) -> Self {
+        self.pattern = Some(pattern);
+        self
+    }
+
+    pub fn emissive(mut self, emissive: Color) -> Self {
+        self.emissive = emissive;
+        self
+    }
+
+    pub fn medium(mut self, medium: Medium) -> Self {
+        self.medium = Some(medium);
+        self
+    }
+
+    pub fn translucency(mut self, translucency: Float) -> Self {
+        self.translucency = translucency;
+        self
+    }
+
+    pub fn specular_map(mut self, map: Pattern) -> Self {
+        self.specular_map = Some(map);
+        self
+    }
+
+    pub fn shininess_map(mut self, map: Pattern) -> Self {
+        self.shininess_map = Some(map);
+        self
+    }
+
+    pub fn transparency_map(mut self, map: Pattern) -> Self {
+        self.transparency_map = Some(map);
+        self
+    }
+
+    pub fn absorption(mut self, absorption: Color) -> Self {
+        self.absorption = absorption;
+        self
+    }
+
+    pub fn shadow_opacity(mut self, shadow_opacity: Float) -> Self {
+        self.shadow_opacity = shadow_opacity;
+        self
+    }
+
+    pub fn back_face(mut self, back_face: BackFace) -> Self {
+        self.back_face = back_face;
+        self
+    }
+
+    pub fn bump_map(mut self, height: Pattern, strength: Float) -> Self {
+        self.bump_map = Some(BumpMap::new(height, strength));
+        self
+    }
 }
 
 #[cfg(test)]
 mod test {
     use crate::{
         color::Color,
+        intersection::Intersectable,
         lights::Light,
         object::Shape,
         pattern::Pattern,
         tuple::{point, vector},
+        util::consts::SQRT_2,
     };
 
     use super::Material;
@@ -119,7 +411,7 @@ mod test {
         let in_shadow = true;
         let result = Material::default().lighting(
             light,
-            Shape::sphere(),
+            &Shape::sphere(),
             position,
             eyev,
             normalv,
@@ -129,6 +421,76 @@ mod test {
     }
 
     #[test]
+    fn emissive_surfaces_glow_even_in_shadow() {
+        let glow = Color::new(0.5, 0.4, 0.3);
+        let m = Material::default().emissive(glow);
+        let light = Light::new(point(0.0, 0.0, -10.0), Color::white());
+        let (eyev, normalv) = (vector(0.0, 0.0, -1.0), vector(0.0, 0.0, -1.0));
+        let at = |in_shadow| {
+            m.lighting(
+                light,
+                &Shape::sphere(),
+                point(0.0, 0.0, 0.0),
+                eyev,
+                normalv,
+                in_shadow,
+            )
+        };
+        assert_eq!(at(true), Color::new(0.6, 0.5, 0.4));
+        assert_eq!(at(false), Color::new(2.4, 2.3, 2.2));
+    }
+
+    #[test]
+    fn translucent_surfaces_let_light_through_from_behind() {
+        let light = Light::new(point(0.0, 0.0, 10.0), Color::white());
+        let (eyev, normalv) = (vector(0.0, 0.0, -1.0), vector(0.0, 0.0, -1.0));
+        let at = |m: Material| {
+            m.lighting(
+                light,
+                &Shape::sphere(),
+                point(0.0, 0.0, 0.0),
+                eyev,
+                normalv,
+                false,
+            )
+        };
+        assert_eq!(at(Material::default()), Color::new(0.1, 0.1, 0.1));
+        assert_eq!(
+            at(Material::default().translucency(0.5)),
+            Color::new(0.6, 0.6, 0.6)
+        );
+    }
+
+    #[test]
+    fn maps_scale_attributes_per_point() {
+        let stripes = Pattern::stripped(Color::white(), Color::new(0.5, 0.5, 0.5));
+        let m = Material::default()
+            .transparency(0.8)
+            .specular_map(stripes.clone())
+            .shininess_map(stripes.clone())
+            .transparency_map(stripes);
+        let s = Shape::sphere();
+        let wet = m.surface_at(&s, point(0.5, 0.0, 0.0), 0.0);
+        assert_eq!(
+            (wet.specular, wet.shininess, wet.transparency),
+            (0.9, 200.0, 0.8)
+        );
+        let dry = m.surface_at(&s, point(1.5, 0.0, 0.0), 0.0);
+        assert_eq!(
+            (dry.specular, dry.shininess, dry.transparency),
+            (0.45, 100.0, 0.4)
+        );
+        assert_eq!(m.transparency_at(&s, point(1.5, 0.0, 0.0)), 0.4);
+        // the highlight fades with the map
+        let light = Light::new(point(0.0, 0.0, -10.0), Color::white());
+        let (eyev, normalv) = (vector(0.0, 0.0, -1.0), vector(0.0, 0.0, -1.0));
+        let lit = |surface| m.shade(light, &surface, point(0.0, 0.0, 0.0), eyev, normalv, 1.0);
+        assert_eq!(lit(wet), Color::new(1.9, 1.9, 1.9));
+        assert_eq!(lit(dry), Color::new(1.45, 1.45, 1.45));
+    }
+
+    #[test]
+    #[allow(clippy::field_reassign_with_default)]
     fn lighting_with_a_pattern_applied() {
         let mut material = Material::default();
         material.pattern = Some(Pattern::stripped(Color::white(), Color::black()));
@@ -140,7 +502,7 @@ mod test {
         let light = Light::new(point(0.0, 0.0, -10.0), Color::white());
         let c1 = material.lighting(
             light,
-            Shape::sphere(),
+            &Shape::sphere(),
             point(0.9, 0.0, 0.0),
             eyev,
             normalv,
@@ -148,7 +510,7 @@ mod test {
         );
         let c2 = material.lighting(
             light,
-            Shape::sphere(),
+            &Shape::sphere(),
             point(1.1, 0.0, 0.0),
             eyev,
             normalv,
@@ -158,9 +520,108 @@ mod test {
         assert_eq!(c2, Color::black());
     }
 
+    #[test]
+    fn bump_maps_tilt_the_normal_down_the_slope() {
+        let plane = Shape::plane().with_material(
+            Material::default().bump_map(Pattern::gradient(Color::black(), Color::white()), 1.0),
+        );
+        let n = plane.normal_at(&point(0.5, 0.0, 0.5));
+        assert_eq!(n, vector(-SQRT_2 / 2.0, SQRT_2 / 2.0, 0.0));
+        // flat heights leave it alone
+        let flat = Shape::plane()
+            .with_material(Material::default().bump_map(Pattern::solid(Color::white()), 1.0));
+        assert_eq!(flat.normal_at(&point(0.5, 0.0, 0.5)), vector(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn building_a_material() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let m = Material::default()
+            .color(red)
+            .ambient(0.2)
+            .diffuse(0.5)
+            .specular(0.3)
+            .shininess(50.0)
+            .reflective(0.4)
+            .transparency(0.6)
+            .refractive_index(1.33)
+            .pattern(Pattern::solid(red));
+        let expected = Material {
+            color: red,
+            ambient: 0.2,
+            diffuse: 0.5,
+            specular: 0.3,
+            shininess: 50.0,
+            reflective: 0.4,
+            transparency: 0.6,
+            refractive_index: 1.33,
+            pattern: Some(Pattern::solid(red)),
+            ..Default::default()
+        };
+        assert_eq!(m, expected);
+    }
+
+    #[test]
+    fn blending_two_materials() {
+        let rust = Material::default()
+            .color(Color::new(0.6, 0.3, 0.1))
+            .specular(0.1);
+        let metal = Material::default().reflective(0.8).specular(0.9);
+        let s = Shape::sphere();
+        let half = Material::blend(metal.clone(), rust.clone(), 0.5).surface_at(
+            &s,
+            point(0.0, 0.0, 0.0),
+            0.0,
+        );
+        assert_eq!(half.color, Color::new(0.8, 0.65, 0.55));
+        assert!((half.specular - 0.5).abs() < 1e-9);
+        assert!((half.reflective - 0.4).abs() < 1e-9);
+
+        let patchy = Material::blend_with(
+            metal,
+            rust,
+            Pattern::stripped(Color::black(), Color::white()),
+        );
+        let clean = patchy.surface_at(&s, point(0.5, 0.0, 0.0), 0.0);
+        let rusty = patchy.surface_at(&s, point(1.5, 0.0, 0.0), 0.0);
+        assert_eq!((clean.color, clean.reflective), (Color::white(), 0.8));
+        assert_eq!(
+            (rusty.color, rusty.reflective),
+            (Color::new(0.6, 0.3, 0.1), 0.0)
+        );
+    }
+
+    #[test]
+    fn clones_share_layers_and_patterns() {
+        let m = Material::blend_with(
+            Material::default(),
+            Material::default().pattern(Pattern::gradient(Color::black(), Color::white())),
+            Pattern::gradient(Color::black(), Color::white()),
+        );
+        let copy = m.clone();
+        // shapes are cloned into every intersection, their materials must
+        // not be copied deeply
+        assert!(std::sync::Arc::ptr_eq(
+            m.layer.as_ref().unwrap(),
+            copy.layer.as_ref().unwrap()
+        ));
+    }
+
     #[test]
     fn relectivity_default_material() {
         let m = Material::default();
         assert_eq!(m.reflective, 0.0);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn materials_round_trip_through_serde() {
+        let m = Material::default()
+            .color(Color::new(0.2, 0.4, 0.6))
+            .reflective(0.3)
+            .medium(crate::medium::Medium::new(0.5, Color::white()));
+        let json = serde_json::to_string(&m).unwrap();
+        assert!(json.contains("\"color\":[0.2,0.4,0.6]"), "{json}");
+        assert_eq!(serde_json::from_str::<Material>(&json).unwrap(), m);
+    }
 }
@@ -1,13 +1,15 @@
 use std::{
     fmt::Display,
-    ops::{Index, IndexMut, Mul},
-    usize,
+    ops::{Add, Index, IndexMut, Mul, Neg, Sub},
 };
 
 use crate::{
-    transformations::{rot_x, rot_y, rot_z, scaling, shearing, translation},
-    tuple::Tuple,
-    util::flt_eq,
+    quaternion::Quaternion,
+    transformations::{
+        reflect_across, rot_x, rot_y, rot_z, rotation_axis_angle, scaling, shearing, translation,
+    },
+    tuple::{vector, Tuple},
+    util::{flt_eq, Float},
 };
 
 pub type Mat4 = Matrix<4>;
@@ -16,14 +18,42 @@ pub type Mat2 = Matrix<2>;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Matrix<const N: usize> {
-    data: [[f64; N]; N],
+    data: [[Float; N]; N],
 }
 
-pub trait MatBase: Default + IndexMut<(usize, usize), Output = f64> {
+pub trait MatBase: Default + IndexMut<(usize, usize), Output = Float> {
+    /// Entries are not finite when the matrix is singular, see
+    /// [`MatBase::try_inverse`].
     fn inverse(&self) -> Self;
-    fn minor(&self, row: usize, col: usize) -> f64;
-    fn cofactor(&self, row: usize, col: usize) -> f64;
-    fn det(&self) -> f64;
+    /// The inverse, or `None` when the matrix is singular.
+    fn try_inverse(&self) -> Option<Self>;
+    fn minor(&self, row: usize, col: usize) -> Float;
+    fn cofactor(&self, row: usize, col: usize) -> Float;
+    fn det(&self) -> Float;
+}
+
+/// Serialized as its rows.
+#[cfg(feature = "serde")]
+impl<const N: usize> serde::Serialize for Matrix<N> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.data.iter().map(|row| row.as_slice()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const N: usize> serde::Deserialize<'de> for Matrix<N> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        let rows = Vec::<Vec<Float>>::deserialize(deserializer)?;
+        if rows.len() != N || rows.iter().any(|row| row.len() != N) {
+            return Err(D::Error::custom(format!("expected {N} rows of {N} values")));
+        }
+        let mut m = Self::default();
+        for (i, row) in rows.iter().enumerate() {
+            m.data[i].copy_from_slice(row);
+        }
+        Ok(m)
+    }
 }
 
 impl<const N: usize> Default for Matrix<N> {
@@ -35,19 +65,19 @@ impl<const N: usize> Default for Matrix<N> {
 }
 
 impl Matrix<2> {
-    pub const fn new(data: [f64; 4]) -> Matrix<2> {
+    pub const fn new(data: [Float; 4]) -> Matrix<2> {
         Self {
             data: [[data[0], data[1]], [data[2], data[3]]],
         }
     }
 
-    pub fn det(&self) -> f64 {
+    pub fn det(&self) -> Float {
         self.data[0][0] * self.data[1][1] - self.data[1][0] * self.data[0][1]
     }
 }
 
 impl Matrix<3> {
-    pub const fn new(data: [f64; 9]) -> Matrix<3> {
+    pub const fn new(data: [Float; 9]) -> Matrix<3> {
         Self {
             data: [
                 [data[0], data[1], data[2]],
@@ -82,19 +112,22 @@ impl MatBase for Mat3 {
         }
         m
     }
-    fn minor(&self, row: usize, col: usize) -> f64 {
+    fn try_inverse(&self) -> Option<Self> {
+        is_invertible(self.det()).then(|| self.inverse())
+    }
+    fn minor(&self, row: usize, col: usize) -> Float {
         self.submatrix(row, col).det()
     }
-    fn cofactor(&self, row: usize, col: usize) -> f64 {
+    fn cofactor(&self, row: usize, col: usize) -> Float {
         self.minor(row, col) * (if (row + col) & 1 == 1 { -1.0 } else { 1.0 })
     }
-    fn det(&self) -> f64 {
+    fn det(&self) -> Float {
         (0..self.data.len()).fold(0.0, |acc, col| acc + self[(0, col)] * self.cofactor(0, col))
     }
 }
 
 impl Matrix<4> {
-    pub const fn new(data: [f64; 16]) -> Matrix<4> {
+    pub const fn new(data: [Float; 16]) -> Matrix<4> {
         Self {
             data: [
                 [data[0], data[1], data[2], data[3]],
@@ -105,22 +138,6 @@ impl Matrix<4> {
         }
     }
 
-    pub fn transpose(self) -> Self {
-        Self {
-            data: [
-                [self[(0, 0)], self[(1, 0)], self[(2, 0)], self[(3, 0)]],
-                [self[(0, 1)], self[(1, 1)], self[(2, 1)], self[(3, 1)]],
-                [self[(0, 2)], self[(1, 2)], self[(2, 2)], self[(3, 2)]],
-                [self[(0, 3)], self[(1, 3)], self[(2, 3)], self[(3, 3)]],
-            ],
-        }
-    }
-    pub const fn identity() -> Matrix<4> {
-        Self::new([
-            1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
-        ])
-    }
-
     pub fn submatrix(&self, row: usize, col: usize) -> Matrix<3> {
         let mut v = Vec::with_capacity(9);
         for r in 0..4 {
@@ -132,59 +149,211 @@ impl Matrix<4> {
         }
         Matrix::<3>::new(v.try_into().unwrap())
     }
-    fn as_array(&self) -> [f64; 16] {
-        unsafe { std::mem::transmute(self.data) }
-    }
-
-    pub fn translation(self, x: f64, y: f64, z: f64) -> Self {
+    pub fn translation(self, x: Float, y: Float, z: Float) -> Self {
         translation(x, y, z) * self
     }
-    pub fn scaling(self, x: f64, y: f64, z: f64) -> Self {
+    pub fn scaling(self, x: Float, y: Float, z: Float) -> Self {
         scaling(x, y, z) * self
     }
 
-    pub fn rot_x(self, angle: f64) -> Self {
+    pub fn rot_x(self, angle: Float) -> Self {
         rot_x(angle) * self
     }
-    pub fn rot_y(self, angle: f64) -> Self {
+    pub fn rot_y(self, angle: Float) -> Self {
         rot_y(angle) * self
     }
-    pub fn rot_z(self, angle: f64) -> Self {
+    pub fn rot_z(self, angle: Float) -> Self {
         rot_z(angle) * self
     }
 
-    pub fn shearing(self, xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Self {
+    pub fn rotation_axis_angle(self, axis: Tuple, angle: Float) -> Self {
+        rotation_axis_angle(axis, angle) * self
+    }
+
+    pub fn reflect_across(self, plane_normal: Tuple) -> Self {
+        reflect_across(plane_normal) * self
+    }
+
+    pub fn shearing(
+        self,
+        xy: Float,
+        xz: Float,
+        yx: Float,
+        yz: Float,
+        zx: Float,
+        zy: Float,
+    ) -> Self {
         shearing(xy, xz, yx, yz, zx, zy) * self
     }
+
+    /// Translation, rotation and scale the matrix applies, scaling first.
+    /// Shearing is not recovered, and the scale must not be zero.
+    pub fn decompose(&self) -> (Tuple, Quaternion, Tuple) {
+        let column = |c: usize| Tuple::new(self[(0, c)], self[(1, c)], self[(2, c)], 0.0);
+        let translation = column(3);
+        let mut scale = vector(column(0).mag(), column(1).mag(), column(2).mag());
+        // a mirroring is folded into the scale along x
+        if column(0) ^ column(1).cross(column(2)) < 0.0 {
+            scale.x = -scale.x;
+        }
+        let mut rotation = Mat4::identity();
+        for (c, s) in [scale.x, scale.y, scale.z].into_iter().enumerate() {
+            for r in 0..3 {
+                rotation[(r, c)] = self[(r, c)] / s;
+            }
+        }
+        (translation, Quaternion::from_matrix(&rotation), scale)
+    }
+
+    /// The transform `t` of the way to `other`, interpolating translation and
+    /// scale linearly and rotation along the shortest arc, for keyframed
+    /// objects and cameras.
+    pub fn lerp_to(&self, other: &Mat4, t: Float) -> Mat4 {
+        let (t0, r0, s0) = self.decompose();
+        let (t1, r1, s1) = other.decompose();
+        let translate = t0 + (t1 - t0) * t;
+        let scale = s0 + (s1 - s0) * t;
+        translation(translate.x, translate.y, translate.z)
+            * r0.slerp(r1, t).to_matrix()
+            * scaling(scale.x, scale.y, scale.z)
+    }
 }
 
 impl MatBase for Mat4 {
     fn inverse(&self) -> Self {
+        let (s, c) = self.pair_dets();
+        self.adjugate(s, c).divided(det_from_pairs(s, c))
+    }
+
+    fn try_inverse(&self) -> Option<Self> {
+        let (s, c) = self.pair_dets();
+        let det = det_from_pairs(s, c);
+        is_invertible(det).then(|| self.adjugate(s, c).divided(det))
+    }
+
+    fn minor(&self, row: usize, col: usize) -> Float {
+        self.submatrix(row, col).det()
+    }
+    fn cofactor(&self, row: usize, col: usize) -> Float {
+        self.minor(row, col) * (if (row + col) & 1 == 1 { -1.0 } else { 1.0 })
+    }
+
+    fn det(&self) -> Float {
+        let (s, c) = self.pair_dets();
+        det_from_pairs(s, c)
+    }
+}
+
+impl Mat4 {
+    /// Determinants of the 2x2 matrices in the top two rows (`s`) and in the
+    /// bottom two rows (`c`), from which the cofactors are built.
+    fn pair_dets(&self) -> ([Float; 6], [Float; 6]) {
+        let a = &self.data;
+        let s = [
+            a[0][0] * a[1][1] - a[1][0] * a[0][1],
+            a[0][0] * a[1][2] - a[1][0] * a[0][2],
+            a[0][0] * a[1][3] - a[1][0] * a[0][3],
+            a[0][1] * a[1][2] - a[1][1] * a[0][2],
+            a[0][1] * a[1][3] - a[1][1] * a[0][3],
+            a[0][2] * a[1][3] - a[1][2] * a[0][3],
+        ];
+        let c = [
+            a[2][0] * a[3][1] - a[3][0] * a[2][1],
+            a[2][0] * a[3][2] - a[3][0] * a[2][2],
+            a[2][0] * a[3][3] - a[3][0] * a[2][3],
+            a[2][1] * a[3][2] - a[3][1] * a[2][2],
+            a[2][1] * a[3][3] - a[3][1] * a[2][3],
+            a[2][2] * a[3][3] - a[3][2] * a[2][3],
+        ];
+        (s, c)
+    }
+
+    /// Transposed matrix of cofactors.
+    fn adjugate(&self, s: [Float; 6], c: [Float; 6]) -> Self {
+        let a = &self.data;
+        Self {
+            data: [
+                [
+                    a[1][1] * c[5] - a[1][2] * c[4] + a[1][3] * c[3],
+                    -a[0][1] * c[5] + a[0][2] * c[4] - a[0][3] * c[3],
+                    a[3][1] * s[5] - a[3][2] * s[4] + a[3][3] * s[3],
+                    -a[2][1] * s[5] + a[2][2] * s[4] - a[2][3] * s[3],
+                ],
+                [
+                    -a[1][0] * c[5] + a[1][2] * c[2] - a[1][3] * c[1],
+                    a[0][0] * c[5] - a[0][2] * c[2] + a[0][3] * c[1],
+                    -a[3][0] * s[5] + a[3][2] * s[2] - a[3][3] * s[1],
+                    a[2][0] * s[5] - a[2][2] * s[2] + a[2][3] * s[1],
+                ],
+                [
+                    a[1][0] * c[4] - a[1][1] * c[2] + a[1][3] * c[0],
+                    -a[0][0] * c[4] + a[0][1] * c[2] - a[0][3] * c[0],
+                    a[3][0] * s[4] - a[3][1] * s[2] + a[3][3] * s[0],
+                    -a[2][0] * s[4] + a[2][1] * s[2] - a[2][3] * s[0],
+                ],
+                [
+                    -a[1][0] * c[3] + a[1][1] * c[1] - a[1][2] * c[0],
+                    a[0][0] * c[3] - a[0][1] * c[1] + a[0][2] * c[0],
+                    -a[3][0] * s[3] + a[3][1] * s[1] - a[3][2] * s[0],
+                    a[2][0] * s[3] - a[2][1] * s[1] + a[2][2] * s[0],
+                ],
+            ],
+        }
+    }
+}
+
+impl<const N: usize> Matrix<N> {
+    pub const fn identity() -> Self {
+        let mut data = [[0.0; N]; N];
+        let mut i = 0;
+        while i < N {
+            data[i][i] = 1.0;
+            i += 1;
+        }
+        Self { data }
+    }
+
+    pub fn transpose(self) -> Self {
         let mut m = Self::default();
-        let det = self.det();
-        for r in 0..4 {
-            for c in 0..4 {
-                // transpose with c<-->r
-                m[(c, r)] = self.cofactor(r, c) / det;
+        for row in 0..N {
+            for col in 0..N {
+                m[(col, row)] = self[(row, col)];
             }
         }
         m
     }
 
-    fn minor(&self, row: usize, col: usize) -> f64 {
-        self.submatrix(row, col).det()
+    fn map(self, f: impl Fn(Float) -> Float) -> Self {
+        Self {
+            data: self.data.map(|row| row.map(&f)),
+        }
     }
-    fn cofactor(&self, row: usize, col: usize) -> f64 {
-        self.minor(row, col) * (if (row + col) & 1 == 1 { -1.0 } else { 1.0 })
+
+    fn zip(self, other: Self, f: impl Fn(Float, Float) -> Float) -> Self {
+        let mut m = self;
+        for row in 0..N {
+            for col in 0..N {
+                m[(row, col)] = f(self[(row, col)], other[(row, col)]);
+            }
+        }
+        m
     }
 
-    fn det(&self) -> f64 {
-        (0..self.data.len()).fold(0.0, |acc, col| acc + self[(0, col)] * self.cofactor(0, col))
+    fn divided(self, by: Float) -> Self {
+        self.map(|x| x / by)
     }
 }
 
+fn det_from_pairs(s: [Float; 6], c: [Float; 6]) -> Float {
+    s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]
+}
+
+fn is_invertible(det: Float) -> bool {
+    det != 0.0 && det.is_finite()
+}
+
 impl<const N: usize> Index<(usize, usize)> for Matrix<N> {
-    type Output = f64;
+    type Output = Float;
 
     fn index(&self, index: (usize, usize)) -> &Self::Output {
         &self.data[index.0][index.1]
@@ -204,16 +373,45 @@ impl<const N: usize> Mul<Matrix<N>> for Matrix<N> {
         let mut m = Self::default();
         for row in 0..N {
             for col in 0..N {
-                m[(row, col)] = self[(row, 0)] * rhs[(0, col)]
-                    + self[(row, 1)] * rhs[(1, col)]
-                    + self[(row, 2)] * rhs[(2, col)]
-                    + self[(row, 3)] * rhs[(3, col)];
+                m[(row, col)] = (0..N).map(|k| self[(row, k)] * rhs[(k, col)]).sum();
             }
         }
         m
     }
 }
 
+impl<const N: usize> Mul<Float> for Matrix<N> {
+    type Output = Self;
+
+    fn mul(self, rhs: Float) -> Self::Output {
+        self.map(|x| x * rhs)
+    }
+}
+
+impl<const N: usize> Add for Matrix<N> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.zip(rhs, |a, b| a + b)
+    }
+}
+
+impl<const N: usize> Sub for Matrix<N> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.zip(rhs, |a, b| a - b)
+    }
+}
+
+impl<const N: usize> Neg for Matrix<N> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        self.map(|x| -x)
+    }
+}
+
 impl<const N: usize> PartialEq for Matrix<N> {
     fn eq(&self, other: &Self) -> bool {
         for x in 0..N {
@@ -230,6 +428,7 @@ impl<const N: usize> PartialEq for Matrix<N> {
 impl Mul<Tuple> for Matrix<4> {
     type Output = Tuple;
 
+    #[allow(clippy::needless_range_loop)]
     fn mul(self, rhs: Tuple) -> Self::Output {
         let mut res = [0.0; 4];
         for row in 0..4 {
@@ -239,18 +438,12 @@ impl Mul<Tuple> for Matrix<4> {
     }
 }
 
-impl Display for Mat4 {
+impl<const N: usize> Display for Matrix<N> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let longest = self.as_array().map(|flt| format!("{:5.5}", flt));
-        for i in 0..4 {
+        for row in &self.data {
             write!(f, "| ")?;
-            for j in 0..4 {
-                write!(
-                    f,
-                    "{}{} |",
-                    if self.data[i][j] >= 0.0 { " " } else { "" },
-                    longest[i * 4 + j]
-                )?;
+            for &value in row {
+                write!(f, "{}{:5.5} |", if value >= 0.0 { " " } else { "" }, value)?;
             }
             writeln!(f)?;
         }
@@ -262,10 +455,11 @@ impl Display for Mat4 {
 mod test {
     use crate::{
         matrix::{Mat4, MatBase},
+        transformations::rot_y,
         tuple::Tuple,
     };
 
-    use super::{Mat3, Matrix};
+    use super::{Mat2, Mat3, Matrix};
 
     #[test]
     fn test_eq() {
@@ -333,6 +527,33 @@ mod test {
         assert_eq!(a.transpose(), t_a);
     }
 
+    #[test]
+    fn test_mul_small() {
+        let a = Mat2::new([1.0, 2.0, 3.0, 4.0]);
+        let b = Mat2::new([0.0, 1.0, 1.0, 0.0]);
+        assert_eq!(a * b, Mat2::new([2.0, 1.0, 4.0, 3.0]));
+        assert_eq!(a * Mat2::identity(), a);
+
+        let m = Mat3::new([1.0, 2.0, 6.0, -5.0, 8.0, -4.0, 2.0, 6.0, 4.0]);
+        assert_eq!(m * m.inverse(), Mat3::identity());
+        assert_eq!(
+            m.transpose(),
+            Mat3::new([1.0, -5.0, 2.0, 2.0, 8.0, 6.0, 6.0, -4.0, 4.0])
+        );
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = Mat2::new([1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(a + a, a * 2.0);
+        assert_eq!(a - a, Mat2::default());
+        assert_eq!(-a, a * -1.0);
+        assert_eq!(
+            a.to_string(),
+            "|  1.00000 | 2.00000 |\n|  3.00000 | 4.00000 |\n"
+        );
+    }
+
     #[test]
     fn test_discriminant_2x2() {
         let m = Matrix::<2>::new([1.0, 5.0, -3.0, 2.0]);
@@ -397,6 +618,36 @@ mod test {
         assert_eq!(m.inverse(), im)
     }
 
+    #[test]
+    fn test_inverse_matches_cofactors() {
+        let m = Mat4::new([
+            9.0, 3.0, 0.0, 9.0, -5.0, -2.0, -6.0, -3.0, -4.0, 9.0, 6.0, 4.0, -7.0, 6.0, 6.0, 2.0,
+        ]);
+        let det = (0..4).fold(0.0, |acc, c| acc + m[(0, c)] * m.cofactor(0, c));
+        let mut expected = Mat4::default();
+        for r in 0..4 {
+            for c in 0..4 {
+                expected[(c, r)] = m.cofactor(r, c) / det;
+            }
+        }
+        assert_eq!(m.det(), det);
+        assert_eq!(m.inverse(), expected);
+        assert_eq!(m.try_inverse(), Some(expected));
+    }
+
+    #[test]
+    fn test_singular_4x4() {
+        let m = Mat4::new([
+            -4.0, 2.0, -2.0, -3.0, 9.0, 6.0, 2.0, 6.0, 0.0, -5.0, 1.0, -5.0, 0.0, 0.0, 0.0, 0.0,
+        ]);
+        assert_eq!(m.det(), 0.0);
+        assert_eq!(m.try_inverse(), None);
+        assert_eq!(
+            Mat3::new([1.0, 2.0, 3.0, 2.0, 4.0, 6.0, 0.0, 1.0, 1.0]).try_inverse(),
+            None
+        );
+    }
+
     #[test]
     fn test_inverse_mul_4x4() {
         let a = Mat4::new([
@@ -409,4 +660,44 @@ mod test {
         let c = a * b;
         assert_eq!(c * b.inverse(), a);
     }
+
+    #[test]
+    fn test_decompose() {
+        let m = Mat4::identity()
+            .scaling(-2.0, 3.0, 4.0)
+            .rot_y(0.5)
+            .translation(1.0, 2.0, 3.0);
+        let (t, r, s) = m.decompose();
+        assert_eq!(t, Tuple::new(1.0, 2.0, 3.0, 0.0));
+        assert_eq!(s, Tuple::new(-2.0, 3.0, 4.0, 0.0));
+        assert_eq!(r.to_matrix(), rot_y(0.5));
+    }
+
+    #[test]
+    fn test_lerp_to() {
+        let a = Mat4::identity().scaling(1.0, 1.0, 1.0).rot_z(0.2);
+        let b = Mat4::identity()
+            .scaling(3.0, 1.0, 1.0)
+            .rot_z(1.0)
+            .translation(4.0, 0.0, -8.0);
+        assert_eq!(a.lerp_to(&b, 0.0), a);
+        assert_eq!(a.lerp_to(&b, 1.0), b);
+        assert_eq!(
+            a.lerp_to(&b, 0.25),
+            Mat4::identity()
+                .scaling(1.5, 1.0, 1.0)
+                .rot_z(0.4)
+                .translation(1.0, 0.0, -2.0)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn matrices_serialize_as_rows() {
+        let m = Mat4::identity().translation(1.0, 2.0, 3.0);
+        let json = serde_json::to_string(&m).unwrap();
+        assert!(json.starts_with("[[1.0,0.0,0.0,1.0],"), "{json}");
+        assert_eq!(serde_json::from_str::<Mat4>(&json).unwrap(), m);
+        assert!(serde_json::from_str::<Mat4>("[[1.0,0.0],[0.0,1.0]]").is_err());
+    }
 }
@@ -0,0 +1,80 @@
+use crate::{color::Color, object::Shape, ray::Ray, util::Float};
+
+/// Number of points sampled along each stretch of a ray crossing a medium.
+pub const MEDIUM_STEPS: usize = 16;
+
+/// Homogeneous participating medium (smoke, fog, murky water) filling the
+/// inside of the shape whose material holds it. The surface of such a shape
+/// is not rendered, rays go through it and get attenuated and lit on the way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Medium {
+    /// Extinction coefficient: fraction of light absorbed or scattered away
+    /// per unit of distance.
+    pub density: Float,
+    /// Fraction of the extinguished light that is scattered rather than
+    /// absorbed, per channel.
+    pub albedo: Color,
+}
+
+impl Medium {
+    pub fn new(density: Float, albedo: Color) -> Self {
+        Self { density, albedo }
+    }
+
+    /// Fraction of light getting through `distance` units of the medium.
+    pub fn transmittance(&self, distance: Float) -> Float {
+        (-self.density * distance).exp()
+    }
+}
+
+/// Part of `r`, between its origin and `end`, that lies inside `shape`, as a
+/// pair of times. Only meaningful for closed convex shapes.
+pub fn segment(shape: &Shape, r: Ray, end: Float) -> Option<(Float, Float)> {
+    let hits = shape.hit_times(r);
+    let times = hits.as_slice();
+    if times.len() < 2 {
+        return None;
+    }
+    let enter = times
+        .iter()
+        .copied()
+        .fold(Float::INFINITY, Float::min)
+        .max(0.0);
+    let exit = times
+        .iter()
+        .copied()
+        .fold(Float::NEG_INFINITY, Float::max)
+        .min(end);
+    (enter < exit).then_some((enter, exit))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tuple::{point, vector};
+
+    use super::*;
+
+    #[test]
+    fn segment_through_a_sphere() {
+        let s = Shape::sphere();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(segment(&s, r, Float::INFINITY), Some((4.0, 6.0)));
+        assert_eq!(segment(&s, r, 5.0), Some((4.0, 5.0)));
+        assert_eq!(segment(&s, r, 3.0), None);
+    }
+
+    #[test]
+    fn segment_starting_inside_a_sphere() {
+        let s = Shape::sphere();
+        let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(segment(&s, r, Float::INFINITY), Some((0.0, 1.0)));
+    }
+
+    #[test]
+    fn transmittance_decays_with_distance() {
+        let m = Medium::new(0.5, Color::white());
+        assert_eq!(m.transmittance(0.0), 1.0);
+        assert!((m.transmittance(2.0) - Float::exp(-1.0)).abs() < 1e-12);
+    }
+}
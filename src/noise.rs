@@ -0,0 +1,151 @@
+use crate::{tuple::Tuple, util::Float};
+
+/// Ken Perlin's permutation of `0..256`, shuffled at compile time from a
+/// fixed seed so every run sees the same noise.
+const PERMUTATION: [u8; 256] = {
+    let mut p = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        p[i] = i as u8;
+        i += 1;
+    }
+    // Fisher-Yates with a xorshift generator
+    let mut state: u32 = 0x9e37_79b9;
+    let mut i = 255;
+    while i > 0 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let j = (state % (i as u32 + 1)) as usize;
+        let t = p[i];
+        p[i] = p[j];
+        p[j] = t;
+        i -= 1;
+    }
+    p
+};
+
+fn hash(i: usize) -> usize {
+    PERMUTATION[i & 255] as usize
+}
+
+fn fade(t: Float) -> Float {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: Float, a: Float, b: Float) -> Float {
+    a + t * (b - a)
+}
+
+/// Dot product of `(x, y, z)` with one of the 12 gradient directions.
+fn grad(hash: usize, x: Float, y: Float, z: Float) -> Float {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = match h {
+        0..=3 => y,
+        12 | 14 => x,
+        _ => z,
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+/// Improved Perlin noise at `p`, smooth and roughly in `[-1, 1]`. It is 0 on
+/// the integer lattice and repeats every 256 units.
+pub fn perlin(p: Tuple) -> Float {
+    let (x0, y0, z0) = (p.x.floor(), p.y.floor(), p.z.floor());
+    let (x, y, z) = (p.x - x0, p.y - y0, p.z - z0);
+    // wrapping keeps negative cells in the table
+    let cell = |v: Float| (v as i64).rem_euclid(256) as usize;
+    let (xi, yi, zi) = (cell(x0), cell(y0), cell(z0));
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+
+    let a = hash(xi) + yi;
+    let (aa, ab) = (hash(a) + zi, hash(a + 1) + zi);
+    let b = hash(xi + 1) + yi;
+    let (ba, bb) = (hash(b) + zi, hash(b + 1) + zi);
+
+    lerp(
+        w,
+        lerp(
+            v,
+            lerp(u, grad(hash(aa), x, y, z), grad(hash(ba), x - 1.0, y, z)),
+            lerp(
+                u,
+                grad(hash(ab), x, y - 1.0, z),
+                grad(hash(bb), x - 1.0, y - 1.0, z),
+            ),
+        ),
+        lerp(
+            v,
+            lerp(
+                u,
+                grad(hash(aa + 1), x, y, z - 1.0),
+                grad(hash(ba + 1), x - 1.0, y, z - 1.0),
+            ),
+            lerp(
+                u,
+                grad(hash(ab + 1), x, y - 1.0, z - 1.0),
+                grad(hash(bb + 1), x - 1.0, y - 1.0, z - 1.0),
+            ),
+        ),
+    )
+}
+
+/// Fractal noise: `octaves` layers of [`perlin`] noise, each of twice the
+/// frequency and half the amplitude of the previous one, in about `[-1, 1]`.
+pub fn fbm(p: Tuple, octaves: u32) -> Float {
+    let (mut sum, mut total) = (0.0, 0.0);
+    let (mut frequency, mut amplitude) = (1.0, 1.0);
+    for _ in 0..octaves {
+        sum += perlin(p * frequency) * amplitude;
+        total += amplitude;
+        frequency *= 2.0;
+        amplitude *= 0.5;
+    }
+    if total == 0.0 {
+        0.0
+    } else {
+        sum / total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tuple::point;
+
+    use super::*;
+
+    #[test]
+    fn noise_vanishes_on_the_lattice() {
+        assert_eq!(perlin(point(0.0, 0.0, 0.0)), 0.0);
+        assert_eq!(perlin(point(3.0, -7.0, 12.0)), 0.0);
+    }
+
+    #[test]
+    fn noise_is_smooth_and_bounded() {
+        let mut seen_positive = false;
+        for i in 0..1000 {
+            let p = point(
+                i as Float * 0.137,
+                i as Float * 0.071 - 20.0,
+                i as Float * 0.019,
+            );
+            let n = perlin(p);
+            assert!((-1.1..=1.1).contains(&n));
+            seen_positive |= n > 0.1;
+            let nearby = perlin(p + crate::tuple::vector(1e-4, 0.0, 0.0));
+            assert!((n - nearby).abs() < 1e-3);
+        }
+        assert!(seen_positive);
+    }
+
+    #[test]
+    fn fractal_noise_adds_detail_to_perlin_noise() {
+        let p = point(1.3, 2.7, -0.4);
+        assert_eq!(fbm(p, 1), perlin(p));
+        assert_eq!(fbm(p, 0), 0.0);
+        let detailed = fbm(p, 5);
+        assert!((-1.1..=1.1).contains(&detailed));
+        assert_ne!(detailed, perlin(p));
+    }
+}
@@ -1,72 +1,147 @@
 use std::fmt::Debug;
 
 use crate::{
+    bounds::Bounds,
     intersection::{Intersectable, Intersection, Intersections},
     material::Material,
     matrix::{Mat4, MatBase},
     pattern::Pattern,
     plane::Plane,
-    ray::Ray,
+    ray::{Ray, RayKind},
+    refractive_index,
     sphere::Sphere,
-    tuple::{vector, Tuple},
+    stats,
+    texture::UvMapping,
+    triangle::Triangle,
+    tuple::{point, vector, Tuple},
+    util::Float,
 };
 
+/// Most hits a primitive can report for a single ray.
+pub const MAX_LOCAL_HITS: usize = 4;
+
+/// Times at which a ray hits a primitive, stored inline so intersecting does
+/// not allocate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LocalHits {
+    times: [Float; MAX_LOCAL_HITS],
+    len: usize,
+}
+
+impl LocalHits {
+    pub fn new() -> Self {
+        Self {
+            times: [0.0; MAX_LOCAL_HITS],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, t: Float) {
+        assert!(self.len < MAX_LOCAL_HITS, "too many hits for one primitive");
+        self.times[self.len] = t;
+        self.len += 1;
+    }
+
+    pub fn as_slice(&self) -> &[Float] {
+        &self.times[..self.len]
+    }
+}
+
+impl Default for LocalHits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> From<[Float; N]> for LocalHits {
+    fn from(times: [Float; N]) -> Self {
+        let mut hits = Self::new();
+        for t in times {
+            hits.push(t);
+        }
+        hits
+    }
+}
+
 pub trait LocalIntersect: Debug + PartialEq {
-    fn local_intersect(&self, r: Ray) -> Vec<f64>;
+    fn local_intersect(&self, r: Ray) -> LocalHits;
     fn local_normal_at(&self, object_point: &Tuple) -> Tuple;
+    fn local_bounds(&self) -> Bounds;
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Object {
     Sphere(Sphere),
     Plane(Plane),
+    Triangle(Triangle),
     No(TestShape),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+impl Object {
+    /// Name of the kind of primitive, as in scene files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Object::Sphere(_) => "sphere",
+            Object::Plane(_) => "plane",
+            Object::Triangle(_) => "triangle",
+            Object::No(_) => "test shape",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Shape {
     pub transform: Mat4,
     pub transform_inverse: Mat4,
 
     pub material: Material,
     object: Object,
+
+    /// Whether camera rays see the shape.
+    pub visible_to_camera: bool,
+    /// Whether the shape shows in reflections.
+    pub visible_in_reflections: bool,
+    /// Whether the shape shows through transparent objects.
+    pub visible_in_refractions: bool,
 }
 
 impl Shape {
-    pub fn sphere() -> Self {
+    fn new(object: Object) -> Self {
         Self {
             transform: Mat4::identity(),
             transform_inverse: Mat4::identity(),
             material: Material::default(),
-            object: Object::Sphere(Sphere),
+            object,
+            visible_to_camera: true,
+            visible_in_reflections: true,
+            visible_in_refractions: true,
         }
     }
 
+    pub fn sphere() -> Self {
+        Self::new(Object::Sphere(Sphere))
+    }
+
     pub fn glass_sphere() -> Self {
-        Self {
-            transform: Mat4::identity(),
-            transform_inverse: Mat4::identity(),
-            material: Material::default().refractive_index(1.5).transparency(1.0),
-            object: Object::Sphere(Sphere),
-        }
+        Self::new(Object::Sphere(Sphere)).with_material(
+            Material::default()
+                .refractive_index(refractive_index::GLASS)
+                .transparency(1.0),
+        )
     }
 
     pub fn plane() -> Self {
-        Self {
-            transform: Mat4::identity(),
-            transform_inverse: Mat4::identity(),
-            material: Material::default(),
-            object: Object::Plane(Plane),
-        }
+        Self::new(Object::Plane(Plane))
+    }
+
+    pub fn triangle(p1: Tuple, p2: Tuple, p3: Tuple) -> Self {
+        Self::new(Object::Triangle(Triangle::new(p1, p2, p3)))
     }
 
     pub fn default_shape() -> Self {
-        Self {
-            transform: Mat4::identity(),
-            transform_inverse: Mat4::identity(),
-            material: Material::default(),
-            object: Object::No(TestShape),
-        }
+        Self::new(Object::No(TestShape))
     }
 
     pub fn with_transform(mut self, transform: Mat4) -> Self {
@@ -85,6 +160,25 @@ impl Shape {
         self
     }
 
+    /// Hides the shape from the rays of the given kind. It still casts
+    /// shadows.
+    pub fn hidden_from(mut self, kind: RayKind) -> Self {
+        match kind {
+            RayKind::Camera => self.visible_to_camera = false,
+            RayKind::Reflection => self.visible_in_reflections = false,
+            RayKind::Refraction => self.visible_in_refractions = false,
+        }
+        self
+    }
+
+    pub fn visible_to(&self, kind: RayKind) -> bool {
+        match kind {
+            RayKind::Camera => self.visible_to_camera,
+            RayKind::Reflection => self.visible_in_reflections,
+            RayKind::Refraction => self.visible_in_refractions,
+        }
+    }
+
     pub fn set_transform(&mut self, transform: Mat4) {
         self.transform = transform;
         self.transform_inverse = transform.inverse()
@@ -97,52 +191,128 @@ impl Shape {
     pub fn set_pattern(&mut self, pattern: Pattern) {
         self.material.pattern = Some(pattern)
     }
+
+    /// Kind of primitive, in object space.
+    pub fn object(&self) -> Object {
+        self.object
+    }
+
+    /// Texture mapping that suits the kind of shape.
+    pub fn uv_mapping(&self) -> UvMapping {
+        match self.object {
+            Object::Sphere(_) => UvMapping::Spherical,
+            Object::Plane(_) | Object::Triangle(_) => UvMapping::Planar,
+            Object::No(_) => UvMapping::Cubic,
+        }
+    }
+
+    /// `point` in the space of the shape. Groups are flattened into the
+    /// transforms of their shapes, so this goes through the whole chain of
+    /// parent transforms at once.
+    pub fn world_to_object(&self, point: Tuple) -> Tuple {
+        self.transform_inverse * point
+    }
+
+    /// `normal`, given in the space of the shape, in world space.
+    pub fn normal_to_world(&self, normal: Tuple) -> Tuple {
+        let mut world_normal = Mat4::transpose(self.transform_inverse) * normal;
+        world_normal.w = 0.0;
+        world_normal.norm()
+    }
+
+    /// World space distance from `point`, a world space point of a triangle,
+    /// to its nearest edge. The other shapes have no edges.
+    pub fn edge_distance(&self, point: Tuple) -> Option<Float> {
+        match self.object {
+            Object::Triangle(t) => {
+                let [p1, p2, p3] = [t.p1, t.p2, t.p3].map(|p| self.transform * p);
+                Some(Triangle::new(p1, p2, p3).edge_distance(point))
+            }
+            _ => None,
+        }
+    }
+
+    /// Bounding box of the shape in world space.
+    pub fn bounds(&self) -> Bounds {
+        let local = match self.object {
+            Object::Sphere(s) => s.local_bounds(),
+            Object::Plane(p) => p.local_bounds(),
+            Object::Triangle(t) => t.local_bounds(),
+            Object::No(ts) => ts.local_bounds(),
+        };
+        local.transform(self.transform)
+    }
 }
 
-impl Intersectable for Shape {
-    fn intersects(&self, r: crate::ray::Ray) -> Intersections {
+impl Shape {
+    /// Times at which `r`, given in world space, hits the shape.
+    pub fn hit_times(&self, r: Ray) -> LocalHits {
+        stats::count_intersection_test();
         let r = r.transform(self.transform_inverse);
-        let xs = match self.object {
+        match self.object {
             Object::Sphere(s) => s.local_intersect(r),
             Object::No(_) => unimplemented!(),
             Object::Plane(p) => p.local_intersect(r),
-        };
+            Object::Triangle(t) => t.local_intersect(r),
+        }
+    }
 
-        Intersections::new(xs.iter().map(|t| Intersection::new(*t, *self)).collect())
+    /// Pushes the intersections of `r` with the shape at the end of `xs`.
+    pub fn intersect_into(&self, r: Ray, xs: &mut Vec<Intersection>) {
+        let hits = self.hit_times(r);
+        xs.extend(
+            hits.as_slice()
+                .iter()
+                .map(|t| Intersection::new(*t, self.clone())),
+        );
+    }
+}
+
+impl Intersectable for Shape {
+    fn intersects(&self, r: crate::ray::Ray) -> Intersections {
+        let mut xs = Vec::with_capacity(MAX_LOCAL_HITS);
+        self.intersect_into(r, &mut xs);
+        Intersections::new(xs)
     }
 
     fn normal_at(&self, point: &Tuple) -> Tuple {
-        let local_point = (self.transform_inverse) * (*point);
+        let local_point = self.world_to_object(*point);
         let local_normal = match self.object {
             Object::Sphere(s) => s.local_normal_at(&local_point),
             Object::No(ts) => ts.local_normal_at(&local_point),
             Object::Plane(p) => p.local_normal_at(&local_point),
+            Object::Triangle(t) => t.local_normal_at(&local_point),
         };
-        let mut world_normal = Mat4::transpose(self.transform_inverse) * local_normal;
-        world_normal.w = 0.0;
-        world_normal.norm()
+        let world_normal = self.normal_to_world(local_normal);
+        match &self.material.bump_map {
+            Some(bump) => bump.perturb(self, *point, world_normal),
+            None => world_normal,
+        }
     }
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TestShape;
 impl LocalIntersect for TestShape {
-    fn local_intersect(&self, _r: Ray) -> Vec<f64> {
+    fn local_intersect(&self, _r: Ray) -> LocalHits {
         todo!()
     }
 
     fn local_normal_at(&self, object_point: &Tuple) -> Tuple {
         vector(object_point.x, object_point.y, object_point.z)
     }
+
+    fn local_bounds(&self) -> Bounds {
+        Bounds::new(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::f64::consts::{PI, SQRT_2};
-
     use crate::{
-        transformations::{rot_z, translation},
-        tuple::point,
+        transformations::{rot_z, scaling, translation},
+        util::consts::{PI, SQRT_2},
     };
 
     use super::*;
@@ -161,6 +331,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn computing_the_normal_on_a_translated_shape() {
         let mut s = Shape::default_shape();
         s.set_transform(translation(0.0, 1.0, 0.0));
@@ -173,4 +344,47 @@ mod tests {
         let n = s.normal_at(&point(0.0, SQRT_2 / 2.0, -SQRT_2 / 2.0));
         assert_eq!(n, vector(0.0, 0.97014, -0.24254))
     }
+
+    #[test]
+    fn local_hits_hold_times_inline() {
+        let mut hits = LocalHits::from([1.0, 2.0]);
+        hits.push(3.0);
+        assert_eq!(hits.as_slice(), &[1.0, 2.0, 3.0]);
+        assert!(LocalHits::new().as_slice().is_empty());
+    }
+
+    #[test]
+    fn intersecting_into_an_existing_buffer_appends() {
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let s = Shape::sphere();
+        let mut xs = vec![Intersection::new(-1.0, Shape::plane())];
+        s.intersect_into(r, &mut xs);
+        assert_eq!(xs.len(), 3);
+        assert_eq!(xs[1].time, 4.0);
+        assert_eq!(xs[2].time, 6.0);
+    }
+
+    #[test]
+    fn bounds_of_a_transformed_sphere() {
+        let s = Shape::sphere().with_transform(scaling(2.0, 2.0, 2.0).translation(1.0, 0.0, 0.0));
+        assert_eq!(
+            s.bounds(),
+            Bounds::new(point(-1.0, -2.0, -2.0), point(3.0, 2.0, 2.0))
+        );
+    }
+
+    #[test]
+    fn bounds_of_a_plane_are_infinite() {
+        assert!(Shape::plane().bounds().is_infinite());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn shapes_round_trip_through_serde() {
+        let s = Shape::sphere()
+            .with_transform(scaling(2.0, 2.0, 2.0).translation(1.0, 0.0, 0.0))
+            .hidden_from(RayKind::Reflection);
+        let json = serde_json::to_string(&s).unwrap();
+        assert_eq!(serde_json::from_str::<Shape>(&json).unwrap(), s);
+    }
 }
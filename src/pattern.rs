@@ -1,37 +1,285 @@
+use std::{fmt::Debug, sync::Arc};
+
 use crate::{
     color::Color,
     matrix::{Mat4, MatBase, Matrix},
+    noise::{fbm, perlin},
     object::Shape,
-    tuple::Tuple,
+    texture::{CubeFace, UvMapping, UvPattern},
+    transformations::{rot_z, scaling, translation},
+    tuple::{point, vector, Tuple},
+    util::Float,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// A pattern defined outside the crate. The lookup point is in pattern space,
+/// the transform of the wrapping [`Pattern`] is already applied.
+pub trait PatternAt: Debug + Send + Sync {
+    fn color_at(&self, p: &Tuple) -> Color;
+}
+
+/// A user pattern, shared between the clones of its shape. Two of them are
+/// equal when they are the same instance.
+#[derive(Debug, Clone)]
+pub struct CustomPattern(Arc<dyn PatternAt>);
+
+impl PartialEq for CustomPattern {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// A closure used as a pattern, see [`Pattern::from_fn`].
+struct FnPattern<F>(F);
+
+impl<F> Debug for FnPattern<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("FnPattern")
+    }
+}
+
+impl<F: Fn(Tuple) -> Color + Send + Sync> PatternAt for FnPattern<F> {
+    fn color_at(&self, p: &Tuple) -> Color {
+        (self.0)(*p)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PatternType {
-    Stripe { a: Color, b: Color },
-    Gradient { a: Color, b: Color },
-    Ring { a: Color, b: Color },
-    Checker { a: Color, b: Color },
+    Solid {
+        color: Color,
+    },
+    Stripe {
+        a: PatternInput,
+        b: PatternInput,
+    },
+    Gradient {
+        a: PatternInput,
+        b: PatternInput,
+    },
+    /// Gradient along x through `stops`, sorted by position.
+    Stops {
+        stops: Arc<[(Float, PatternInput)]>,
+        wrap: GradientWrap,
+    },
+    Ring {
+        a: PatternInput,
+        b: PatternInput,
+    },
+    Checker {
+        a: PatternInput,
+        b: PatternInput,
+    },
+    /// `pattern` looked up at points moved by up to `amplitude` along a 3D
+    /// noise of frequency `scale`.
+    Perturb {
+        pattern: Arc<Pattern>,
+        scale: Float,
+        amplitude: Float,
+    },
+    /// Mix of two patterns, `weight` giving the share of `b`.
+    Blend {
+        a: Arc<Pattern>,
+        b: Arc<Pattern>,
+        weight: BlendWeight,
+    },
+    /// A 2D texture wrapped around the object by `mapping`.
+    TextureMap {
+        uv: UvPattern,
+        mapping: UvMapping,
+    },
+    /// A texture per face of the `[-1, 1]` cube, in [`CubeFace`] order.
+    /// Works for cubes and, seen from the inside, skyboxes.
+    CubeMap {
+        faces: Arc<[UvPattern; 6]>,
+    },
+    /// Looked up by direction, see [`Pattern::color_at_direction`].
+    Sky(Sky),
+    /// User patterns are not serializable.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(CustomPattern),
     Test {},
 }
 
+/// What fills the two slots of stripes, gradients, rings and checkers: a
+/// flat color or another pattern.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PatternInput {
+    Color(Color),
+    Pattern(Arc<Pattern>),
+}
+
+impl PatternInput {
+    fn color_in(&self, p: Tuple, footprint: Float) -> Color {
+        match self {
+            PatternInput::Color(c) => *c,
+            PatternInput::Pattern(pattern) => pattern.color_in(p, footprint),
+        }
+    }
+
+    fn colors(&self) -> Vec<Color> {
+        match self {
+            PatternInput::Color(c) => vec![*c],
+            PatternInput::Pattern(pattern) => pattern.colors(),
+        }
+    }
+}
+
+impl From<Color> for PatternInput {
+    fn from(color: Color) -> Self {
+        PatternInput::Color(color)
+    }
+}
+
+impl From<Pattern> for PatternInput {
+    fn from(pattern: Pattern) -> Self {
+        PatternInput::Pattern(Arc::new(pattern))
+    }
+}
+
+/// How a multi-stop gradient extends along x past `[0, 1]`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GradientWrap {
+    /// Holds the first and last stops.
+    #[default]
+    Clamp,
+    /// Starts over at every integer.
+    Repeat,
+    /// Goes back and forth, reversed on odd intervals.
+    Mirror,
+}
+
+impl GradientWrap {
+    fn apply(self, x: Float) -> Float {
+        match self {
+            GradientWrap::Clamp => x.clamp(0.0, 1.0),
+            GradientWrap::Repeat => x - x.floor(),
+            GradientWrap::Mirror => 1.0 - (x.rem_euclid(2.0) - 1.0).abs(),
+        }
+    }
+}
+
+/// A daylight sky for the background: a gradient from the horizon to the
+/// zenith over a flat ground, with fractal noise clouds.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sky {
+    pub horizon: Color,
+    pub zenith: Color,
+    pub ground: Color,
+    pub clouds: Color,
+    /// Share of the sky hidden by clouds, in `[0, 1]`.
+    pub coverage: Float,
+    /// Frequency of the clouds, laid on a plane one unit above the viewer.
+    pub scale: Float,
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Self {
+            horizon: Color::new(0.85, 0.9, 1.0),
+            zenith: Color::new(0.25, 0.45, 0.85),
+            ground: Color::new(0.3, 0.28, 0.25),
+            clouds: Color::white(),
+            coverage: 0.4,
+            scale: 2.0,
+        }
+    }
+}
+
+impl Sky {
+    fn color_towards(&self, d: Tuple) -> Color {
+        let d = vector(d.x, d.y, d.z).norm();
+        if d.y <= 0.0 {
+            return self.ground;
+        }
+        let sky = self.horizon + (self.zenith - self.horizon) * d.y.sqrt();
+        if self.coverage <= 0.0 {
+            return sky;
+        }
+        // where the view ray meets the cloud plane
+        let q = vector(d.x / d.y, 0.0, d.z / d.y) * self.scale;
+        let density = 0.5 + 0.5 * fbm(q, 5);
+        let cloud = ((density - (1.0 - self.coverage)) * 4.0).clamp(0.0, 1.0)
+            // thinned out towards the horizon, where they would alias
+            * (d.y * 8.0).min(1.0);
+        sky * (1.0 - cloud) + self.clouds * cloud
+    }
+}
+
+/// Share of the second pattern in a blend.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BlendWeight {
+    Constant(Float),
+    /// Varies with the point: the brightness of a mask pattern, black
+    /// keeping the first pattern and white the second.
+    Mask(Arc<Pattern>),
+}
+
+impl BlendWeight {
+    fn at(&self, p: Tuple, footprint: Float) -> Float {
+        match self {
+            BlendWeight::Constant(w) => *w,
+            BlendWeight::Mask(mask) => mask.color_in(p, footprint).mean(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pattern {
     p_type: PatternType,
+    #[cfg_attr(feature = "serde", serde(default = "Mat4::identity"))]
     transform: Mat4,
 }
 
 impl Pattern {
-    pub fn stripped(a: Color, b: Color) -> Self {
+    /// The same color everywhere, for slots that take a pattern.
+    pub fn solid(color: Color) -> Self {
+        Self {
+            p_type: PatternType::Solid { color },
+            transform: Mat4::identity(),
+        }
+    }
+
+    pub fn stripped(a: impl Into<PatternInput>, b: impl Into<PatternInput>) -> Self {
         use PatternType::*;
         Self {
-            p_type: Stripe { a, b },
+            p_type: Stripe {
+                a: a.into(),
+                b: b.into(),
+            },
             transform: Mat4::identity(),
         }
     }
-    pub fn gradient(a: Color, b: Color) -> Self {
+    pub fn gradient(a: impl Into<PatternInput>, b: impl Into<PatternInput>) -> Self {
         use PatternType::*;
         Self {
-            p_type: Gradient { a, b },
+            p_type: Gradient {
+                a: a.into(),
+                b: b.into(),
+            },
+            transform: Mat4::identity(),
+        }
+    }
+
+    /// Gradient through any number of `(position, color)` stops placed in
+    /// `[0, 1]` along x, with `wrap` deciding what happens outside.
+    pub fn gradient_stops<C: Into<PatternInput>>(
+        stops: impl IntoIterator<Item = (Float, C)>,
+        wrap: GradientWrap,
+    ) -> Self {
+        let mut stops: Vec<_> = stops.into_iter().map(|(t, c)| (t, c.into())).collect();
+        assert!(!stops.is_empty(), "a gradient needs at least one stop");
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self {
+            p_type: PatternType::Stops {
+                stops: stops.into(),
+                wrap,
+            },
             transform: Mat4::identity(),
         }
     }
@@ -42,65 +290,253 @@ impl Pattern {
             transform: Matrix::identity(),
         }
     }
-    pub fn checker(a: Color, b: Color) -> Self {
+    pub fn checker(a: impl Into<PatternInput>, b: impl Into<PatternInput>) -> Self {
         use PatternType::*;
         Self {
-            p_type: Checker { a, b },
+            p_type: Checker {
+                a: a.into(),
+                b: b.into(),
+            },
             transform: Mat4::identity(),
         }
     }
-    pub fn ring(a: Color, b: Color) -> Self {
+    pub fn ring(a: impl Into<PatternInput>, b: impl Into<PatternInput>) -> Self {
         use PatternType::*;
         Self {
-            p_type: Ring { a, b },
+            p_type: Ring {
+                a: a.into(),
+                b: b.into(),
+            },
+            transform: Mat4::identity(),
+        }
+    }
+
+    /// Wobbles the lookups into `pattern` with Perlin noise, turning
+    /// straight stripes and rings into marble-like veins.
+    pub fn perturb(pattern: Pattern, scale: Float, amplitude: Float) -> Self {
+        Self {
+            p_type: PatternType::Perturb {
+                pattern: Arc::new(pattern),
+                scale,
+                amplitude,
+            },
+            transform: Mat4::identity(),
+        }
+    }
+
+    /// Average of `a` and `b`, weighted by `weight` (the share of `b`), e.g.
+    /// stripes crossing at 90°.
+    pub fn blend(a: Pattern, b: Pattern, weight: Float) -> Self {
+        Self::blend_with(a, b, BlendWeight::Constant(weight))
+    }
+
+    pub fn blend_with(a: Pattern, b: Pattern, weight: BlendWeight) -> Self {
+        Self {
+            p_type: PatternType::Blend {
+                a: Arc::new(a),
+                b: Arc::new(b),
+                weight,
+            },
+            transform: Mat4::identity(),
+        }
+    }
+
+    /// Wraps `uv` around the object, see [`Shape::uv_mapping`] for the
+    /// mapping that suits each shape.
+    pub fn texture_map(uv: UvPattern, mapping: UvMapping) -> Self {
+        Self {
+            p_type: PatternType::TextureMap { uv, mapping },
             transform: Mat4::identity(),
         }
     }
 
+    /// Checkers laid out on the surface rather than in space: `width` by
+    /// `height` squares over the `(u, v)` square of `mapping`. Unlike
+    /// [`Pattern::checker`] they don't speckle where a curved surface runs
+    /// along an integer coordinate.
+    pub fn uv_checkers(
+        width: Float,
+        height: Float,
+        a: Color,
+        b: Color,
+        mapping: UvMapping,
+    ) -> Self {
+        Self::texture_map(UvPattern::checkers(width, height, a, b), mapping)
+    }
+
+    /// Textures each face of the cube with its own pattern, given in
+    /// [`CubeFace`] order: left, right, front, back, up, down.
+    pub fn cube_map(faces: [UvPattern; 6]) -> Self {
+        Self {
+            p_type: PatternType::CubeMap {
+                faces: Arc::new(faces),
+            },
+            transform: Mat4::identity(),
+        }
+    }
+
+    /// Background going from `bottom`, straight down, to `top`, straight
+    /// up. The unit directions are mapped from `y` in `[-1, 1]` to `x` in
+    /// `[0, 1]` along a clamped gradient.
+    pub fn vertical_gradient(bottom: Color, top: Color) -> Self {
+        Self::gradient_stops([(0.0, bottom), (1.0, top)], GradientWrap::Clamp).with_transform(
+            translation(0.0, -1.0, 0.0)
+                * rot_z(crate::util::consts::FRAC_PI_2)
+                * scaling(2.0, 1.0, 1.0),
+        )
+    }
+
+    /// A procedural sky, to be used as the background of a world.
+    pub fn sky(sky: Sky) -> Self {
+        Self {
+            p_type: PatternType::Sky(sky),
+            transform: Mat4::identity(),
+        }
+    }
+
+    /// Wraps a pattern implemented outside the crate, so it can be
+    /// transformed, nested and blended like the built-in ones.
+    pub fn custom(pattern: impl PatternAt + 'static) -> Self {
+        Self {
+            p_type: PatternType::Custom(CustomPattern(Arc::new(pattern))),
+            transform: Mat4::identity(),
+        }
+    }
+
+    /// A pattern computed by `f` from the point in pattern space, for quick
+    /// procedural textures written where they are used.
+    pub fn from_fn(f: impl Fn(Tuple) -> Color + Send + Sync + 'static) -> Self {
+        Self::custom(FnPattern(f))
+    }
+
     pub fn colors(&self) -> Vec<Color> {
-        match self.p_type {
-            PatternType::Stripe { a, b } => vec![a, b],
-            PatternType::Gradient { a, b } => vec![a, b],
-            PatternType::Ring { a, b } => vec![a, b],
-            PatternType::Checker { a, b } => vec![a, b],
-            PatternType::Test {} => vec![],
+        match &self.p_type {
+            PatternType::Solid { color } => vec![*color],
+            PatternType::Stripe { a, b }
+            | PatternType::Gradient { a, b }
+            | PatternType::Ring { a, b }
+            | PatternType::Checker { a, b } => [a.colors(), b.colors()].concat(),
+            PatternType::Stops { stops, .. } => {
+                stops.iter().flat_map(|(_, c)| c.colors()).collect()
+            }
+            PatternType::Perturb { pattern, .. } => pattern.colors(),
+            PatternType::Blend { a, b, .. } => [a.colors(), b.colors()].concat(),
+            PatternType::TextureMap { uv, .. } => uv.colors(),
+            PatternType::CubeMap { faces } => faces.iter().flat_map(|f| f.colors()).collect(),
+            PatternType::Sky(sky) => vec![sky.horizon, sky.zenith, sky.ground, sky.clouds],
+            PatternType::Custom(_) | PatternType::Test {} => vec![],
         }
     }
 
-    pub fn pattern_at_shape(&self, shape: Shape, world_point: Tuple) -> Color {
-        let object_point = shape.transform_inverse * world_point;
-        let pattern_point = self.transform.inverse() * object_point;
-        self.color_at(pattern_point)
+    pub fn pattern_at_shape(&self, shape: &Shape, world_point: Tuple) -> Color {
+        self.filtered_at_shape(shape, world_point, 0.0)
+    }
+
+    /// Color at `world_point` averaged over a `footprint` wide area, for the
+    /// patterns that can be filtered. The others are point sampled.
+    pub fn filtered_at_shape(&self, shape: &Shape, world_point: Tuple, footprint: Float) -> Color {
+        let object_point = shape.world_to_object(world_point);
+        self.color_in(
+            object_point,
+            transformed_footprint(&shape.transform_inverse, footprint),
+        )
+    }
+
+    /// Color at `p`, given in the space the pattern is transformed in.
+    fn color_in(&self, p: Tuple, footprint: Float) -> Color {
+        let inverse = self.transform.inverse();
+        self.filtered_at(inverse * p, transformed_footprint(&inverse, footprint))
     }
 
     pub fn color_at(&self, p: Tuple) -> Color {
+        self.filtered_at(p, 0.0)
+    }
+
+    /// Color of a pattern wrapped around the scene, seen towards
+    /// `direction`: the unit direction is looked up as a point, through the
+    /// pattern transform.
+    pub fn color_at_direction(&self, direction: Tuple) -> Color {
+        let d = direction.norm();
+        self.color_in(point(d.x, d.y, d.z), 0.0)
+    }
+
+    fn filtered_at(&self, p: Tuple, footprint: Float) -> Color {
         match self.p_type {
-            PatternType::Stripe { a, b } => {
+            PatternType::Solid { color } => color,
+            PatternType::Stripe { ref a, ref b } => {
                 if p.x.floor() % 2.0 == 0.0 {
-                    a
+                    a.color_in(p, footprint)
                 } else {
-                    b
+                    b.color_in(p, footprint)
                 }
             }
-            PatternType::Gradient { a, b } => {
+            PatternType::Gradient { ref a, ref b } => {
+                let (a, b) = (a.color_in(p, footprint), b.color_in(p, footprint));
                 let distance = b - a;
                 let fraction = p.x - p.x.floor();
                 a + distance * fraction
             }
-            PatternType::Ring { a, b } => {
-                if f64::sqrt(p.x * p.x + p.z * p.z).floor() % 2.0 == 0.0 {
-                    a
+            PatternType::Stops { ref stops, wrap } => {
+                let x = wrap.apply(p.x);
+                let next = stops.partition_point(|(t, _)| *t <= x);
+                if next == 0 {
+                    return stops[0].1.color_in(p, footprint);
+                }
+                if next == stops.len() {
+                    return stops[next - 1].1.color_in(p, footprint);
+                }
+                let ((t0, a), (t1, b)) = (&stops[next - 1], &stops[next]);
+                let (a, b) = (a.color_in(p, footprint), b.color_in(p, footprint));
+                a + (b - a) * ((x - t0) / (t1 - t0))
+            }
+            PatternType::Ring { ref a, ref b } => {
+                if Float::sqrt(p.x * p.x + p.z * p.z).floor() % 2.0 == 0.0 {
+                    a.color_in(p, footprint)
                 } else {
-                    b
+                    b.color_in(p, footprint)
                 }
             }
-            PatternType::Checker { a, b } => {
+            PatternType::Checker { ref a, ref b } => {
                 if (p.x.floor() + p.y.floor() + p.z.floor()) % 2.0 == 0.0 {
-                    a
+                    a.color_in(p, footprint)
                 } else {
-                    b
+                    b.color_in(p, footprint)
                 }
             }
+            PatternType::Perturb {
+                ref pattern,
+                scale,
+                amplitude,
+            } => {
+                let q = p * scale;
+                // decorrelated noise per axis, sampled at distant offsets
+                let offset = vector(
+                    perlin(q),
+                    perlin(q + vector(31.4, 47.2, 12.9)),
+                    perlin(q + vector(-17.3, 5.8, 63.1)),
+                );
+                pattern.color_in(p + offset * amplitude, footprint)
+            }
+            PatternType::Blend {
+                ref a,
+                ref b,
+                ref weight,
+            } => {
+                let w = weight.at(p, footprint);
+                a.color_in(p, footprint) * (1.0 - w) + b.color_in(p, footprint) * w
+            }
+            PatternType::TextureMap { ref uv, mapping } => {
+                let (u, v) = mapping.map(p);
+                uv.filtered_at(u, v, mapping.uv_footprint(footprint))
+            }
+            PatternType::CubeMap { ref faces } => {
+                let face = CubeFace::of(p);
+                let (u, v) = face.uv(p);
+                let footprint = UvMapping::Cubic.uv_footprint(footprint);
+                faces[face.index()].filtered_at(u, v, footprint)
+            }
+            PatternType::Sky(ref sky) => sky.color_towards(p),
+            PatternType::Custom(ref custom) => custom.0.color_at(&p),
             PatternType::Test {} => Color::new(p.x, p.y, p.z),
         }
     }
@@ -109,13 +545,69 @@ impl Pattern {
         self.transform = transform;
         self
     }
+
+    // Chainable transforms, each applied after the current transform like
+    // the `Mat4` helpers of the same name.
+
+    pub fn translation(mut self, x: Float, y: Float, z: Float) -> Self {
+        self.transform = self.transform.translation(x, y, z);
+        self
+    }
+
+    pub fn scaling(mut self, x: Float, y: Float, z: Float) -> Self {
+        self.transform = self.transform.scaling(x, y, z);
+        self
+    }
+
+    pub fn rot_x(mut self, angle: Float) -> Self {
+        self.transform = self.transform.rot_x(angle);
+        self
+    }
+
+    pub fn rot_y(mut self, angle: Float) -> Self {
+        self.transform = self.transform.rot_y(angle);
+        self
+    }
+
+    pub fn rot_z(mut self, angle: Float) -> Self {
+        self.transform = self.transform.rot_z(angle);
+        self
+    }
+
+    pub fn shearing(
+        mut self,
+        xy: Float,
+        xz: Float,
+        yx: Float,
+        yz: Float,
+        zx: Float,
+        zy: Float,
+    ) -> Self {
+        self.transform = self.transform.shearing(xy, xz, yx, yz, zx, zy);
+        self
+    }
+}
+
+/// Size of a `footprint` wide area once moved by `m`, averaged over the axes.
+fn transformed_footprint(m: &Mat4, footprint: Float) -> Float {
+    if footprint == 0.0 {
+        return 0.0;
+    }
+    (*m * vector(footprint, footprint, footprint)).mag() / Float::sqrt(3.0)
+}
+
+impl PatternAt for Pattern {
+    fn color_at(&self, p: &Tuple) -> Color {
+        Pattern::color_at(self, *p)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        transformations::{scaling, translation},
+        transformations::{rot_y, scaling, translation},
         tuple::point,
+        util::consts::PI,
     };
 
     use super::*;
@@ -161,14 +653,14 @@ mod tests {
     fn stripes_with_an_object_transformation() {
         let s = Shape::sphere().with_transform(scaling(2.0, 2.0, 2.0));
         let pattern = Pattern::stripped(WHITE, BLACK);
-        assert_eq!(pattern.pattern_at_shape(s, point(1.5, 0.0, 0.0)), WHITE)
+        assert_eq!(pattern.pattern_at_shape(&s, point(1.5, 0.0, 0.0)), WHITE)
     }
 
     #[test]
     fn stripes_with_a_pattern_transformation() {
         let s = Shape::sphere();
         let pattern = Pattern::stripped(WHITE, BLACK).with_transform(scaling(2.0, 2.0, 2.0));
-        assert_eq!(pattern.pattern_at_shape(s, point(1.5, 0.0, 0.0)), WHITE)
+        assert_eq!(pattern.pattern_at_shape(&s, point(1.5, 0.0, 0.0)), WHITE)
     }
 
     #[test]
@@ -176,7 +668,7 @@ mod tests {
         let s = Shape::sphere().with_transform(scaling(2.0, 2.0, 2.0));
         let pattern = Pattern::stripped(WHITE, BLACK).with_transform(translation(0.5, 0.0, 0.0));
 
-        assert_eq!(pattern.pattern_at_shape(s, point(2.5, 0.0, 0.0)), WHITE)
+        assert_eq!(pattern.pattern_at_shape(&s, point(2.5, 0.0, 0.0)), WHITE)
     }
 
     #[test]
@@ -225,4 +717,272 @@ mod tests {
         assert_eq!(p.color_at(point(0.0, 0.0, 0.99)), WHITE);
         assert_eq!(p.color_at(point(0.0, 0.0, 1.01)), BLACK);
     }
+
+    #[test]
+    fn perturbing_bends_the_stripes() {
+        let stripes = Pattern::stripped(WHITE, BLACK);
+        let p = Pattern::perturb(stripes.clone(), 1.0, 0.0);
+        assert_eq!(p.color_at(point(0.5, 0.3, 0.2)), WHITE);
+        assert_eq!(p.colors(), stripes.colors());
+        // with noise, points along a line across a boundary no longer all
+        // agree with the straight stripes
+        let p = Pattern::perturb(stripes.clone(), 1.3, 0.5);
+        let moved = (0..100)
+            .map(|i| point(0.97, i as Float * 0.31, i as Float * 0.17))
+            .filter(|&q| p.color_at(q) != stripes.color_at(q))
+            .count();
+        assert!(moved > 0 && moved < 100);
+    }
+
+    #[test]
+    fn blending_crossed_stripes() {
+        let along_x = Pattern::stripped(WHITE, BLACK);
+        let along_z = Pattern::stripped(WHITE, BLACK).with_transform(rot_y(PI / 2.0));
+        let p = Pattern::blend(along_x, along_z, 0.5);
+        assert_eq!(p.color_at(point(0.5, 0.0, -0.5)), WHITE);
+        assert_eq!(p.color_at(point(1.5, 0.0, -0.5)), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(p.color_at(point(1.5, 0.0, 0.5)), BLACK);
+        assert_eq!(p.colors().len(), 4);
+    }
+
+    #[test]
+    fn blending_through_a_mask() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let p = Pattern::blend_with(
+            Pattern::stripped(WHITE, WHITE),
+            Pattern::stripped(red, red),
+            BlendWeight::Mask(Arc::new(Pattern::gradient(BLACK, WHITE))),
+        );
+        assert_eq!(p.color_at(point(0.0, 0.0, 0.0)), WHITE);
+        assert_eq!(
+            p.color_at(point(0.25, 0.0, 0.0)),
+            Color::new(1.0, 0.75, 0.75)
+        );
+    }
+
+    #[test]
+    fn gradient_through_several_stops() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let p = Pattern::gradient_stops(
+            [(1.0, WHITE), (0.0, BLACK), (0.5, red)],
+            GradientWrap::Clamp,
+        );
+        assert_eq!(p.color_at(point(0.25, 0.0, 0.0)), Color::new(0.5, 0.0, 0.0));
+        assert_eq!(p.color_at(point(0.5, 0.0, 0.0)), red);
+        assert_eq!(p.color_at(point(0.75, 0.0, 0.0)), Color::new(1.0, 0.5, 0.5));
+        assert_eq!(p.color_at(point(-3.0, 0.0, 0.0)), BLACK);
+        assert_eq!(p.color_at(point(3.0, 0.0, 0.0)), WHITE);
+        assert_eq!(p.colors(), vec![BLACK, red, WHITE]);
+    }
+
+    #[test]
+    fn gradient_wrap_modes() {
+        let stops = [(0.0, BLACK), (1.0, WHITE)];
+        let repeat = Pattern::gradient_stops(stops, GradientWrap::Repeat);
+        let mirror = Pattern::gradient_stops(stops, GradientWrap::Mirror);
+        let quarter = Color::new(0.25, 0.25, 0.25);
+        assert_eq!(repeat.color_at(point(1.25, 0.0, 0.0)), quarter);
+        assert_eq!(repeat.color_at(point(-0.75, 0.0, 0.0)), quarter);
+        assert_eq!(
+            mirror.color_at(point(1.25, 0.0, 0.0)),
+            Color::new(0.75, 0.75, 0.75)
+        );
+        assert_eq!(mirror.color_at(point(-0.25, 0.0, 0.0)), quarter);
+        assert_eq!(mirror.color_at(point(2.25, 0.0, 0.0)), quarter);
+    }
+
+    #[test]
+    fn checkers_of_nested_patterns() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let p = Pattern::checker(
+            Pattern::gradient(BLACK, WHITE),
+            Pattern::stripped(red, BLACK).with_transform(scaling(0.5, 0.5, 0.5)),
+        );
+        assert_eq!(p.color_at(point(0.5, 0.0, 0.0)), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(p.color_at(point(1.25, 0.0, 0.0)), red);
+        assert_eq!(p.color_at(point(1.75, 0.0, 0.0)), BLACK);
+        assert_eq!(p.colors(), vec![BLACK, WHITE, red, BLACK]);
+    }
+
+    #[test]
+    fn a_solid_pattern_is_constant() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let p = Pattern::solid(red).with_transform(scaling(3.0, 3.0, 3.0));
+        assert_eq!(p.color_at(point(0.0, 0.0, 0.0)), red);
+        assert_eq!(
+            p.pattern_at_shape(&Shape::sphere(), point(-7.3, 2.0, 0.5)),
+            red
+        );
+        assert_eq!(p.colors(), vec![red]);
+        let stripes = Pattern::stripped(Pattern::solid(red), BLACK);
+        assert_eq!(stripes.color_at(point(0.5, 0.0, 0.0)), red);
+    }
+
+    #[test]
+    fn texture_mapping_checkers_on_a_sphere() {
+        let uv = UvPattern::checkers(16.0, 8.0, BLACK, WHITE);
+        let p = Pattern::texture_map(uv, UvMapping::Spherical);
+        for (q, expected) in [
+            (point(0.4315, 0.4670, 0.7719), WHITE),
+            (point(-0.9654, 0.2552, -0.0534), BLACK),
+            (point(0.1039, 0.7090, 0.6975), WHITE),
+            (point(-0.4986, -0.7856, -0.3663), BLACK),
+            (point(-0.0317, -0.9395, 0.3411), BLACK),
+            (point(0.4809, -0.7721, 0.4154), BLACK),
+            (point(0.0285, -0.9612, -0.2745), BLACK),
+            (point(-0.5734, -0.2162, -0.7903), WHITE),
+            (point(0.7688, -0.1470, 0.6223), BLACK),
+            (point(-0.7652, 0.2175, 0.6060), BLACK),
+        ] {
+            assert_eq!(p.color_at(q), expected);
+        }
+    }
+
+    #[test]
+    fn finding_the_colors_on_a_mapped_cube() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let yellow = Color::new(1.0, 1.0, 0.0);
+        let brown = Color::new(1.0, 0.5, 0.0);
+        let green = Color::new(0.0, 1.0, 0.0);
+        let cyan = Color::new(0.0, 1.0, 1.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+        let purple = Color::new(1.0, 0.0, 1.0);
+        let left = UvPattern::align_check(yellow, cyan, red, blue, brown);
+        let front = UvPattern::align_check(cyan, red, yellow, brown, green);
+        let right = UvPattern::align_check(red, yellow, purple, green, WHITE);
+        let back = UvPattern::align_check(green, purple, cyan, WHITE, blue);
+        let up = UvPattern::align_check(brown, cyan, purple, red, yellow);
+        let down = UvPattern::align_check(purple, brown, green, blue, WHITE);
+        let p = Pattern::cube_map([left, right, front, back, up, down]);
+        for (q, expected) in [
+            (point(-1.0, 0.0, 0.0), yellow),
+            (point(-1.0, 0.9, -0.9), cyan),
+            (point(-1.0, 0.9, 0.9), red),
+            (point(-1.0, -0.9, -0.9), blue),
+            (point(-1.0, -0.9, 0.9), brown),
+            (point(0.0, 0.0, 1.0), cyan),
+            (point(-0.9, 0.9, 1.0), red),
+            (point(0.9, -0.9, 1.0), green),
+            (point(1.0, 0.0, 0.0), red),
+            (point(1.0, 0.9, 0.9), yellow),
+            (point(0.0, 0.0, -1.0), green),
+            (point(0.9, 0.9, -1.0), purple),
+            (point(0.0, 1.0, 0.0), brown),
+            (point(-0.9, 1.0, -0.9), cyan),
+            (point(0.9, 1.0, 0.9), yellow),
+            (point(0.0, -1.0, 0.0), purple),
+            (point(-0.9, -1.0, -0.9), blue),
+        ] {
+            assert_eq!(p.color_at(q), expected, "{q:?}");
+        }
+    }
+
+    #[test]
+    fn uv_checkers_are_clean_on_curved_surfaces() {
+        let sphere = Shape::sphere();
+        // both sides of the rounding error of a point on the sphere
+        let (inside, outside) = (
+            point(1.0 - Float::EPSILON, 0.1, 0.1),
+            point(1.0 + Float::EPSILON, 0.1, 0.1),
+        );
+        let solid = Pattern::checker(WHITE, BLACK);
+        assert_ne!(solid.color_at(inside), solid.color_at(outside));
+        let globe = Pattern::uv_checkers(16.0, 8.0, WHITE, BLACK, sphere.uv_mapping());
+        assert_eq!(globe.color_at(inside), globe.color_at(outside));
+        assert_eq!(globe.colors(), vec![WHITE, BLACK]);
+    }
+
+    #[test]
+    fn a_sky_fades_from_the_horizon_to_the_zenith() {
+        let sky = Sky::default();
+        let clear = Pattern::sky(Sky {
+            coverage: 0.0,
+            ..sky
+        });
+        assert_eq!(clear.color_at_direction(vector(0.0, 2.0, 0.0)), sky.zenith);
+        assert_eq!(clear.color_at_direction(vector(1.0, -0.1, 0.0)), sky.ground);
+        let low = clear.color_at_direction(vector(1.0, 1e-9, 0.0));
+        assert!((low.b() - sky.horizon.b()).abs() < 1e-3);
+        // clouds cover part of the sky only
+        let cloudy = Pattern::sky(sky);
+        let covered = (0..200)
+            .map(|i| {
+                vector(
+                    (i as Float * 0.37).sin(),
+                    1.0,
+                    (i as Float * 0.37).cos() * i as Float / 50.0,
+                )
+            })
+            .filter(|&d| cloudy.color_at_direction(d) != clear.color_at_direction(d))
+            .count();
+        assert!(covered > 10 && covered < 190, "{covered}");
+    }
+
+    #[derive(Debug)]
+    struct Bands(Float);
+
+    impl PatternAt for Bands {
+        fn color_at(&self, p: &Tuple) -> Color {
+            if (p.y / self.0).floor() % 2.0 == 0.0 {
+                WHITE
+            } else {
+                BLACK
+            }
+        }
+    }
+
+    #[test]
+    fn wrapping_a_user_pattern() {
+        let p = Pattern::custom(Bands(0.5)).with_transform(scaling(2.0, 2.0, 2.0));
+        assert_eq!(p.color_at(point(0.0, 0.25, 0.0)), WHITE);
+        assert_eq!(p.color_at(point(0.0, 0.75, 0.0)), BLACK);
+        assert_eq!(
+            p.pattern_at_shape(&Shape::sphere(), point(0.0, 0.75, 0.0)),
+            WHITE
+        );
+        assert_eq!(p, p.clone());
+        assert_ne!(p, Pattern::custom(Bands(0.5)));
+        // built-in patterns are user patterns too
+        let built_in: &dyn PatternAt = &Pattern::stripped(WHITE, BLACK);
+        assert_eq!(built_in.color_at(&point(1.5, 0.0, 0.0)), BLACK);
+    }
+
+    #[test]
+    fn a_pattern_from_a_closure() {
+        let p = Pattern::from_fn(|p| Color::new(p.x, 0.0, 0.0)).scaling(2.0, 1.0, 1.0);
+        assert_eq!(p.color_at(point(0.5, 0.0, 0.0)), Color::new(0.5, 0.0, 0.0));
+        assert_eq!(
+            p.pattern_at_shape(&Shape::sphere(), point(0.5, 0.0, 0.0)),
+            Color::new(0.25, 0.0, 0.0)
+        );
+        assert_eq!(
+            format!("{:?}", p.p_type),
+            "Custom(CustomPattern(FnPattern))"
+        );
+    }
+
+    #[test]
+    fn chaining_pattern_transforms() {
+        let chained = Pattern::stripped(WHITE, BLACK)
+            .scaling(2.0, 2.0, 2.0)
+            .rot_y(PI / 2.0)
+            .translation(0.5, 0.0, 0.0);
+        let built = Pattern::stripped(WHITE, BLACK)
+            .with_transform(translation(0.5, 0.0, 0.0) * rot_y(PI / 2.0) * scaling(2.0, 2.0, 2.0));
+        assert_eq!(chained, built);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn patterns_round_trip_through_serde() {
+        let p = Pattern::checker(
+            Pattern::gradient_stops([(0.0, BLACK), (1.0, WHITE)], GradientWrap::Mirror),
+            Color::new(1.0, 0.0, 0.0),
+        )
+        .scaling(0.5, 0.5, 0.5)
+        .rot_y(PI / 3.0);
+        let json = serde_json::to_string(&p).unwrap();
+        assert_eq!(serde_json::from_str::<Pattern>(&json).unwrap(), p);
+        assert!(serde_json::to_string(&Pattern::custom(Bands(0.5))).is_err());
+    }
 }
@@ -1,25 +1,38 @@
-use crate::{object::LocalIntersect, tuple::vector, util::EPSILON};
+use crate::{
+    bounds::Bounds,
+    object::{LocalHits, LocalIntersect},
+    tuple::{point, vector},
+    util::{Float, EPSILON},
+};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Plane;
 
 impl LocalIntersect for Plane {
-    fn local_intersect(&self, r: crate::ray::Ray) -> Vec<f64> {
+    fn local_intersect(&self, r: crate::ray::Ray) -> LocalHits {
         if r.direction.y.abs() < EPSILON {
-            vec![]
+            LocalHits::new()
         } else {
-            vec![-r.origin.y / r.direction.y]
+            LocalHits::from([-r.origin.y / r.direction.y])
         }
     }
 
     fn local_normal_at(&self, _: &crate::tuple::Tuple) -> crate::tuple::Tuple {
         vector(0.0, 1.0, 0.0)
     }
+
+    fn local_bounds(&self) -> Bounds {
+        Bounds::new(
+            point(Float::NEG_INFINITY, 0.0, Float::NEG_INFINITY),
+            point(Float::INFINITY, 0.0, Float::INFINITY),
+        )
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{intersection::Intersectable, object::Shape, ray::Ray, tuple::point};
+    use crate::{intersection::Intersectable, object::Shape, ray::Ray};
 
     use super::*;
 
@@ -55,7 +68,7 @@ mod tests {
         let xs = p.intersects(r).data().clone();
         assert_eq!(xs.len(), 1);
         assert_eq!(xs[0].time, 1.0);
-        assert_eq!(xs[0].object, p);
+        assert_eq!(*xs[0].object, p);
     }
 
     #[test]
@@ -65,6 +78,6 @@ mod tests {
         let xs = p.intersects(r).data().clone();
         assert_eq!(xs.len(), 1);
         assert_eq!(xs[0].time, 1.0);
-        assert_eq!(xs[0].object, p);
+        assert_eq!(*xs[0].object, p);
     }
 }
@@ -0,0 +1,246 @@
+use std::ops::Mul;
+
+use crate::{
+    matrix::Mat4,
+    tuple::{vector, Tuple},
+    util::{flt_eq, Float},
+};
+
+/// A rotation, `w + xi + yj + zk` of unit length.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quaternion {
+    pub w: Float,
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+impl Quaternion {
+    pub const fn new(w: Float, x: Float, y: Float, z: Float) -> Self {
+        Self { w, x, y, z }
+    }
+
+    /// No rotation.
+    pub const fn identity() -> Self {
+        Self::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    /// Rotation by `angle` radians around `axis`, counterclockwise when
+    /// looking down the axis, like `rot_x`, `rot_y` and `rot_z`.
+    pub fn from_axis_angle(axis: Tuple, angle: Float) -> Self {
+        let axis = axis.norm();
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        Self::new(cos, axis.x * sin, axis.y * sin, axis.z * sin)
+    }
+
+    /// Rotation of `m`, whose upper 3x3 part must be a rotation matrix.
+    pub fn from_matrix(m: &Mat4) -> Self {
+        let (m00, m11, m22) = (m[(0, 0)], m[(1, 1)], m[(2, 2)]);
+        let trace = m00 + m11 + m22;
+        let q = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Self::new(
+                s / 4.0,
+                (m[(2, 1)] - m[(1, 2)]) / s,
+                (m[(0, 2)] - m[(2, 0)]) / s,
+                (m[(1, 0)] - m[(0, 1)]) / s,
+            )
+        } else if m00 > m11 && m00 > m22 {
+            let s = (1.0 + m00 - m11 - m22).sqrt() * 2.0;
+            Self::new(
+                (m[(2, 1)] - m[(1, 2)]) / s,
+                s / 4.0,
+                (m[(0, 1)] + m[(1, 0)]) / s,
+                (m[(0, 2)] + m[(2, 0)]) / s,
+            )
+        } else if m11 > m22 {
+            let s = (1.0 + m11 - m00 - m22).sqrt() * 2.0;
+            Self::new(
+                (m[(0, 2)] - m[(2, 0)]) / s,
+                (m[(0, 1)] + m[(1, 0)]) / s,
+                s / 4.0,
+                (m[(1, 2)] + m[(2, 1)]) / s,
+            )
+        } else {
+            let s = (1.0 + m22 - m00 - m11).sqrt() * 2.0;
+            Self::new(
+                (m[(1, 0)] - m[(0, 1)]) / s,
+                (m[(0, 2)] + m[(2, 0)]) / s,
+                (m[(1, 2)] + m[(2, 1)]) / s,
+                s / 4.0,
+            )
+        };
+        q.norm()
+    }
+
+    pub fn mag(&self) -> Float {
+        self.dot(*self).sqrt()
+    }
+
+    pub fn norm(&self) -> Self {
+        let mag = self.mag();
+        Self::new(self.w / mag, self.x / mag, self.y / mag, self.z / mag)
+    }
+
+    pub fn dot(&self, rhs: Self) -> Float {
+        self.w * rhs.w + self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
+
+    /// The opposite rotation.
+    pub fn conjugate(&self) -> Self {
+        Self::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    /// Rotation `t` of the way from `self` to `other` along the shortest arc,
+    /// at constant angular speed.
+    pub fn slerp(&self, other: Self, t: Float) -> Self {
+        // q and -q are the same rotation, the closest one takes the short way
+        let (other, cos) = match self.dot(other) {
+            cos if cos < 0.0 => (Self::new(-other.w, -other.x, -other.y, -other.z), -cos),
+            cos => (other, cos),
+        };
+        let (a, b) = if cos > 0.9995 {
+            // too close for the sine to be divided by, lerp instead
+            (1.0 - t, t)
+        } else {
+            let theta = cos.acos();
+            (
+                ((1.0 - t) * theta).sin() / theta.sin(),
+                (t * theta).sin() / theta.sin(),
+            )
+        };
+        Self::new(
+            a * self.w + b * other.w,
+            a * self.x + b * other.x,
+            a * self.y + b * other.y,
+            a * self.z + b * other.z,
+        )
+        .norm()
+    }
+
+    /// `v`, a vector, rotated.
+    pub fn rotate(&self, v: Tuple) -> Tuple {
+        let rotated = *self * Self::new(0.0, v.x, v.y, v.z) * self.conjugate();
+        vector(rotated.x, rotated.y, rotated.z)
+    }
+
+    pub fn to_matrix(&self) -> Mat4 {
+        let Self { w, x, y, z } = self.norm();
+        Mat4::new([
+            1.0 - 2.0 * (y * y + z * z),
+            2.0 * (x * y - w * z),
+            2.0 * (x * z + w * y),
+            0.0,
+            2.0 * (x * y + w * z),
+            1.0 - 2.0 * (x * x + z * z),
+            2.0 * (y * z - w * x),
+            0.0,
+            2.0 * (x * z - w * y),
+            2.0 * (y * z + w * x),
+            1.0 - 2.0 * (x * x + y * y),
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            1.0,
+        ])
+    }
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl PartialEq for Quaternion {
+    fn eq(&self, other: &Self) -> bool {
+        flt_eq(self.w, other.w)
+            && flt_eq(self.x, other.x)
+            && flt_eq(self.y, other.y)
+            && flt_eq(self.z, other.z)
+    }
+}
+
+/// Rotates by `rhs`, then by `self`, like the product of their matrices.
+impl Mul for Quaternion {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self::new(
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        )
+    }
+}
+
+impl From<Quaternion> for Mat4 {
+    fn from(q: Quaternion) -> Self {
+        q.to_matrix()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        matrix::MatBase,
+        transformations::{rot_x, rot_y, rot_z},
+        util::consts::PI,
+    };
+
+    use super::*;
+
+    #[test]
+    fn rotations_around_the_axes_match_the_euler_ones() {
+        let angle = PI / 3.0;
+        let x = Quaternion::from_axis_angle(vector(1.0, 0.0, 0.0), angle);
+        let y = Quaternion::from_axis_angle(vector(0.0, 2.0, 0.0), angle);
+        let z = Quaternion::from_axis_angle(vector(0.0, 0.0, 1.0), angle);
+        assert_eq!(x.to_matrix(), rot_x(angle));
+        assert_eq!(y.to_matrix(), rot_y(angle));
+        assert_eq!(Mat4::from(z), rot_z(angle));
+        assert_eq!(Quaternion::identity().to_matrix(), Mat4::identity());
+    }
+
+    #[test]
+    fn rotations_round_trip_through_matrices() {
+        for (axis, angle) in [
+            (vector(1.0, 2.0, 3.0), 0.5),
+            (vector(0.0, 1.0, 0.0), PI),
+            (vector(-1.0, 0.0, 1.0), 3.0),
+        ] {
+            let m = Quaternion::from_axis_angle(axis, angle).to_matrix();
+            assert_eq!(Quaternion::from_matrix(&m).to_matrix(), m);
+        }
+    }
+
+    #[test]
+    fn slerp_turns_at_constant_speed() {
+        let axis = vector(0.0, 1.0, 0.0);
+        let a = Quaternion::from_axis_angle(axis, 0.2);
+        let b = Quaternion::from_axis_angle(axis, 1.8);
+        assert_eq!(a.slerp(b, 0.0), a);
+        assert_eq!(a.slerp(b, 1.0), b);
+        assert_eq!(a.slerp(b, 0.25), Quaternion::from_axis_angle(axis, 0.6));
+        // -b is the same rotation as b
+        let minus_b = Quaternion::new(-b.w, -b.x, -b.y, -b.z);
+        assert_eq!(
+            a.slerp(minus_b, 0.25),
+            Quaternion::from_axis_angle(axis, 0.6)
+        );
+    }
+
+    #[test]
+    fn products_compose_like_matrices() {
+        let a = Quaternion::from_axis_angle(vector(1.0, 1.0, 0.0), PI / 4.0);
+        let b = Quaternion::from_axis_angle(vector(0.0, 1.0, -1.0), 2.0);
+        assert_eq!((a * b).to_matrix(), a.to_matrix() * b.to_matrix());
+
+        let v = vector(1.0, -2.0, 3.0);
+        assert_eq!(a.rotate(v), a.to_matrix() * v);
+        assert_eq!(a.conjugate().to_matrix(), a.to_matrix().inverse());
+    }
+}
@@ -1,17 +1,43 @@
-use crate::{matrix::Mat4, tuple::Tuple};
+use crate::{matrix::Mat4, tuple::Tuple, util::Float};
+
+/// Why a ray is traced, which decides the objects it can see.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RayKind {
+    Camera,
+    Reflection,
+    Refraction,
+}
 
 #[derive(Debug, Clone, Copy)]
 pub struct Ray {
     pub origin: Tuple,
     pub direction: Tuple,
+    /// Angle of the cone the ray stands for: its footprint on a surface
+    /// widens by `spread` per unit of distance. 0 for an infinitely thin ray.
+    pub spread: Float,
 }
 impl Ray {
     pub fn new(origin: Tuple, direction: Tuple) -> Self {
-        Self { origin, direction }
+        Self {
+            origin,
+            direction,
+            spread: 0.0,
+        }
+    }
+
+    pub fn with_spread(mut self, spread: Float) -> Self {
+        self.spread = spread;
+        self
+    }
+
+    /// Width of the footprint of the ray at `time`.
+    pub fn footprint(&self, time: Float) -> Float {
+        self.spread * time.abs() * self.direction.mag()
     }
 
     #[inline]
-    pub fn position(&self, time: f64) -> Tuple {
+    pub fn position(&self, time: Float) -> Tuple {
         self.origin + self.direction * time
     }
 
@@ -19,6 +45,7 @@ impl Ray {
         Self {
             origin: t * self.origin,
             direction: t * self.direction,
+            spread: self.spread,
         }
     }
 }
@@ -0,0 +1,10 @@
+use crate::util::Float;
+
+// Refractive indices of common materials, e.g.
+// `Material::default().refractive_index(refractive_index::DIAMOND)`.
+
+pub const VACUUM: Float = 1.0;
+pub const AIR: Float = 1.00029;
+pub const WATER: Float = 1.333;
+pub const GLASS: Float = 1.5;
+pub const DIAMOND: Float = 2.417;
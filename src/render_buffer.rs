@@ -0,0 +1,191 @@
+use std::{io, path::Path};
+
+use crate::{canvas::Canvas, color::Color, util::Float};
+
+/// Running sum of the radiance samples taken for one pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Radiance {
+    sum: Color,
+    samples: u32,
+    /// Samples whose camera ray hit something.
+    covered: u32,
+}
+
+impl Radiance {
+    /// Radiance of `samples` samples adding up to `sum`, `covered` of which
+    /// hit an object.
+    pub fn from_parts(sum: Color, samples: u32, covered: u32) -> Self {
+        Self {
+            sum,
+            samples,
+            covered,
+        }
+    }
+
+    /// Replaces the samples by as many samples of value `mean`. A pixel
+    /// without samples has nothing to replace and stays unaccumulated.
+    pub fn set_mean(&mut self, mean: Color) {
+        if self.samples == 0 {
+            return;
+        }
+        self.sum = mean * self.samples as Float;
+    }
+
+    pub fn add(&mut self, sample: Color) {
+        self.add_with_coverage(sample, true)
+    }
+
+    /// Adds a sample, `covered` telling whether its camera ray hit an
+    /// object.
+    pub fn add_with_coverage(&mut self, sample: Color, covered: bool) {
+        self.sum = self.sum + sample;
+        self.samples += 1;
+        self.covered += covered as u32;
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Sum of the samples.
+    pub fn sum(&self) -> Color {
+        self.sum
+    }
+
+    /// Samples whose camera ray hit an object.
+    pub fn covered(&self) -> u32 {
+        self.covered
+    }
+
+    /// Fraction of the samples that hit an object, 0 when there are none.
+    pub fn alpha(&self) -> Float {
+        if self.samples == 0 {
+            0.0
+        } else {
+            self.covered as Float / self.samples as Float
+        }
+    }
+
+    /// Average of the samples, black when there are none yet.
+    pub fn mean(&self) -> Color {
+        if self.samples == 0 {
+            Color::black()
+        } else {
+            self.sum * (1.0 / self.samples as Float)
+        }
+    }
+}
+
+/// Linear, unclamped radiance accumulated by the integrators. Samples can be
+/// added over several passes, it is only turned into a [`Canvas`] at the end.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderBuffer {
+    width: usize,
+    height: usize,
+    pub pixels: Vec<Radiance>,
+}
+
+impl RenderBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Radiance::default(); width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn add_sample(&mut self, x: usize, y: usize, sample: Color) {
+        assert!(x < self.width);
+        assert!(y < self.height);
+        self.pixels[x + y * self.width].add(sample)
+    }
+
+    pub fn radiance_at(&self, x: usize, y: usize) -> Radiance {
+        assert!(x < self.width);
+        assert!(y < self.height);
+        self.pixels[x + y * self.width]
+    }
+
+    /// Mean radiance of the pixel.
+    pub fn pixel_at(&self, x: usize, y: usize) -> Color {
+        self.radiance_at(x, y).mean()
+    }
+
+    /// Adds the samples of `other`, rendered at the same size, to this one.
+    pub fn merge(&mut self, other: &RenderBuffer) {
+        assert_eq!(self.width, other.width);
+        assert_eq!(self.height, other.height);
+        for (a, b) in self.pixels.iter_mut().zip(&other.pixels) {
+            a.sum = a.sum + b.sum;
+            a.samples += b.samples;
+            a.covered += b.covered;
+        }
+    }
+
+    /// Saves the mean radiance, unclamped, as a Radiance HDR file.
+    pub fn save_hdr(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.to_canvas().save_hdr(path)
+    }
+
+    /// Image of the mean radiance, with an alpha channel when some camera
+    /// rays missed.
+    pub fn to_canvas(&self) -> Canvas {
+        let canvas = Canvas::new_with_colors(
+            self.width,
+            self.height,
+            self.pixels.iter().map(|p| p.mean()).collect(),
+        );
+        if self.pixels.iter().all(|p| p.covered == p.samples) {
+            canvas
+        } else {
+            canvas.with_alpha(self.pixels.iter().map(|p| p.alpha()).collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixels_average_their_samples() {
+        let mut b = RenderBuffer::new(2, 2);
+        b.add_sample(1, 0, Color::new(1.0, 0.0, 4.0));
+        b.add_sample(1, 0, Color::new(0.0, 0.0, 2.0));
+        assert_eq!(b.radiance_at(1, 0).samples(), 2);
+        assert_eq!(b.pixel_at(1, 0), Color::new(0.5, 0.0, 3.0));
+        assert_eq!(b.pixel_at(0, 0), Color::black());
+    }
+
+    #[test]
+    fn setting_the_mean_keeps_the_sample_count() {
+        let mut r = Radiance::default();
+        r.set_mean(Color::new(1.0, 1.0, 1.0));
+        assert_eq!(r, Radiance::default());
+        r.add(Color::black());
+        r.add(Color::black());
+        r.set_mean(Color::new(0.5, 0.5, 0.5));
+        assert_eq!(r.samples(), 2);
+        assert_eq!(r.mean(), Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn merging_passes_accumulates_samples() {
+        let mut a = RenderBuffer::new(1, 1);
+        let mut b = RenderBuffer::new(1, 1);
+        a.add_sample(0, 0, Color::new(1.0, 1.0, 1.0));
+        b.add_sample(0, 0, Color::new(0.0, 0.0, 0.0));
+        b.add_sample(0, 0, Color::new(0.5, 0.5, 0.5));
+        a.merge(&b);
+        assert_eq!(a.radiance_at(0, 0).samples(), 3);
+        assert_eq!(a.to_canvas().pixel_at(0, 0), Color::new(0.5, 0.5, 0.5));
+    }
+}
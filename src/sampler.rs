@@ -0,0 +1,332 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use crate::{sampling::stratified_2d, util::Float};
+
+/// Strategy used to place sub-pixel samples.
+///
+/// Every sampler returns points in the unit square `[0, 1) x [0, 1)`, so the
+/// same sequence can be used to offset a camera ray inside a pixel, to pick a
+/// point on a lens or on an area light.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Sampler {
+    /// A single sample at the center of the square, whatever the count.
+    #[default]
+    Center,
+    /// Independent uniform samples (jitter).
+    Random,
+    /// One jittered sample per cell of a grid of `n` cells, as square as the
+    /// factors of `n` allow. When they are too far apart, as for primes, one
+    /// sample per row and per column of an `n x n` grid (Latin hypercube).
+    Stratified,
+    /// Halton sequence in bases 2 and 3, shifted per pixel.
+    Halton,
+    /// Mitchell's best-candidate samples, spread apart like blue noise. The
+    /// same tile is used for every pixel, shifted per pixel.
+    BlueNoise,
+}
+
+/// Number of candidates drawn per already-placed sample by the best-candidate
+/// algorithm.
+const BLUE_NOISE_CANDIDATES: usize = 8;
+
+/// Seed of the blue noise tiles, the same for every render.
+const BLUE_NOISE_SEED: u64 = 0x5EED;
+
+thread_local! {
+    /// Blue noise tiles generated on the thread, by sample count.
+    static BLUE_NOISE_TILES: RefCell<HashMap<usize, Vec<(Float, Float)>>> =
+        RefCell::new(HashMap::new());
+}
+
+impl Sampler {
+    /// Returns `n` sample positions, drawing any randomness from `rng` so the
+    /// same generator state always gives the same samples.
+    pub fn samples(&self, n: usize, rng: &mut Rng) -> Vec<(Float, Float)> {
+        match self {
+            Sampler::Center => vec![(0.5, 0.5)],
+            Sampler::Random => (0..n).map(|_| (rng.next_f64(), rng.next_f64())).collect(),
+            Sampler::Stratified => {
+                let rows = (1..=n)
+                    .take_while(|r| r * r <= n)
+                    .filter(|r| n.is_multiple_of(*r))
+                    .last();
+                match rows {
+                    Some(rows) if n / rows <= 2 * rows => stratified_2d(n / rows, rows, rng),
+                    _ => latin_hypercube(n, rng),
+                }
+            }
+            Sampler::Halton => {
+                // Cranley-Patterson rotation, so neighbouring pixels do not
+                // share the exact same pattern.
+                let (ox, oy) = rng.next_2d();
+                (1..=n)
+                    .map(|i| {
+                        (
+                            (radical_inverse(i, 2) + ox).fract(),
+                            (radical_inverse(i, 3) + oy).fract(),
+                        )
+                    })
+                    .collect()
+            }
+            Sampler::BlueNoise => {
+                // shifting the tile around the torus keeps the samples as
+                // far apart as they were
+                let (ox, oy) = rng.next_2d();
+                BLUE_NOISE_TILES.with(|tiles| {
+                    tiles
+                        .borrow_mut()
+                        .entry(n)
+                        .or_insert_with(|| blue_noise_tile(n))
+                        .iter()
+                        .map(|(x, y)| ((x + ox).fract(), (y + oy).fract()))
+                        .collect()
+                })
+            }
+        }
+    }
+
+    /// Whether the first samples of a pixel are the same whatever their
+    /// count, so that more samples can be added to a finished render. The
+    /// stratified grid depends on the count.
+    pub fn is_progressive(&self) -> bool {
+        !matches!(self, Sampler::Stratified)
+    }
+}
+
+/// Van der Corput radical inverse of `i` in the given `base`.
+pub fn radical_inverse(mut i: usize, base: usize) -> Float {
+    let inv_base = 1.0 / base as Float;
+    let mut factor = inv_base;
+    let mut result = 0.0;
+    while i > 0 {
+        result += (i % base) as Float * factor;
+        i /= base;
+        factor *= inv_base;
+    }
+    result
+}
+
+/// `n` jittered samples, one in each row and each column of an `n x n`
+/// grid, the rows shuffled.
+fn latin_hypercube(n: usize, rng: &mut Rng) -> Vec<(Float, Float)> {
+    let mut rows = (0..n).collect::<Vec<_>>();
+    // Fisher-Yates
+    for i in (1..n).rev() {
+        rows.swap(i, (rng.next_u64() % (i as u64 + 1)) as usize);
+    }
+    let cell = 1.0 / n as Float;
+    rows.into_iter()
+        .enumerate()
+        .map(|(col, row)| {
+            let (u, v) = rng.next_2d();
+            ((col as Float + u) * cell, (row as Float + v) * cell)
+        })
+        .collect()
+}
+
+/// `n` samples placed by Mitchell's best-candidate algorithm, from a fixed
+/// seed.
+fn blue_noise_tile(n: usize) -> Vec<(Float, Float)> {
+    let mut rng = Rng::new(BLUE_NOISE_SEED);
+    let mut samples = Vec::with_capacity(n);
+    for i in 0..n {
+        let (best, _) = (0..(i * BLUE_NOISE_CANDIDATES).max(1))
+            .map(|_| {
+                let candidate = rng.next_2d();
+                (candidate, min_toroidal_distance(&samples, candidate))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .expect("at least one candidate");
+        samples.push(best);
+    }
+    samples
+}
+
+fn min_toroidal_distance(samples: &[(Float, Float)], p: (Float, Float)) -> Float {
+    samples
+        .iter()
+        .map(|s| {
+            let dx = (s.0 - p.0).abs();
+            let dy = (s.1 - p.1).abs();
+            let dx = dx.min(1.0 - dx);
+            let dy = dy.min(1.0 - dy);
+            dx * dx + dy * dy
+        })
+        .fold(Float::INFINITY, Float::min)
+}
+
+/// Small splitmix64 generator, good enough to scatter samples. Every
+/// stochastic part of a render draws from one of these, so a render is fully
+/// determined by its seed.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// Independent generator for the sub-stream `stream` (a pixel, a tile...),
+    /// which does not depend on the state of `self` after creation. This is
+    /// what keeps parallel renders reproducible.
+    pub fn split(&self, stream: u64) -> Self {
+        let mut mixer = Self(self.0 ^ stream.wrapping_mul(0xD1B5_4A32_D192_ED03));
+        Self(mixer.next_u64())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub fn next_f64(&mut self) -> Float {
+        // as many bits as the mantissa holds, so that it never rounds to 1
+        let bits = Float::MANTISSA_DIGITS;
+        (self.next_u64() >> (64 - bits)) as Float / (1u64 << bits) as Float
+    }
+
+    /// Uniform sample of the unit square, for the functions of
+    /// [`crate::sampling`].
+    pub fn next_2d(&mut self) -> (Float, Float) {
+        (self.next_f64(), self.next_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::flt_eq;
+
+    use super::*;
+
+    const ALL: [Sampler; 4] = [
+        Sampler::Random,
+        Sampler::Stratified,
+        Sampler::Halton,
+        Sampler::BlueNoise,
+    ];
+
+    #[test]
+    fn center_sampler_gives_a_single_centered_sample() {
+        assert_eq!(
+            Sampler::Center.samples(16, &mut Rng::new(3)),
+            vec![(0.5, 0.5)]
+        );
+    }
+
+    #[test]
+    fn samplers_stay_in_the_unit_square() {
+        for sampler in ALL {
+            let samples = sampler.samples(16, &mut Rng::new(42));
+            assert_eq!(samples.len(), 16);
+            for (x, y) in samples {
+                assert!((0.0..1.0).contains(&x), "{:?} {}", sampler, x);
+                assert!((0.0..1.0).contains(&y), "{:?} {}", sampler, y);
+            }
+        }
+    }
+
+    #[test]
+    fn samplers_are_deterministic_per_seed() {
+        for sampler in ALL {
+            assert_eq!(
+                sampler.samples(8, &mut Rng::new(7)),
+                sampler.samples(8, &mut Rng::new(7))
+            );
+            assert_ne!(
+                sampler.samples(8, &mut Rng::new(7)),
+                sampler.samples(8, &mut Rng::new(8))
+            );
+        }
+    }
+
+    #[test]
+    fn split_streams_are_reproducible_and_distinct() {
+        let root = Rng::new(1234);
+        let mut a = root.split(5);
+        let mut b = root.split(5);
+        let mut c = root.split(6);
+        let (va, vb, vc) = (a.next_u64(), b.next_u64(), c.next_u64());
+        assert_eq!(va, vb);
+        assert_ne!(va, vc);
+    }
+
+    #[test]
+    fn stratified_samples_fill_every_cell() {
+        let samples = Sampler::Stratified.samples(4, &mut Rng::new(0));
+        let mut cells = samples
+            .iter()
+            .map(|(x, y)| ((x * 2.0) as usize, (y * 2.0) as usize))
+            .collect::<Vec<_>>();
+        cells.sort();
+        assert_eq!(cells, vec![(0, 0), (0, 1), (1, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn stratified_samples_spread_over_both_halves() {
+        // 2 samples: one on each side, not both in the top half
+        let samples = Sampler::Stratified.samples(2, &mut Rng::new(0));
+        assert!(samples[0].0 < 0.5 && samples[1].0 >= 0.5, "{:?}", samples);
+        // 6 samples: a 3 x 2 grid
+        let mut cells = Sampler::Stratified
+            .samples(6, &mut Rng::new(0))
+            .iter()
+            .map(|(x, y)| ((x * 3.0) as usize, (y * 2.0) as usize))
+            .collect::<Vec<_>>();
+        cells.sort();
+        cells.dedup();
+        assert_eq!(cells.len(), 6);
+    }
+
+    #[test]
+    fn prime_counts_fall_back_to_a_latin_hypercube() {
+        let samples = Sampler::Stratified.samples(7, &mut Rng::new(0));
+        for axis in [|s: &(Float, Float)| s.0, |s: &(Float, Float)| s.1] {
+            let mut strata = samples
+                .iter()
+                .map(|s| (axis(s) * 7.0) as usize)
+                .collect::<Vec<_>>();
+            strata.sort();
+            assert_eq!(strata, (0..7).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn blue_noise_pixels_share_a_shifted_tile() {
+        let a = Sampler::BlueNoise.samples(16, &mut Rng::new(1));
+        let b = Sampler::BlueNoise.samples(16, &mut Rng::new(2));
+        // the same distances separate the samples of both pixels
+        let spacing = |s: &[(Float, Float)]| {
+            (1..s.len())
+                .map(|i| min_toroidal_distance(&s[..i], s[i]))
+                .collect::<Vec<_>>()
+        };
+        for (da, db) in spacing(&a).into_iter().zip(spacing(&b)) {
+            assert!(flt_eq(da, db), "{} {}", da, db);
+        }
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn progressive_samplers_start_with_the_same_samples() {
+        for sampler in [Sampler::Random, Sampler::Halton, Sampler::BlueNoise] {
+            assert!(sampler.is_progressive());
+            let few = sampler.samples(4, &mut Rng::new(3));
+            let many = sampler.samples(9, &mut Rng::new(3));
+            assert_eq!(few, many[..4]);
+        }
+        assert!(!Sampler::Stratified.is_progressive());
+    }
+
+    #[test]
+    fn radical_inverse_base_2() {
+        assert!(flt_eq(radical_inverse(1, 2), 0.5));
+        assert!(flt_eq(radical_inverse(2, 2), 0.25));
+        assert!(flt_eq(radical_inverse(3, 2), 0.75));
+        assert!(flt_eq(radical_inverse(1, 3), 1.0 / 3.0));
+    }
+}
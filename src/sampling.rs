@@ -0,0 +1,139 @@
+use crate::{
+    sampler::Rng,
+    tuple::{vector, Tuple},
+    util::{
+        consts::{FRAC_PI_2, FRAC_PI_4, PI},
+        Float,
+    },
+};
+
+/// `nx * ny` samples of the unit square, one jittered sample per cell of an
+/// `nx` by `ny` grid, row by row.
+pub fn stratified_2d(nx: usize, ny: usize, rng: &mut Rng) -> Vec<(Float, Float)> {
+    let (w, h) = (1.0 / nx as Float, 1.0 / ny as Float);
+    (0..ny)
+        .flat_map(|y| (0..nx).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let (u, v) = rng.next_2d();
+            ((x as Float + u) * w, (y as Float + v) * h)
+        })
+        .collect()
+}
+
+/// Point of the unit disk from a sample `(u, v)` of the unit square,
+/// uniformly spread. Shirley's concentric mapping keeps neighbouring samples
+/// together, so stratified samples stay stratified, on a lens for instance.
+pub fn concentric_disk((u, v): (Float, Float)) -> (Float, Float) {
+    let (a, b) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return (0.0, 0.0);
+    }
+    let (r, phi) = if a.abs() > b.abs() {
+        (a, FRAC_PI_4 * (b / a))
+    } else {
+        (b, FRAC_PI_2 - FRAC_PI_4 * (a / b))
+    };
+    (r * phi.cos(), r * phi.sin())
+}
+
+/// Direction uniformly spread over the unit sphere, from a sample `(u, v)`
+/// of the unit square. Its pdf is `1 / (4 PI)`.
+pub fn uniform_sphere((u, v): (Float, Float)) -> Tuple {
+    let z = 1.0 - 2.0 * u;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * v;
+    vector(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Two unit vectors completing `n` into an orthonormal basis.
+pub fn orthonormal_basis(n: Tuple) -> (Tuple, Tuple) {
+    let helper = if n.x.abs() > 0.9 {
+        vector(0.0, 1.0, 0.0)
+    } else {
+        vector(1.0, 0.0, 0.0)
+    };
+    let t = n.cross(helper).norm();
+    (t, n.cross(t))
+}
+
+/// Direction around the unit vector `axis` whose cosine with `axis` is
+/// `cos_theta`, at angle `phi` around it.
+fn around(axis: Tuple, cos_theta: Float, phi: Float) -> Tuple {
+    let (t, b) = orthonormal_basis(axis);
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    t * (sin_theta * phi.cos()) + b * (sin_theta * phi.sin()) + axis * cos_theta
+}
+
+/// Cosine weighted direction in the hemisphere around `normal`, from a sample
+/// `(u, v)` of the unit square. Its pdf is `cos(theta) / PI`.
+pub fn cosine_hemisphere(normal: Tuple, (u, v): (Float, Float)) -> Tuple {
+    around(normal, (1.0 - u).sqrt(), 2.0 * PI * v)
+}
+
+/// Direction around `axis` distributed like a Phong lobe of the given
+/// `exponent`, from a sample `(u, v)` of the unit square.
+pub fn phong_lobe(axis: Tuple, exponent: Float, (u, v): (Float, Float)) -> Tuple {
+    around(axis, u.powf(1.0 / (exponent + 1.0)), 2.0 * PI * v)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{sampler::Sampler, util::flt_eq};
+
+    use super::*;
+
+    #[test]
+    fn stratified_samples_fill_every_cell() {
+        let samples = stratified_2d(3, 2, &mut Rng::new(0));
+        let cells = samples
+            .iter()
+            .map(|(u, v)| ((u * 3.0) as usize, (v * 2.0) as usize))
+            .collect::<Vec<_>>();
+        assert_eq!(cells, vec![(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]);
+    }
+
+    #[test]
+    fn disk_samples_cover_the_unit_disk() {
+        assert_eq!(concentric_disk((0.5, 0.5)), (0.0, 0.0));
+        let (x, y) = concentric_disk((1.0, 0.5));
+        assert!(flt_eq(x, 1.0) && flt_eq(y, 0.0));
+        let mut quadrants = [0; 4];
+        for sample in stratified_2d(8, 8, &mut Rng::new(1)) {
+            let (x, y) = concentric_disk(sample);
+            assert!(x * x + y * y <= 1.0);
+            quadrants[(x > 0.0) as usize * 2 + (y > 0.0) as usize] += 1;
+        }
+        assert_eq!(quadrants, [16; 4]);
+    }
+
+    #[test]
+    fn sphere_samples_are_unit_and_balanced() {
+        let samples = stratified_2d(16, 16, &mut Rng::new(2));
+        let mut sum = vector(0.0, 0.0, 0.0);
+        for sample in &samples {
+            let d = uniform_sphere(*sample);
+            assert!(flt_eq(d.mag(), 1.0));
+            sum += d;
+        }
+        assert!((sum / samples.len() as Float).mag() < 0.05);
+    }
+
+    #[test]
+    fn cosine_samples_stay_in_the_hemisphere() {
+        let n = vector(0.0, 0.0, 1.0);
+        for (u, v) in Sampler::Halton.samples(32, &mut Rng::new(0)) {
+            let d = cosine_hemisphere(n, (u, v));
+            assert!(flt_eq(d.mag(), 1.0));
+            assert!((d ^ n) >= 0.0);
+        }
+    }
+
+    #[test]
+    fn narrow_phong_lobes_stay_close_to_their_axis() {
+        let axis = vector(1.0, 1.0, 0.0).norm();
+        for (u, v) in Sampler::Random.samples(32, &mut Rng::new(0)) {
+            let d = phong_lobe(axis, 10_000.0, (u, v));
+            assert!((d ^ axis) > 0.99);
+        }
+    }
+}
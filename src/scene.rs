@@ -0,0 +1,1261 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use serde_yaml::{Mapping, Value};
+
+use crate::{
+    camera::Camera,
+    color::Color,
+    lights::Light,
+    material::Material,
+    matrix::Mat4,
+    object::{Object, Shape},
+    transformations::{rot_x, rot_y, rot_z, scaling, shearing, translation, view_transform},
+    tuple::{point, vector, Tuple},
+    util::Float,
+    world::World,
+};
+
+/// Why a scene file could not be loaded, with where the problem is: the
+/// file, the line of the item and the path to the faulty field, such as
+/// `[3].material.diffuse` for the `diffuse` of the material of the fourth
+/// item.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneError {
+    file: Option<PathBuf>,
+    line: Option<usize>,
+    path: String,
+    message: String,
+}
+
+impl SceneError {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            file: None,
+            line: None,
+            path: String::new(),
+            message: message.into(),
+        }
+    }
+
+    /// The same error, in the field `key` of the enclosing value.
+    fn at(mut self, key: &str) -> Self {
+        self.path = match self.path.chars().next() {
+            None => key.to_string(),
+            Some('[') => format!("{key}{}", self.path),
+            Some(_) => format!("{key}.{}", self.path),
+        };
+        self
+    }
+
+    /// The same error, in the element `index` of the enclosing list.
+    fn at_index(mut self, index: usize) -> Self {
+        self.path = match self.path.chars().next() {
+            None | Some('[') => format!("[{index}]{}", self.path),
+            Some(_) => format!("[{index}].{}", self.path),
+        };
+        self
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file.display())?;
+        }
+        if let Some(line) = self.line {
+            write!(f, "{line}:")?;
+        }
+        if self.file.is_some() || self.line.is_some() {
+            f.write_str(" ")?;
+        }
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for SceneError {}
+
+impl From<serde_yaml::Error> for SceneError {
+    fn from(e: serde_yaml::Error) -> Self {
+        let message = e.to_string();
+        // the line is kept apart, like for the other errors
+        let message = message.split(" at line ").next().unwrap_or_default();
+        Self {
+            line: e.location().map(|l| l.line()),
+            ..Self::new(message)
+        }
+    }
+}
+
+/// World and camera described by a scene file.
+///
+/// A scene file is a YAML (or JSON) list of items, read in order:
+///
+/// ```yaml
+/// - add: camera
+///   width: 100
+///   height: 50
+///   field_of_view: 0.785
+///   from: [0, 1.5, -5]
+///   to: [0, 1, 0]
+///   up: [0, 1, 0]
+/// - add: light
+///   at: [-10, 10, -10]
+///   intensity: [1, 1, 1]
+/// - define: base-metal
+///   value: { reflective: 0.8, diffuse: 0.3 }
+/// - define: blue-metal
+///   extend: base-metal
+///   value: { color: [0.2, 0.3, 0.9] }
+/// - define: small
+///   value: [[scale, 0.5, 0.5, 0.5]]
+/// - add: sphere
+///   material: blue-metal
+///   transform: [small, [translate, 1, 0.5, 0]]
+/// ```
+///
+/// Materials take the fields of [`Material`], the ones left out keep their
+/// default. Transforms are applied in the order they are listed, the steps
+/// are `translate`, `scale`, `rotate-x`, `rotate-y`, `rotate-z`, `shear` and
+/// `matrix`, followed by the 16 numbers of the matrix row by row. Besides
+/// spheres and planes, `triangle` items take their three `points`. A camera
+/// can also be placed by a `transform` instead of `from`, `to` and `up`. A
+/// definition can stand for a material or a list of transforms wherever one
+/// is expected, and `extend` starts a definition from an earlier one: the
+/// fields of a material are overridden, transforms are appended. JSON has
+/// no tags, so the variants of patterns are written as single key objects
+/// instead: `{"!Checker": ...}` for `!Checker ...`.
+///
+/// `- include: props/table.yml` reads the items of another file in place,
+/// its definitions included. The path is relative to the including file.
+///
+/// A `frames` item makes the scene an animation. The fields of an added item
+/// listed under `animate` then change from frame to frame, interpolated
+/// between keyframes which must have the same shape, only their numbers
+/// differ:
+///
+/// ```yaml
+/// - frames: 24
+/// - add: sphere
+///   animate:
+///     transform:
+///       - { frame: 0, value: [[rotate-y, 0], [translate, 2, 0, 0]] }
+///       - { frame: 23, value: [[rotate-y, 3.14], [translate, 2, 0, 0]] }
+/// ```
+pub struct Scene {
+    pub world: World,
+    pub camera: Option<Camera>,
+    /// Number of frames of the animation, 1 for a still image.
+    pub frames: usize,
+}
+
+impl Scene {
+    pub fn parse(source: &str) -> Result<Self, SceneError> {
+        Self::parse_frame(source, 0)
+    }
+
+    /// The scene as it is at `frame` of its animation. Included files are
+    /// looked for from the working directory.
+    pub fn parse_frame(source: &str, frame: usize) -> Result<Self, SceneError> {
+        let mut scene = Scene {
+            world: World::new(),
+            camera: None,
+            frames: 1,
+        };
+        Parser::new(frame).items(source, &mut scene)?;
+        Ok(scene)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        Self::load_frame(path, 0)
+    }
+
+    /// Same as [`Scene::parse_frame`] for the scene file at `path`. Included
+    /// files are looked for next to it.
+    pub fn load_frame(path: impl AsRef<Path>, frame: usize) -> Result<Self, SceneError> {
+        #[cfg(feature = "ron")]
+        if path.as_ref().extension().is_some_and(|e| e == "ron") {
+            let path = path.as_ref();
+            let in_file = |e: SceneError| SceneError {
+                file: Some(path.to_path_buf()),
+                ..e
+            };
+            let source =
+                fs::read_to_string(path).map_err(|e| in_file(SceneError::new(e.to_string())))?;
+            return Self::parse_ron(&source).map_err(in_file);
+        }
+        let mut scene = Scene {
+            world: World::new(),
+            camera: None,
+            frames: 1,
+        };
+        Parser::new(frame).file(path.as_ref(), &mut scene)?;
+        Ok(scene)
+    }
+}
+
+/// Line where the item `index` of a block style list starts.
+fn item_line(source: &str, index: usize) -> Option<usize> {
+    source
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.starts_with("- ") || *line == "-")
+        .nth(index)
+        .map(|(n, _)| n + 1)
+}
+
+const CAMERA_FIELDS: &[&str] = &[
+    "add",
+    "animate",
+    "width",
+    "height",
+    "field_of_view",
+    "from",
+    "to",
+    "up",
+    "transform",
+];
+const LIGHT_FIELDS: &[&str] = &["add", "animate", "at", "intensity"];
+const SHAPE_FIELDS: &[&str] = &["add", "animate", "material", "transform", "points"];
+
+struct Parser {
+    definitions: HashMap<String, Value>,
+    frame: usize,
+    /// Directory relative paths start from.
+    directory: PathBuf,
+    /// Files being read, the outermost first.
+    files: Vec<PathBuf>,
+}
+
+impl Parser {
+    fn new(frame: usize) -> Self {
+        Self {
+            definitions: HashMap::new(),
+            frame,
+            directory: PathBuf::new(),
+            files: Vec::new(),
+        }
+    }
+
+    fn items(&mut self, source: &str, scene: &mut Scene) -> Result<(), SceneError> {
+        let items: Vec<Value> = serde_yaml::from_str(source)?;
+        for (i, item) in items.iter().enumerate() {
+            self.item(item, scene).map_err(|e| match e.file {
+                // from an included file, already located
+                Some(_) => e,
+                None => SceneError {
+                    line: item_line(source, i),
+                    ..e.at_index(i)
+                },
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Reads the items of the file at `path`, relative to the directory of
+    /// the file being read.
+    fn file(&mut self, path: &Path, scene: &mut Scene) -> Result<(), SceneError> {
+        let path = self.directory.join(path);
+        if self.files.contains(&path) {
+            return Err(SceneError::new(format!(
+                "{} includes itself",
+                path.display()
+            )));
+        }
+        let in_file = |e: SceneError| SceneError {
+            file: e.file.or_else(|| Some(path.clone())),
+            ..e
+        };
+        let source =
+            fs::read_to_string(&path).map_err(|e| in_file(SceneError::new(e.to_string())))?;
+        let directory = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let outer = std::mem::replace(&mut self.directory, directory);
+        self.files.push(path.clone());
+        let read = self.items(&source, scene).map_err(in_file);
+        self.files.pop();
+        self.directory = outer;
+        read
+    }
+
+    fn item(&mut self, item: &Value, scene: &mut Scene) -> Result<(), SceneError> {
+        let item = item.as_mapping().ok_or_else(|| {
+            SceneError::new("expected an `add`, `define`, `include` or `frames` item")
+        })?;
+        if item.contains_key("define") {
+            check_fields(item, &["define", "extend", "value"])?;
+            let name = parse_field(item, "define", string)?;
+            let value = field(item, "value")?;
+            let value = match item.get("extend") {
+                Some(base) => {
+                    let base = string(base).and_then(|b| self.definition(b));
+                    extend(base.map_err(|e| e.at("extend"))?, value)?
+                }
+                None => value.clone(),
+            };
+            self.definitions.insert(name.to_string(), value);
+            return Ok(());
+        }
+        if item.contains_key("include") {
+            check_fields(item, &["include"])?;
+            let path = parse_field(item, "include", string)?;
+            return self.file(Path::new(path), scene);
+        }
+        if item.contains_key("frames") {
+            check_fields(item, &["frames"])?;
+            scene.frames = (parse_field(item, "frames", number)? as usize).max(1);
+            return Ok(());
+        }
+        let animated;
+        let item = match item.get("animate") {
+            Some(animate) => {
+                animated = self.animated(item, animate).map_err(|e| e.at("animate"))?;
+                &animated
+            }
+            None => item,
+        };
+        match parse_field(item, "add", string)? {
+            "camera" => {
+                check_fields(item, CAMERA_FIELDS)?;
+                let mut camera = Camera::new(
+                    parse_field(item, "width", number)? as usize,
+                    parse_field(item, "height", number)? as usize,
+                    parse_field(item, "field_of_view", number)?,
+                );
+                camera.set_transform(match item.get("transform") {
+                    // an exact transform, as written by `Scene::to_yaml`
+                    Some(t) => self.transform(t).map_err(|e| e.at("transform"))?,
+                    None => view_transform(
+                        parse_field(item, "from", point_from)?,
+                        parse_field(item, "to", point_from)?,
+                        parse_field(item, "up", vector_from)?,
+                    ),
+                });
+                scene.camera = Some(camera);
+            }
+            "light" => {
+                check_fields(item, LIGHT_FIELDS)?;
+                let [r, g, b] = parse_field(item, "intensity", triple)?;
+                scene.world.add_light(Light::new(
+                    parse_field(item, "at", point_from)?,
+                    Color::new(r, g, b),
+                ));
+            }
+            kind => {
+                let mut shape = match kind {
+                    "sphere" => Shape::sphere(),
+                    "plane" => Shape::plane(),
+                    "triangle" => {
+                        let [a, b, c] = parse_field(item, "points", points)?;
+                        Shape::triangle(a, b, c)
+                    }
+                    _ => {
+                        return Err(SceneError::new(format!(
+                        "unknown item `{kind}`, expected camera, light, sphere, plane or triangle"
+                    ))
+                        .at("add"))
+                    }
+                };
+                check_fields(item, SHAPE_FIELDS)?;
+                if kind != "triangle" && item.contains_key("points") {
+                    return Err(SceneError::new("only triangles have points").at("points"));
+                }
+                if item.contains_key("material") {
+                    shape.set_material(parse_field(item, "material", |v| self.material(v))?);
+                }
+                if item.contains_key("transform") {
+                    shape.set_transform(parse_field(item, "transform", |v| self.transform(v))?);
+                }
+                scene.world.add(shape);
+            }
+        }
+        Ok(())
+    }
+
+    /// `item` with its animated fields set to their value at the current
+    /// frame.
+    fn animated(&self, item: &Mapping, animate: &Value) -> Result<Mapping, SceneError> {
+        let Value::Mapping(fields) = animate else {
+            return Err(SceneError::new("expected fields mapped to their keyframes"));
+        };
+        let mut item = item.clone();
+        for (key, keyframes) in fields {
+            let name = string(key)?;
+            let value = keyframe_value(keyframes, self.frame as f64).map_err(|e| e.at(name))?;
+            item.insert(key.clone(), value);
+        }
+        Ok(item)
+    }
+
+    fn definition(&self, name: &str) -> Result<&Value, SceneError> {
+        self.definitions
+            .get(name)
+            .ok_or_else(|| SceneError::new(format!("`{name}` is not defined")))
+    }
+
+    /// The definition `name` stands for, while resolving the definitions in
+    /// `resolving`, which it must not be one of.
+    fn definition_within<'a>(
+        &'a self,
+        name: &'a str,
+        resolving: &[&str],
+    ) -> Result<&'a Value, SceneError> {
+        if resolving.contains(&name) {
+            return Err(SceneError::new(format!(
+                "`{name}` is defined in terms of itself"
+            )));
+        }
+        self.definition(name)
+    }
+
+    fn material(&self, value: &Value) -> Result<Material, SceneError> {
+        self.material_within(value, &mut vec![])
+    }
+
+    fn material_within<'a>(
+        &'a self,
+        value: &'a Value,
+        resolving: &mut Vec<&'a str>,
+    ) -> Result<Material, SceneError> {
+        match value {
+            Value::String(name) => {
+                let definition = self.definition_within(name, resolving)?;
+                resolving.push(name);
+                let material = self.material_within(definition, resolving);
+                resolving.pop();
+                material
+            }
+            Value::Mapping(fields) => material_from(fields),
+            _ => Err(SceneError::new("expected a material or the name of one")),
+        }
+    }
+
+    fn transform(&self, value: &Value) -> Result<Mat4, SceneError> {
+        self.transform_within(value, &mut vec![])
+    }
+
+    fn transform_within<'a>(
+        &'a self,
+        value: &'a Value,
+        resolving: &mut Vec<&'a str>,
+    ) -> Result<Mat4, SceneError> {
+        let Value::Sequence(steps) = value else {
+            return Err(SceneError::new("expected a list of transform steps"));
+        };
+        let mut transform = Mat4::identity();
+        for (i, step) in steps.iter().enumerate() {
+            let step = match step {
+                Value::String(name) => self.definition_within(name, resolving).and_then(|t| {
+                    resolving.push(name);
+                    let t = self.transform_within(t, resolving);
+                    resolving.pop();
+                    t
+                }),
+                step => transform_step(step),
+            };
+            transform = step.map_err(|e| e.at_index(i))? * transform;
+        }
+        Ok(transform)
+    }
+}
+
+/// Checks every field of `fields` against the defaults of [`Material`], so
+/// that a misspelled or mistyped field is reported by name.
+fn material_from(fields: &Mapping) -> Result<Material, SceneError> {
+    let Value::Mapping(fields) = &tagged(Value::Mapping(fields.clone())) else {
+        unreachable!("mappings stay mappings");
+    };
+    let Ok(Value::Mapping(defaults)) = serde_yaml::to_value(Material::default()) else {
+        unreachable!("materials serialize to a mapping");
+    };
+    for (key, value) in fields {
+        let name = string(key)?;
+        if !defaults.contains_key(key) {
+            return Err(SceneError::new(format!("unknown material field `{name}`")).at(name));
+        }
+        let mut one = defaults.clone();
+        one.insert(key.clone(), value.clone());
+        serde_yaml::from_value::<Material>(Value::Mapping(one))
+            .map_err(|e| SceneError::from(e).at(name))?;
+    }
+    Ok(serde_yaml::from_value(Value::Mapping(fields.clone()))?)
+}
+
+fn transform_step(step: &Value) -> Result<Mat4, SceneError> {
+    let Some((op, args)) = step.as_sequence().and_then(|s| s.split_first()) else {
+        return Err(SceneError::new(
+            "expected a transform step like [scale, 1, 2, 1] or a name",
+        ));
+    };
+    let op = string(op)?;
+    let args = args
+        .iter()
+        .enumerate()
+        .map(|(i, a)| number(a).map_err(|e| e.at_index(i + 1)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(match (op, args.as_slice()) {
+        ("translate", &[x, y, z]) => translation(x, y, z),
+        ("scale", &[x, y, z]) => scaling(x, y, z),
+        ("rotate-x", &[a]) => rot_x(a),
+        ("rotate-y", &[a]) => rot_y(a),
+        ("rotate-z", &[a]) => rot_z(a),
+        ("shear", &[xy, xz, yx, yz, zx, zy]) => shearing(xy, xz, yx, yz, zx, zy),
+        // row by row
+        ("matrix", m) if m.len() == 16 => Mat4::new(m.try_into().unwrap()),
+        _ => {
+            return Err(SceneError::new(format!(
+                "unknown transform `{op}` with {} arguments",
+                args.len()
+            )))
+        }
+    })
+}
+
+/// Value of a field at `frame`, between the keyframes around it.
+fn keyframe_value(keyframes: &Value, frame: f64) -> Result<Value, SceneError> {
+    let Value::Sequence(keyframes) = keyframes else {
+        return Err(SceneError::new("expected a list of keyframes"));
+    };
+    let mut keys = Vec::new();
+    for (i, key) in keyframes.iter().enumerate() {
+        let key = key
+            .as_mapping()
+            .ok_or_else(|| SceneError::new("expected a keyframe with a `frame` and a `value`"))
+            .and_then(|key| {
+                check_fields(key, &["frame", "value"])?;
+                Ok((parse_field(key, "frame", number_f64)?, field(key, "value")?))
+            });
+        keys.push(key.map_err(|e| e.at_index(i))?);
+    }
+    keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let (Some(first), Some(last)) = (keys.first(), keys.last()) else {
+        return Err(SceneError::new("an animated field needs keyframes"));
+    };
+    if frame <= first.0 {
+        return Ok(first.1.clone());
+    }
+    if frame >= last.0 {
+        return Ok(last.1.clone());
+    }
+    let i = keys.iter().position(|k| k.0 > frame).unwrap();
+    let ((f0, a), (f1, b)) = (keys[i - 1], keys[i]);
+    lerp(a, b, (frame - f0) / (f1 - f0))
+}
+
+/// Numbers of `a` moved towards the ones of `b`, the rest must match.
+fn lerp(a: &Value, b: &Value, t: f64) -> Result<Value, SceneError> {
+    let mismatch = || SceneError::new("keyframes must have the same shape");
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => {
+            let (x, y) = (x.as_f64().unwrap(), y.as_f64().unwrap());
+            Ok(Value::from(x + (y - x) * t))
+        }
+        (Value::Sequence(xs), Value::Sequence(ys)) if xs.len() == ys.len() => xs
+            .iter()
+            .zip(ys)
+            .map(|(x, y)| lerp(x, y, t))
+            .collect::<Result<_, _>>()
+            .map(Value::Sequence),
+        (Value::Mapping(xs), Value::Mapping(ys)) if xs.len() == ys.len() => {
+            let mut mapping = Mapping::new();
+            for (k, x) in xs {
+                let y = ys.get(k).ok_or_else(mismatch)?;
+                mapping.insert(k.clone(), lerp(x, y, t)?);
+            }
+            Ok(Value::Mapping(mapping))
+        }
+        (a, b) if a == b => Ok(a.clone()),
+        _ => Err(mismatch()),
+    }
+}
+
+/// `value` laid over `base`: mappings are merged, lists appended.
+fn extend(base: &Value, value: &Value) -> Result<Value, SceneError> {
+    match (base, value) {
+        (Value::Mapping(base), Value::Mapping(value)) => {
+            let mut merged = base.clone();
+            for (k, v) in value {
+                merged.insert(k.clone(), v.clone());
+            }
+            Ok(Value::Mapping(merged))
+        }
+        (Value::Sequence(base), Value::Sequence(value)) => {
+            Ok(Value::Sequence([base.clone(), value.clone()].concat()))
+        }
+        _ => Err(SceneError::new("a definition can only extend one of the same kind").at("value")),
+    }
+}
+
+fn check_fields(item: &Mapping, known: &[&str]) -> Result<(), SceneError> {
+    for key in item.keys() {
+        let name = string(key)?;
+        if !known.contains(&name) {
+            return Err(SceneError::new(format!(
+                "unknown field `{name}`, expected one of {}",
+                known.join(", ")
+            ))
+            .at(name));
+        }
+    }
+    Ok(())
+}
+
+fn field<'a>(item: &'a Mapping, key: &str) -> Result<&'a Value, SceneError> {
+    item.get(key)
+        .ok_or_else(|| SceneError::new(format!("missing field `{key}`")))
+}
+
+/// The field `key` of `item`, read by `parse`.
+fn parse_field<'a, T>(
+    item: &'a Mapping,
+    key: &str,
+    parse: impl FnOnce(&'a Value) -> Result<T, SceneError>,
+) -> Result<T, SceneError> {
+    parse(field(item, key)?).map_err(|e| e.at(key))
+}
+
+fn string(value: &Value) -> Result<&str, SceneError> {
+    value
+        .as_str()
+        .ok_or_else(|| SceneError::new("expected a string"))
+}
+
+fn number(value: &Value) -> Result<Float, SceneError> {
+    number_f64(value).map(|x| x as Float)
+}
+
+fn number_f64(value: &Value) -> Result<f64, SceneError> {
+    value
+        .as_f64()
+        .ok_or_else(|| SceneError::new("expected a number"))
+}
+
+fn triple(value: &Value) -> Result<[Float; 3], SceneError> {
+    match value.as_sequence().map(Vec::as_slice) {
+        Some([x, y, z]) => Ok([number(x)?, number(y)?, number(z)?]),
+        _ => Err(SceneError::new("expected a list of 3 numbers")),
+    }
+}
+
+fn mapping<const N: usize>(fields: [(&str, Value); N]) -> Mapping {
+    fields.into_iter().map(|(k, v)| (k.into(), v)).collect()
+}
+
+fn tuple_value(t: Tuple) -> Value {
+    vec![t.x, t.y, t.z].into()
+}
+
+/// Turns the `{"!Tag": value}` objects of JSON files into tagged values.
+fn tagged(value: Value) -> Value {
+    use serde_yaml::value::{Tag, TaggedValue};
+
+    match value {
+        Value::Mapping(fields) => {
+            let tag = match fields.iter().next() {
+                Some((Value::String(key), _)) if fields.len() == 1 && key.starts_with('!') => {
+                    Tag::new(key.as_str())
+                }
+                _ => {
+                    return Value::Mapping(
+                        fields.into_iter().map(|(k, v)| (k, tagged(v))).collect(),
+                    )
+                }
+            };
+            let (_, value) = fields.into_iter().next().expect("one field");
+            Value::Tagged(Box::new(TaggedValue {
+                tag,
+                value: tagged(value),
+            }))
+        }
+        Value::Sequence(values) => Value::Sequence(values.into_iter().map(tagged).collect()),
+        Value::Tagged(mut t) => {
+            t.value = tagged(t.value);
+            Value::Tagged(t)
+        }
+        other => other,
+    }
+}
+
+/// Transform made of a single `matrix` step.
+fn matrix_value(m: Mat4) -> Value {
+    let numbers = (0..16).map(|i| m[(i / 4, i % 4)].into());
+    let step: Vec<Value> = std::iter::once("matrix".into()).chain(numbers).collect();
+    vec![Value::from(step)].into()
+}
+
+fn points(value: &Value) -> Result<[Tuple; 3], SceneError> {
+    match value.as_sequence().map(Vec::as_slice) {
+        Some([a, b, c]) => Ok([
+            point_from(a).map_err(|e| e.at_index(0))?,
+            point_from(b).map_err(|e| e.at_index(1))?,
+            point_from(c).map_err(|e| e.at_index(2))?,
+        ]),
+        _ => Err(SceneError::new("expected a list of 3 points")),
+    }
+}
+
+fn point_from(value: &Value) -> Result<Tuple, SceneError> {
+    let [x, y, z] = triple(value)?;
+    Ok(point(x, y, z))
+}
+
+fn vector_from(value: &Value) -> Result<Tuple, SceneError> {
+    let [x, y, z] = triple(value)?;
+    Ok(vector(x, y, z))
+}
+
+impl Scene {
+    /// The scene as a YAML scene file, which [`Scene::parse`] reads back.
+    /// Only what the format describes is written: the camera, the lights,
+    /// and the shapes with their material and transform. Material fields
+    /// left at their default are left out.
+    pub fn to_yaml(&self) -> String {
+        serde_yaml::to_string(&self.items()).expect("scenes serialize")
+    }
+
+    /// Same as [`Scene::to_yaml`], as JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.items()).expect("scenes serialize")
+    }
+
+    fn items(&self) -> Vec<Mapping> {
+        let mut items = Vec::new();
+        if let Some(camera) = &self.camera {
+            items.push(mapping([
+                ("add", "camera".into()),
+                ("width", (camera.hsize() as u64).into()),
+                ("height", (camera.vsize() as u64).into()),
+                ("field_of_view", camera.fov().into()),
+                // from, to and up do not always give back the same matrix
+                ("transform", matrix_value(camera.transform)),
+            ]));
+        }
+        for light in self.world.lights() {
+            let i = light.intensity;
+            items.push(mapping([
+                ("add", "light".into()),
+                ("at", tuple_value(light.position)),
+                ("intensity", vec![i.r(), i.g(), i.b()].into()),
+            ]));
+        }
+        let Ok(Value::Mapping(defaults)) = serde_yaml::to_value(Material::default()) else {
+            unreachable!("materials serialize to a mapping");
+        };
+        for shape in self.world.objects() {
+            let mut item = match shape.object() {
+                Object::Sphere(_) => mapping([("add", "sphere".into())]),
+                Object::Plane(_) => mapping([("add", "plane".into())]),
+                Object::Triangle(t) => mapping([
+                    ("add", "triangle".into()),
+                    (
+                        "points",
+                        vec![tuple_value(t.p1), tuple_value(t.p2), tuple_value(t.p3)].into(),
+                    ),
+                ]),
+                // only found in tests
+                Object::No(_) => continue,
+            };
+            let Ok(Value::Mapping(mut material)) = serde_yaml::to_value(&shape.material) else {
+                unreachable!("materials serialize to a mapping");
+            };
+            material.retain(|k, v| defaults.get(k) != Some(v));
+            if !material.is_empty() {
+                item.insert("material".into(), Value::Mapping(material));
+            }
+            if shape.transform != Mat4::identity() {
+                item.insert("transform".into(), matrix_value(shape.transform));
+            }
+            items.push(item);
+        }
+        items
+    }
+
+    /// Reads a scene written in RON, which follows the Rust types: patterns,
+    /// shapes and transform steps are written as the enum variants they are.
+    /// Definitions and animations are only found in YAML scenes.
+    ///
+    /// ```ron
+    /// (
+    ///     camera: Some((width: 100, height: 50, field_of_view: 0.785,
+    ///         from: (0, 1.5, -5), to: (0, 1, 0), up: (0, 1, 0))),
+    ///     lights: [(at: (-10, 10, -10), intensity: (1, 1, 1))],
+    ///     objects: [
+    ///         (shape: Plane, material: (pattern: Some((p_type: Checker(
+    ///             a: Color((1, 1, 1)), b: Color((0, 0, 0))))))),
+    ///         (shape: Sphere, transform: [Translate(0, 1, 0)],
+    ///             material: (color: (1, 0.2, 0.2), reflective: 0.3)),
+    ///     ],
+    /// )
+    /// ```
+    #[cfg(feature = "ron")]
+    pub fn parse_ron(source: &str) -> Result<Self, SceneError> {
+        let document: ron_format::Document = ::ron::from_str(source).map_err(|e| SceneError {
+            line: Some(e.span.start.line),
+            ..SceneError::new(e.code.to_string())
+        })?;
+        Ok(document.into_scene())
+    }
+}
+
+#[cfg(feature = "ron")]
+mod ron_format {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Document {
+        #[serde(default)]
+        camera: Option<CameraSpec>,
+        #[serde(default)]
+        lights: Vec<LightSpec>,
+        #[serde(default)]
+        objects: Vec<ObjectSpec>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct CameraSpec {
+        width: usize,
+        height: usize,
+        field_of_view: Float,
+        from: [Float; 3],
+        to: [Float; 3],
+        up: [Float; 3],
+    }
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct LightSpec {
+        at: [Float; 3],
+        intensity: Color,
+    }
+
+    #[derive(Deserialize)]
+    enum ShapeSpec {
+        Sphere,
+        Plane,
+        Triangle([Float; 3], [Float; 3], [Float; 3]),
+    }
+
+    #[derive(Deserialize)]
+    enum Step {
+        Translate(Float, Float, Float),
+        Scale(Float, Float, Float),
+        RotateX(Float),
+        RotateY(Float),
+        RotateZ(Float),
+        Shear(Float, Float, Float, Float, Float, Float),
+    }
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct ObjectSpec {
+        shape: ShapeSpec,
+        #[serde(default)]
+        material: Material,
+        /// Applied in order, like in YAML scenes.
+        #[serde(default)]
+        transform: Vec<Step>,
+    }
+
+    fn to_point([x, y, z]: [Float; 3]) -> Tuple {
+        point(x, y, z)
+    }
+
+    impl Document {
+        pub fn into_scene(self) -> Scene {
+            let mut world = World::new();
+            for light in self.lights {
+                world.add_light(Light::new(to_point(light.at), light.intensity));
+            }
+            for object in self.objects {
+                let shape = match object.shape {
+                    ShapeSpec::Sphere => Shape::sphere(),
+                    ShapeSpec::Plane => Shape::plane(),
+                    ShapeSpec::Triangle(a, b, c) => {
+                        Shape::triangle(to_point(a), to_point(b), to_point(c))
+                    }
+                };
+                let transform = object.transform.iter().fold(Mat4::identity(), |m, step| {
+                    let step = match *step {
+                        Step::Translate(x, y, z) => translation(x, y, z),
+                        Step::Scale(x, y, z) => scaling(x, y, z),
+                        Step::RotateX(a) => rot_x(a),
+                        Step::RotateY(a) => rot_y(a),
+                        Step::RotateZ(a) => rot_z(a),
+                        Step::Shear(xy, xz, yx, yz, zx, zy) => shearing(xy, xz, yx, yz, zx, zy),
+                    };
+                    step * m
+                });
+                world.add(
+                    shape
+                        .with_transform(transform)
+                        .with_material(object.material),
+                );
+            }
+            let camera = self.camera.map(|c| {
+                let mut camera = Camera::new(c.width, c.height, c.field_of_view);
+                let [x, y, z] = c.up;
+                camera.set_transform(view_transform(
+                    to_point(c.from),
+                    to_point(c.to),
+                    vector(x, y, z),
+                ));
+                camera
+            });
+            Scene {
+                world,
+                camera,
+                frames: 1,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = "
+- add: camera
+  width: 100
+  height: 50
+  field_of_view: 0.785
+  from: [0, 1.5, -5]
+  to: [0, 1, 0]
+  up: [0, 1, 0]
+- add: light
+  at: [-10, 10, -10]
+  intensity: [1, 1, 1]
+- define: base-metal
+  value:
+    reflective: 0.8
+    diffuse: 0.3
+- define: blue-metal
+  extend: base-metal
+  value:
+    color: [0.2, 0.3, 0.9]
+    diffuse: 0.4
+- define: small
+  value:
+    - [scale, 0.5, 0.5, 0.5]
+- define: small-raised
+  extend: small
+  value:
+    - [translate, 0, 1, 0]
+- add: sphere
+  material: blue-metal
+  transform:
+    - small-raised
+    - [translate, 1, 0, 0]
+- add: plane
+";
+
+    #[test]
+    fn parsing_a_scene() {
+        let scene = Scene::parse(SCENE).unwrap();
+        let camera = scene.camera.unwrap();
+        assert_eq!((camera.hsize(), camera.vsize()), (100, 50));
+        assert_eq!(scene.world.lights().len(), 1);
+        assert_eq!(scene.world.objects().len(), 2);
+        assert_eq!(*scene.world.objects()[1], Shape::plane());
+    }
+
+    #[test]
+    fn definitions_extend_earlier_ones() {
+        let scene = Scene::parse(SCENE).unwrap();
+        let sphere = &scene.world.objects()[0];
+        assert_eq!(
+            sphere.material,
+            Material::default()
+                .reflective(0.8)
+                .diffuse(0.4)
+                .color(Color::new(0.2, 0.3, 0.9))
+        );
+        assert_eq!(
+            sphere.transform,
+            translation(1.0, 0.0, 0.0) * translation(0.0, 1.0, 0.0) * scaling(0.5, 0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn undefined_names_are_reported() {
+        let e = Scene::parse("- add: sphere\n  material: gold")
+            .err()
+            .unwrap();
+        assert_eq!(e.to_string(), "1: [0].material: `gold` is not defined");
+    }
+
+    #[test]
+    fn cyclic_definitions_are_reported() {
+        let e = Scene::parse("- define: a\n  value: a\n- add: sphere\n  material: a")
+            .err()
+            .unwrap();
+        assert_eq!(
+            e.to_string(),
+            "3: [1].material: `a` is defined in terms of itself"
+        );
+        let e = Scene::parse(
+            "- define: a\n  value: [b]\n- define: b\n  value: [a]\n- add: sphere\n  transform: [a]",
+        )
+        .err()
+        .unwrap();
+        assert!(
+            e.to_string().ends_with("`a` is defined in terms of itself"),
+            "{e}"
+        );
+        // a name used twice side by side is not a cycle
+        let s = Scene::parse(
+            "- define: t\n  value: [[translate, 1, 0, 0]]\n- add: sphere\n  transform: [t, t]",
+        )
+        .unwrap();
+        assert_eq!(s.world.objects()[0].transform, translation(2.0, 0.0, 0.0));
+    }
+
+    const ANIMATION: &str = "
+- frames: 5
+- add: sphere
+  animate:
+    transform:
+      - { frame: 0, value: [[translate, 0, 0, 0]] }
+      - { frame: 4, value: [[translate, 2, 0, 0]] }
+    material:
+      - { frame: 2, value: { color: [1, 0, 0] } }
+      - { frame: 4, value: { color: [0, 0, 1] } }
+";
+
+    #[test]
+    fn animated_fields_follow_their_keyframes() {
+        let at = |frame| Scene::parse_frame(ANIMATION, frame).unwrap();
+        assert_eq!(at(0).frames, 5);
+        assert_eq!(
+            at(1).world.objects()[0].transform,
+            translation(0.5, 0.0, 0.0)
+        );
+        assert_eq!(
+            at(1).world.objects()[0].material.color,
+            Color::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            at(3).world.objects()[0].material.color,
+            Color::new(0.5, 0.0, 0.5)
+        );
+        assert_eq!(
+            at(4).world.objects()[0].transform,
+            translation(2.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn keyframes_must_have_the_same_shape() {
+        let scene = "
+- add: sphere
+  animate:
+    transform:
+      - { frame: 0, value: [[translate, 0, 0, 0]] }
+      - { frame: 4, value: [[scale, 2, 2, 2]] }
+";
+        let e = Scene::parse_frame(scene, 2).err().unwrap();
+        assert_eq!(e.path(), "[0].animate.transform");
+        assert_eq!(e.message(), "keyframes must have the same shape");
+    }
+
+    #[test]
+    fn included_files_are_read_in_place() {
+        let dir = std::env::temp_dir().join("ray_tracer_scene_include");
+        fs::create_dir_all(dir.join("props")).unwrap();
+        fs::write(
+            dir.join("props/metals.yml"),
+            "- define: gold\n  value: { color: [1, 0.8, 0] }\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("props/ball.yml"),
+            "- include: metals.yml\n- add: sphere\n  material: gold\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("scene.yml"),
+            "- include: props/ball.yml\n- add: plane\n  material: gold\n",
+        )
+        .unwrap();
+        let scene = Scene::load(dir.join("scene.yml")).unwrap();
+        assert_eq!(scene.world.objects().len(), 2);
+        assert_eq!(
+            scene.world.objects()[1].material.color,
+            Color::new(1.0, 0.8, 0.0)
+        );
+
+        fs::write(dir.join("props/metals.yml"), "- add: cube\n").unwrap();
+        let e = Scene::load(dir.join("scene.yml")).err().unwrap();
+        assert!(e.to_string().starts_with(&format!(
+            "{}:1: [0].add: unknown item",
+            dir.join("props/metals.yml").display()
+        )));
+
+        fs::write(dir.join("props/metals.yml"), "- include: ball.yml\n").unwrap();
+        let e = Scene::load(dir.join("scene.yml")).err().unwrap();
+        assert!(e.message().ends_with("includes itself"), "{e}");
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn ron_scenes_keep_enum_variants() {
+        let scene = Scene::parse_ron(
+            "(
+                camera: Some((width: 10, height: 5, field_of_view: 1.0,
+                    from: (0, 1.5, -5), to: (0, 1, 0), up: (0, 1, 0))),
+                lights: [(at: (-10, 10, -10), intensity: (1, 1, 1))],
+                objects: [
+                    (shape: Plane, material: (pattern: Some((p_type: Stripe(
+                        a: Color((1, 1, 1)), b: Color((0, 0, 0))))))),
+                    (shape: Sphere, transform: [Scale(2, 2, 2), Translate(0, 1, 0)],
+                        material: (color: (1, 0.2, 0.2))),
+                ],
+            )",
+        )
+        .unwrap();
+        assert_eq!(scene.camera.unwrap().hsize(), 10);
+        assert_eq!(scene.world.lights().len(), 1);
+        let [floor, ball] = scene.world.objects() else {
+            panic!("expected two objects");
+        };
+        assert_eq!(
+            floor.material.pattern,
+            Some(crate::pattern::Pattern::stripped(
+                Color::white(),
+                Color::black()
+            ))
+        );
+        assert_eq!(
+            ball.transform,
+            translation(0.0, 1.0, 0.0) * scaling(2.0, 2.0, 2.0)
+        );
+        assert_eq!(ball.material.color, Color::new(1.0, 0.2, 0.2));
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn ron_errors_have_a_line() {
+        let e = Scene::parse_ron("(\n  objects: [(shape: Cube)],\n)")
+            .err()
+            .unwrap();
+        assert_eq!(e.line(), Some(2));
+    }
+
+    fn exported(scene: &Scene, text: String) {
+        let back = Scene::parse(&text).unwrap();
+        assert_eq!(back.world.objects(), scene.world.objects(), "{text}");
+        assert_eq!(back.world.lights(), scene.world.lights());
+        let (a, b) = (scene.camera.unwrap(), back.camera.unwrap());
+        assert_eq!((a.hsize(), a.vsize()), (b.hsize(), b.vsize()));
+        for (x, y) in [(0, 0), (7, 3)] {
+            let (r, s) = (a.ray_for_pixel(x, y), b.ray_for_pixel(x, y));
+            assert_eq!((r.origin, r.direction), (s.origin, s.direction));
+        }
+    }
+
+    #[test]
+    fn exported_scenes_read_back_the_same() {
+        let (mut world, camera) = crate::scenes::checkered_floor(16, 8);
+        world.add(Shape::triangle(
+            point(0.0, 0.0, 0.0),
+            point(1.0, 0.0, 0.0),
+            point(0.0, 1.0, 0.0),
+        ));
+        let scene = Scene {
+            world,
+            camera: Some(camera),
+            frames: 1,
+        };
+        exported(&scene, scene.to_yaml());
+        exported(&scene, scene.to_json());
+    }
+
+    #[test]
+    fn default_material_fields_are_left_out() {
+        let mut world = World::new();
+        world.add(Shape::sphere());
+        let scene = Scene {
+            world,
+            camera: None,
+            frames: 1,
+        };
+        assert_eq!(scene.to_yaml(), "- add: sphere\n");
+    }
+
+    #[test]
+    fn json_scenes_are_read_too() {
+        let scene = Scene::parse(r#"[{"add": "sphere", "material": {"ambient": 1}}]"#).unwrap();
+        assert_eq!(scene.world.objects()[0].material.ambient, 1.0);
+    }
+
+    #[test]
+    fn errors_point_at_the_faulty_field() {
+        let scene = "
+- add: light
+  at: [0, 0, 0]
+  intensity: [1, 1, 1]
+- add: sphere
+  material:
+    diffuse: shiny
+";
+        let e = Scene::parse(scene).err().unwrap();
+        assert_eq!(e.line(), Some(5));
+        assert_eq!(e.path(), "[1].material.diffuse");
+        assert!(
+            e.message().contains("invalid type: string \"shiny\""),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn unknown_fields_are_reported() {
+        let e = Scene::parse("- add: sphere\n  material: { difuse: 0.5 }")
+            .err()
+            .unwrap();
+        assert_eq!(e.path(), "[0].material.difuse");
+        let e = Scene::parse("- add: sphere\n  colour: [1, 0, 0]")
+            .err()
+            .unwrap();
+        assert_eq!(e.path(), "[0].colour");
+    }
+
+    #[test]
+    fn missing_fields_and_wrong_types_are_reported() {
+        let e = Scene::parse("- add: light\n  at: [0, 0, 0]").err().unwrap();
+        assert_eq!(e.to_string(), "1: [0]: missing field `intensity`");
+        let e = Scene::parse("- add: sphere\n  transform: [[scale, 1, two, 1]]")
+            .err()
+            .unwrap();
+        assert_eq!(e.to_string(), "1: [0].transform[0][2]: expected a number");
+    }
+
+    #[test]
+    fn syntax_errors_have_a_line() {
+        let e = Scene::parse("- add: sphere\n  material: [\n")
+            .err()
+            .unwrap();
+        assert!(e.line().is_some(), "{e}");
+    }
+}
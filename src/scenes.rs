@@ -0,0 +1,207 @@
+use crate::{
+    camera::Camera,
+    color::Color,
+    lights::Light,
+    material::Material,
+    object::Shape,
+    pattern::Pattern,
+    refractive_index,
+    sampler::Rng,
+    transformations::{rot_x, rot_z, scaling, translation, view_transform},
+    tuple::{point, vector},
+    util::{
+        consts::{FRAC_PI_2, PI},
+        Float,
+    },
+    world::World,
+};
+
+fn camera(hsize: usize, vsize: usize, fov: Float, from: (Float, Float, Float)) -> Camera {
+    let mut camera = Camera::new(hsize, vsize, fov);
+    camera.set_transform(view_transform(
+        point(from.0, from.1, from.2),
+        point(0.0, 1.0, 0.0),
+        vector(0.0, 1.0, 0.0),
+    ));
+    camera
+}
+
+fn matte(color: Color) -> Material {
+    Material::default().color(color).diffuse(0.7).specular(0.3)
+}
+
+fn glass() -> Material {
+    Material::default()
+        .color(Color::black())
+        .diffuse(0.1)
+        .specular(1.0)
+        .shininess(300.0)
+        .reflective(0.9)
+        .transparency(0.9)
+        .refractive_index(refractive_index::GLASS)
+}
+
+fn mirror() -> Material {
+    Material::default()
+        .color(Color::new(0.1, 0.1, 0.1))
+        .diffuse(0.2)
+        .specular(1.0)
+        .shininess(300.0)
+        .reflective(0.9)
+}
+
+/// Three spheres on a checkered floor, in front of a backdrop, lit from the
+/// upper left.
+pub fn checkered_floor(hsize: usize, vsize: usize) -> (World, Camera) {
+    let floor = Shape::plane()
+        .with_material(Material::default().specular(0.0).reflective(0.1))
+        .with_pattern(Pattern::checker(Color::white(), Color::new(0.3, 0.3, 0.3)));
+    let backdrop = Shape::plane()
+        .with_transform(translation(0.0, 0.0, 10.0) * rot_x(FRAC_PI_2))
+        .with_material(matte(Color::new(0.6, 0.7, 0.9)).specular(0.0));
+    let middle = Shape::sphere()
+        .with_transform(translation(-0.5, 1.0, 0.5))
+        .with_material(matte(Color::new(0.1, 1.0, 0.5)));
+    let right = Shape::sphere()
+        .with_transform(translation(1.5, 0.5, -0.5) * scaling(0.5, 0.5, 0.5))
+        .with_material(mirror());
+    let left = Shape::sphere()
+        .with_transform(translation(-1.5, 0.33, -0.75) * scaling(0.33, 0.33, 0.33))
+        .with_material(glass());
+    let world = World::new()
+        .with_light(Light::new(point(-10.0, 10.0, -10.0), Color::white()))
+        .with_objects([floor, backdrop, middle, right, left]);
+    (world, camera(hsize, vsize, PI / 3.0, (0.0, 1.5, -5.0)))
+}
+
+/// Closed box with a red wall on the left and a green one on the right, lit
+/// from under the ceiling, holding a glass and a mirror sphere. The inside
+/// spans `[-1, 1]` on x and z, `[0, 2]` on y, and the box is open towards
+/// the camera.
+pub fn cornell_box(hsize: usize, vsize: usize) -> (World, Camera) {
+    (
+        cornell_box_world(),
+        camera(hsize, vsize, PI / 3.0, (0.0, 1.0, -2.8)),
+    )
+}
+
+pub(crate) fn cornell_box_world() -> World {
+    let white = matte(Color::new(0.73, 0.73, 0.73)).specular(0.0);
+    let wall = |transform| Shape::plane().with_transform(transform);
+    let floor = wall(translation(0.0, 0.0, 0.0)).with_material(white.clone());
+    let ceiling = wall(translation(0.0, 2.0, 0.0)).with_material(white.clone());
+    let back = wall(translation(0.0, 0.0, 1.0) * rot_x(FRAC_PI_2)).with_material(white);
+    let left = wall(translation(-1.0, 0.0, 0.0) * rot_z(FRAC_PI_2))
+        .with_material(matte(Color::new(0.65, 0.05, 0.05)).specular(0.0));
+    let right = wall(translation(1.0, 0.0, 0.0) * rot_z(FRAC_PI_2))
+        .with_material(matte(Color::new(0.12, 0.45, 0.15)).specular(0.0));
+    let glass_ball = Shape::sphere()
+        .with_transform(translation(0.4, 0.35, -0.2) * scaling(0.35, 0.35, 0.35))
+        .with_material(glass());
+    let mirror_ball = Shape::sphere()
+        .with_transform(translation(-0.4, 0.45, 0.4) * scaling(0.45, 0.45, 0.45))
+        .with_material(mirror());
+    World::new()
+        .with_light(Light::new(point(0.0, 1.9, 0.0), Color::white()))
+        .with_objects([floor, ceiling, back, left, right, glass_ball, mirror_ball])
+}
+
+/// Grid of small random spheres around three large ones, a stress test in
+/// the spirit of "Ray Tracing in One Weekend". The same `seed` gives the
+/// same scene.
+pub fn random_spheres(seed: u64, hsize: usize, vsize: usize) -> (World, Camera) {
+    let mut rng = Rng::new(seed);
+    let mut random_color = || {
+        Color::new(
+            rng.next_f64() * rng.next_f64(),
+            rng.next_f64() * rng.next_f64(),
+            rng.next_f64() * rng.next_f64(),
+        )
+    };
+    let mut objects = vec![Shape::plane()
+        .with_material(Material::default().specular(0.0))
+        .with_pattern(Pattern::checker(
+            Color::new(0.2, 0.3, 0.1),
+            Color::new(0.9, 0.9, 0.9),
+        ))];
+    let mut rng = Rng::new(seed).split(1);
+    for a in -11..11 {
+        for b in -11..11 {
+            let (x, z) = (
+                a as Float + 0.9 * rng.next_f64(),
+                b as Float + 0.9 * rng.next_f64(),
+            );
+            // keep clear of the large spheres
+            if ((x - 4.0).powi(2) + z * z).sqrt() < 0.9 {
+                continue;
+            }
+            let choice = rng.next_f64();
+            let material = if choice < 0.8 {
+                matte(random_color())
+            } else if choice < 0.95 {
+                mirror()
+                    .color(random_color())
+                    .reflective(0.5 + rng.next_f64() / 2.0)
+            } else {
+                glass()
+            };
+            objects.push(
+                Shape::sphere()
+                    .with_transform(translation(x, 0.2, z) * scaling(0.2, 0.2, 0.2))
+                    .with_material(material),
+            );
+        }
+    }
+    let large = |x: Float| Shape::sphere().with_transform(translation(x, 1.0, 0.0));
+    objects.push(large(0.0).with_material(glass()));
+    objects.push(large(-4.0).with_material(matte(Color::new(0.4, 0.2, 0.1))));
+    objects.push(large(4.0).with_material(mirror().color(Color::new(0.7, 0.6, 0.5))));
+    let world = World::new()
+        .with_light(Light::new(point(-10.0, 20.0, -10.0), Color::white()))
+        .with_objects(objects);
+    (world, camera(hsize, vsize, PI / 9.0, (13.0, 2.0, -3.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ray::Ray;
+
+    use super::*;
+
+    #[test]
+    fn random_spheres_depend_on_the_seed() {
+        let (a, _) = random_spheres(1, 10, 10);
+        let (b, _) = random_spheres(1, 10, 10);
+        let (c, _) = random_spheres(2, 10, 10);
+        assert!(a.objects().len() > 300);
+        assert_eq!(a.objects(), b.objects());
+        assert_ne!(a.objects(), c.objects());
+    }
+
+    #[test]
+    fn the_cornell_box_is_closed_around_its_light() {
+        let (world, camera) = cornell_box(20, 20);
+        let light = world.lights()[0].position;
+        for direction in [
+            vector(1.0, 0.0, 0.0),
+            vector(-1.0, 0.0, 0.0),
+            vector(0.0, 1.0, 0.0),
+            vector(0.0, -1.0, 0.0),
+            vector(0.0, 0.0, 1.0),
+        ] {
+            assert!(!world
+                .intersects(Ray::new(light, direction))
+                .data()
+                .is_empty());
+        }
+        assert_ne!(world.color_at(camera.ray_for_pixel(10, 10)), Color::black());
+    }
+
+    #[test]
+    fn the_showcase_faces_its_spheres() {
+        let (world, camera) = checkered_floor(40, 20);
+        let r = camera.ray_for_pixel(20, 10);
+        let hit = world.intersects(r).data()[0].object.clone();
+        assert_eq!(hit, world.objects()[2]);
+    }
+}
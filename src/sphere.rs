@@ -1,32 +1,38 @@
 use crate::{
-    object::LocalIntersect,
+    bounds::Bounds,
+    object::{LocalHits, LocalIntersect},
     ray::Ray,
     tuple::{point, Tuple},
 };
 
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Sphere;
 
 impl LocalIntersect for Sphere {
-    fn local_intersect(&self, r: Ray) -> Vec<f64> {
+    fn local_intersect(&self, r: Ray) -> LocalHits {
         let sphere_to_ray = r.origin - point(0.0, 0.0, 0.0);
-        let a = r.direction ^ r.direction;
+        let a = r.direction.dot(r.direction);
         let b = 2.0 * (r.direction ^ sphere_to_ray);
-        let c = (sphere_to_ray ^ sphere_to_ray) - 1.0;
+        let c = sphere_to_ray.dot(sphere_to_ray) - 1.0;
         let discriminant = b * b - 4.0 * a * c;
         if discriminant < 0.0 {
-            vec![]
+            LocalHits::new()
         } else {
-            vec![
+            LocalHits::from([
                 (-b - discriminant.sqrt()) / (2.0 * a),
                 (-b + discriminant.sqrt()) / (2.0 * a),
-            ]
+            ])
         }
     }
 
     fn local_normal_at(&self, object_point: &Tuple) -> Tuple {
         *object_point - point(0.0, 0.0, 0.0)
     }
+
+    fn local_bounds(&self) -> Bounds {
+        Bounds::new(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0))
+    }
 }
 
 impl Sphere {
@@ -35,10 +41,14 @@ impl Sphere {
     }
 }
 
+impl Default for Sphere {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::f64::consts::{PI, SQRT_2};
-
     use crate::{
         intersection::Intersectable,
         matrix::Mat4,
@@ -46,6 +56,10 @@ mod tests {
         ray::Ray,
         transformations::{scaling, translation},
         tuple::{point, vector},
+        util::{
+            consts::{PI, SQRT_2},
+            Float,
+        },
     };
 
     use super::Sphere;
@@ -154,13 +168,17 @@ mod tests {
     fn normal_on_sphere_point_non_axial() {
         let s = Sphere::new();
         let n = s.local_normal_at(&point(
-            3f64.sqrt() / 3.0,
-            3f64.sqrt() / 3.0,
-            3f64.sqrt() / 3.0,
+            Float::sqrt(3.0) / 3.0,
+            Float::sqrt(3.0) / 3.0,
+            Float::sqrt(3.0) / 3.0,
         ));
         assert_eq!(
             n,
-            vector(3f64.sqrt() / 3.0, 3f64.sqrt() / 3.0, 3f64.sqrt() / 3.0)
+            vector(
+                Float::sqrt(3.0) / 3.0,
+                Float::sqrt(3.0) / 3.0,
+                Float::sqrt(3.0) / 3.0
+            )
         );
     }
 
@@ -168,14 +186,15 @@ mod tests {
     fn normal_on_sphere_is_normalized() {
         let s = Sphere::new();
         let n = s.local_normal_at(&point(
-            3f64.sqrt() / 3.0,
-            3f64.sqrt() / 3.0,
-            3f64.sqrt() / 3.0,
+            Float::sqrt(3.0) / 3.0,
+            Float::sqrt(3.0) / 3.0,
+            Float::sqrt(3.0) / 3.0,
         ));
 
         assert_eq!(n, n.norm());
     }
     #[test]
+    #[allow(clippy::approx_constant)]
     fn normal_on_translated_sphere() {
         let s = Shape::sphere().with_transform(Mat4::identity().translation(0.0, 1.0, 0.0));
         let n = s.normal_at(&point(0.0, 1.70711, -0.70711));
@@ -0,0 +1,52 @@
+use std::cell::Cell;
+
+/// Work done while tracing rays, counted per thread.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RayStats {
+    /// Ray-shape intersection tests, shadow rays included.
+    pub intersection_tests: u64,
+    /// Rays followed for shading: camera rays, reflections, refractions and
+    /// path bounces. Shadow rays are not counted.
+    pub rays: u64,
+}
+
+impl RayStats {
+    /// Work done between `earlier` and `self`, two snapshots of the same
+    /// thread.
+    pub fn since(&self, earlier: RayStats) -> RayStats {
+        RayStats {
+            intersection_tests: self.intersection_tests - earlier.intersection_tests,
+            rays: self.rays - earlier.rays,
+        }
+    }
+}
+
+thread_local! {
+    static STATS: Cell<RayStats> = const {
+        Cell::new(RayStats {
+            intersection_tests: 0,
+            rays: 0,
+        })
+    };
+}
+
+/// Counters of the current thread since it started.
+pub fn current() -> RayStats {
+    STATS.with(|s| s.get())
+}
+
+pub(crate) fn count_intersection_test() {
+    STATS.with(|s| {
+        let mut stats = s.get();
+        stats.intersection_tests += 1;
+        s.set(stats)
+    })
+}
+
+pub(crate) fn count_ray() {
+    STATS.with(|s| {
+        let mut stats = s.get();
+        stats.rays += 1;
+        s.set(stats)
+    })
+}
@@ -0,0 +1,482 @@
+use std::sync::Arc;
+
+use crate::{
+    canvas::Canvas,
+    color::Color,
+    tuple::Tuple,
+    util::{
+        consts::{FRAC_1_PI, PI},
+        Float,
+    },
+};
+
+/// Flattens a point of an object onto the unit square, so 2D textures can be
+/// wrapped around it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UvMapping {
+    /// Longitude and latitude around the origin, for spheres.
+    Spherical,
+    /// The xz plane, repeating every unit, for planes.
+    Planar,
+    /// Angle around the y axis and height, repeating every unit.
+    Cylindrical,
+    /// The face of the `[-1, 1]` cube the point faces, each face getting the
+    /// whole texture.
+    Cubic,
+}
+
+impl UvMapping {
+    /// `(u, v)` in `[0, 1)` of the point `p`, in object space.
+    pub fn map(&self, p: Tuple) -> (Float, Float) {
+        match self {
+            UvMapping::Spherical => {
+                let theta = p.x.atan2(p.z);
+                let radius = (p.x * p.x + p.y * p.y + p.z * p.z).sqrt();
+                let phi = (p.y / radius).acos();
+                let raw_u = theta / (2.0 * PI);
+                (1.0 - (raw_u + 0.5), 1.0 - phi * FRAC_1_PI)
+            }
+            UvMapping::Planar => (p.x.rem_euclid(1.0), p.z.rem_euclid(1.0)),
+            UvMapping::Cylindrical => {
+                let raw_u = p.x.atan2(p.z) / (2.0 * PI);
+                (1.0 - (raw_u + 0.5), p.y.rem_euclid(1.0))
+            }
+            UvMapping::Cubic => CubeFace::of(p).uv(p),
+        }
+    }
+
+    /// Extent in `(u, v)` of an area `footprint` wide on the unit object.
+    pub fn uv_footprint(&self, footprint: Float) -> Float {
+        match self {
+            // half a turn around the sphere spans all of v
+            UvMapping::Spherical => footprint * FRAC_1_PI,
+            UvMapping::Planar | UvMapping::Cylindrical => footprint,
+            UvMapping::Cubic => footprint / 2.0,
+        }
+    }
+}
+
+/// Faces of the `[-1, 1]` cube, in the order [`CubeFace::index`] gives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFace {
+    Left,
+    Right,
+    Front,
+    Back,
+    Up,
+    Down,
+}
+
+impl CubeFace {
+    /// Face the point lies on or faces, from its largest coordinate.
+    pub fn of(p: Tuple) -> Self {
+        let largest = p.x.abs().max(p.y.abs()).max(p.z.abs());
+        if largest == p.x {
+            CubeFace::Right
+        } else if largest == -p.x {
+            CubeFace::Left
+        } else if largest == p.y {
+            CubeFace::Up
+        } else if largest == -p.y {
+            CubeFace::Down
+        } else if largest == p.z {
+            CubeFace::Front
+        } else {
+            CubeFace::Back
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    /// `(u, v)` of `p` on this face, seen from outside the cube with `v`
+    /// going up (towards the back for the up face).
+    pub fn uv(&self, p: Tuple) -> (Float, Float) {
+        let side = |a: Float| (a + 1.0).rem_euclid(2.0) / 2.0;
+        match self {
+            CubeFace::Left => (side(p.z), side(p.y)),
+            CubeFace::Right => (side(-p.z), side(p.y)),
+            CubeFace::Front => (side(p.x), side(p.y)),
+            CubeFace::Back => (side(-p.x), side(p.y)),
+            CubeFace::Up => (side(p.x), side(-p.z)),
+            CubeFace::Down => (side(p.x), side(p.z)),
+        }
+    }
+}
+
+/// An image with its chain of pre-filtered levels, each half the size of
+/// the previous one down to a single pixel. An empty image has no other
+/// level.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MipMap {
+    levels: Vec<Canvas>,
+}
+
+impl MipMap {
+    pub fn new(image: Canvas) -> Self {
+        let mut levels = vec![image];
+        loop {
+            let last = &levels[levels.len() - 1];
+            let (w, h) = (last.width(), last.height());
+            if w.max(h) <= 1 || w.min(h) == 0 {
+                break;
+            }
+            let (nw, nh) = (w.div_ceil(2), h.div_ceil(2));
+            let mut next = Canvas::new(nw, nh);
+            for y in 0..nh {
+                for x in 0..nw {
+                    // 2x2 box filter, repeating the last row and column of
+                    // odd sizes
+                    let (x0, y0) = (2 * x, 2 * y);
+                    let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+                    let sum = last.pixel_at(x0, y0)
+                        + last.pixel_at(x1, y0)
+                        + last.pixel_at(x0, y1)
+                        + last.pixel_at(x1, y1);
+                    next.write_pixel(x, y, sum * 0.25);
+                }
+            }
+            levels.push(next);
+        }
+        Self { levels }
+    }
+
+    pub fn levels(&self) -> &[Canvas] {
+        &self.levels
+    }
+
+    /// Color at `(u, v)` seen over an area `footprint` wide, blending the two
+    /// levels whose pixels are closest to that size.
+    pub fn sample(&self, u: Float, v: Float, footprint: Float) -> Color {
+        let size = self.levels[0].width().max(self.levels[0].height()) as Float;
+        let lod = (footprint * size).log2();
+        let last = (self.levels.len() - 1) as Float;
+        if lod.is_nan() || lod <= 0.0 {
+            return image_at(&self.levels[0], u, v);
+        }
+        if lod >= last {
+            return image_at(&self.levels[self.levels.len() - 1], u, v);
+        }
+        let level = lod.floor() as usize;
+        let t = lod - lod.floor();
+        image_at(&self.levels[level], u, v) * (1.0 - t)
+            + image_at(&self.levels[level + 1], u, v) * t
+    }
+}
+
+/// Nearest pixel of `canvas` at `(u, v)`, `v` going up. Black for an empty
+/// canvas.
+fn image_at(canvas: &Canvas, u: Float, v: Float) -> Color {
+    let (w, h) = (canvas.width(), canvas.height());
+    if w == 0 || h == 0 {
+        return Color::black();
+    }
+    let x = (u.clamp(0.0, 1.0) * (w - 1) as Float).round() as usize;
+    let y = ((1.0 - v.clamp(0.0, 1.0)) * (h - 1) as Float).round() as usize;
+    canvas.pixel_at(x, y)
+}
+
+/// A 2D texture over the unit square.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UvPattern {
+    /// `width` by `height` squares alternating between `a` and `b`.
+    Checkers {
+        width: Float,
+        height: Float,
+        a: Color,
+        b: Color,
+    },
+    /// An image stretched over the square, `v` going up. Shared, since shapes
+    /// are cloned with their material.
+    Image(Arc<Canvas>),
+    /// An image looked up in the level of its mip chain that matches the
+    /// footprint of the ray, so it doesn't shimmer in the distance.
+    MipMapped(Arc<MipMap>),
+    /// `main` with a square of another color in each corner, to check how
+    /// a texture is oriented.
+    AlignCheck {
+        main: Color,
+        ul: Color,
+        ur: Color,
+        bl: Color,
+        br: Color,
+    },
+}
+
+impl UvPattern {
+    pub fn checkers(width: Float, height: Float, a: Color, b: Color) -> Self {
+        UvPattern::Checkers {
+            width,
+            height,
+            a,
+            b,
+        }
+    }
+
+    pub fn image(canvas: Canvas) -> Self {
+        UvPattern::Image(Arc::new(canvas))
+    }
+
+    pub fn mipmapped(canvas: Canvas) -> Self {
+        UvPattern::MipMapped(Arc::new(MipMap::new(canvas)))
+    }
+
+    pub fn align_check(main: Color, ul: Color, ur: Color, bl: Color, br: Color) -> Self {
+        UvPattern::AlignCheck {
+            main,
+            ul,
+            ur,
+            bl,
+            br,
+        }
+    }
+
+    pub fn uv_pattern_at(&self, u: Float, v: Float) -> Color {
+        self.filtered_at(u, v, 0.0)
+    }
+
+    /// Color at `(u, v)` seen over an area `footprint` wide in uv space.
+    /// Only mipmapped images are filtered.
+    pub fn filtered_at(&self, u: Float, v: Float, footprint: Float) -> Color {
+        match self {
+            UvPattern::Checkers {
+                width,
+                height,
+                a,
+                b,
+            } => {
+                let (u, v) = ((u * width).floor(), (v * height).floor());
+                if (u + v).rem_euclid(2.0) == 0.0 {
+                    *a
+                } else {
+                    *b
+                }
+            }
+            UvPattern::Image(canvas) => image_at(canvas, u, v),
+            UvPattern::MipMapped(mip) => mip.sample(u, v, footprint),
+            UvPattern::AlignCheck {
+                main,
+                ul,
+                ur,
+                bl,
+                br,
+            } => match (u < 0.2, u > 0.8, v < 0.2, v > 0.8) {
+                (true, _, _, true) => *ul,
+                (_, true, _, true) => *ur,
+                (true, _, true, _) => *bl,
+                (_, true, true, _) => *br,
+                _ => *main,
+            },
+        }
+    }
+
+    pub fn colors(&self) -> Vec<Color> {
+        match self {
+            UvPattern::Checkers { a, b, .. } => vec![*a, *b],
+            UvPattern::Image(_) | UvPattern::MipMapped(_) => vec![],
+            UvPattern::AlignCheck {
+                main,
+                ul,
+                ur,
+                bl,
+                br,
+            } => vec![*main, *ul, *ur, *bl, *br],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tuple::point, util::consts::SQRT_2};
+
+    use super::*;
+
+    const WHITE: Color = Color::white();
+    const BLACK: Color = Color::black();
+
+    #[test]
+    fn checker_pattern_in_2d() {
+        let checkers = UvPattern::checkers(2.0, 2.0, BLACK, WHITE);
+        for (u, v, expected) in [
+            (0.0, 0.0, BLACK),
+            (0.5, 0.0, WHITE),
+            (0.0, 0.5, WHITE),
+            (0.5, 0.5, BLACK),
+            (1.0, 1.0, BLACK),
+        ] {
+            assert_eq!(checkers.uv_pattern_at(u, v), expected);
+        }
+    }
+
+    #[test]
+    fn using_a_spherical_mapping_on_a_3d_point() {
+        for (p, uv) in [
+            (point(0.0, 0.0, -1.0), (0.0, 0.5)),
+            (point(1.0, 0.0, 0.0), (0.25, 0.5)),
+            (point(0.0, 0.0, 1.0), (0.5, 0.5)),
+            (point(-1.0, 0.0, 0.0), (0.75, 0.5)),
+            (point(0.0, 1.0, 0.0), (0.5, 1.0)),
+            (point(0.0, -1.0, 0.0), (0.5, 0.0)),
+            (point(SQRT_2 / 2.0, SQRT_2 / 2.0, 0.0), (0.25, 0.75)),
+        ] {
+            let (u, v) = UvMapping::Spherical.map(p);
+            assert!((u - uv.0).abs() < 1e-9 && (v - uv.1).abs() < 1e-9, "{p:?}");
+        }
+    }
+
+    #[test]
+    fn using_a_planar_mapping_on_a_3d_point() {
+        for (p, uv) in [
+            (point(0.25, 0.0, 0.5), (0.25, 0.5)),
+            (point(0.25, 0.0, -0.25), (0.25, 0.75)),
+            (point(0.25, 0.5, -0.25), (0.25, 0.75)),
+            (point(1.25, 0.0, 0.5), (0.25, 0.5)),
+            (point(0.25, 0.0, -1.75), (0.25, 0.25)),
+            (point(0.0, 0.0, 0.0), (0.0, 0.0)),
+        ] {
+            assert_eq!(UvMapping::Planar.map(p), uv);
+        }
+    }
+
+    #[test]
+    fn using_a_cylindrical_mapping_on_a_3d_point() {
+        for (p, uv) in [
+            (point(0.0, 0.0, -1.0), (0.0, 0.0)),
+            (point(0.0, 0.5, -1.0), (0.0, 0.5)),
+            (point(0.0, 1.0, -1.0), (0.0, 0.0)),
+            (point(SQRT_2 / 2.0, 0.5, -SQRT_2 / 2.0), (0.125, 0.5)),
+            (point(1.0, 0.5, 0.0), (0.25, 0.5)),
+            (point(-1.0, 1.25, 0.0), (0.75, 0.25)),
+        ] {
+            let (u, v) = UvMapping::Cylindrical.map(p);
+            assert!((u - uv.0).abs() < 1e-5 && (v - uv.1).abs() < 1e-9, "{p:?}");
+        }
+    }
+
+    #[test]
+    fn using_a_cubic_mapping_on_each_face() {
+        for (p, uv) in [
+            // front
+            (point(-0.5, 0.5, 1.0), (0.25, 0.75)),
+            // back
+            (point(0.5, -0.5, -1.0), (0.25, 0.25)),
+            // left
+            (point(-1.0, 0.5, -0.5), (0.25, 0.75)),
+            // right
+            (point(1.0, -0.5, 0.5), (0.25, 0.25)),
+            // up
+            (point(-0.5, 1.0, -0.5), (0.25, 0.75)),
+            // down
+            (point(0.5, -1.0, 0.5), (0.75, 0.75)),
+        ] {
+            assert_eq!(UvMapping::Cubic.map(p), uv, "{p:?}");
+        }
+    }
+
+    #[test]
+    fn image_textures_put_v_up() {
+        let mut canvas = Canvas::new(2, 2);
+        canvas.write_pixel(0, 1, WHITE);
+        let image = UvPattern::image(canvas);
+        assert_eq!(image.uv_pattern_at(0.0, 0.0), WHITE);
+        assert_eq!(image.uv_pattern_at(0.0, 1.0), BLACK);
+    }
+
+    #[test]
+    fn mip_chains_halve_down_to_one_pixel() {
+        let mut canvas = Canvas::new(4, 3);
+        canvas.fill(WHITE);
+        for x in 0..4 {
+            canvas.write_pixel(x, 0, BLACK);
+        }
+        let mip = MipMap::new(canvas);
+        let sizes = mip
+            .levels()
+            .iter()
+            .map(|l| (l.width(), l.height()))
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, vec![(4, 3), (2, 2), (1, 1)]);
+        assert_eq!(mip.levels()[1].pixel_at(0, 0), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(mip.levels()[1].pixel_at(0, 1), WHITE);
+    }
+
+    #[test]
+    fn empty_images_are_black() {
+        for canvas in [Canvas::new(0, 5), Canvas::new(4, 0), Canvas::new(0, 0)] {
+            assert_eq!(MipMap::new(canvas.clone()).levels().len(), 1);
+            assert_eq!(
+                UvPattern::image(canvas.clone()).uv_pattern_at(0.5, 0.5),
+                BLACK
+            );
+            assert_eq!(
+                UvPattern::mipmapped(canvas).filtered_at(0.5, 0.5, 0.1),
+                BLACK
+            );
+        }
+    }
+
+    #[test]
+    fn mipmapped_images_average_with_the_footprint() {
+        let mut canvas = Canvas::new(4, 4);
+        for (x, y) in [
+            (0, 0),
+            (2, 0),
+            (1, 1),
+            (3, 1),
+            (0, 2),
+            (2, 2),
+            (1, 3),
+            (3, 3),
+        ] {
+            canvas.write_pixel(x, y, WHITE);
+        }
+        let image = UvPattern::mipmapped(canvas);
+        let grey = Color::new(0.5, 0.5, 0.5);
+        assert_eq!(image.filtered_at(0.0, 1.0, 0.0), WHITE);
+        assert_eq!(image.uv_pattern_at(0.0, 1.0), WHITE);
+        assert_eq!(image.filtered_at(0.0, 1.0, 1.0), grey);
+        assert_eq!(image.filtered_at(0.0, 1.0, 0.5), grey);
+        // halfway to the first level
+        let between = image.filtered_at(0.0, 1.0, Float::sqrt(2.0) / 4.0);
+        assert_eq!(between, Color::new(0.75, 0.75, 0.75));
+    }
+
+    #[test]
+    fn layout_of_the_align_check_pattern() {
+        let [main, ul, ur, bl, br] = [
+            WHITE,
+            Color::new(1.0, 0.0, 0.0),
+            Color::new(1.0, 1.0, 0.0),
+            Color::new(0.0, 1.0, 0.0),
+            Color::new(0.0, 1.0, 1.0),
+        ];
+        let pattern = UvPattern::align_check(main, ul, ur, bl, br);
+        for (u, v, expected) in [
+            (0.5, 0.5, main),
+            (0.1, 0.9, ul),
+            (0.9, 0.9, ur),
+            (0.1, 0.1, bl),
+            (0.9, 0.1, br),
+        ] {
+            assert_eq!(pattern.uv_pattern_at(u, v), expected);
+        }
+    }
+
+    #[test]
+    fn identifying_the_face_of_a_cube_from_a_point() {
+        for (p, face) in [
+            (point(-1.0, 0.5, -0.25), CubeFace::Left),
+            (point(1.1, -0.75, 0.8), CubeFace::Right),
+            (point(0.1, 0.6, 0.9), CubeFace::Front),
+            (point(-0.7, 0.0, -2.0), CubeFace::Back),
+            (point(0.5, 1.0, 0.9), CubeFace::Up),
+            (point(-0.2, -1.3, 1.1), CubeFace::Down),
+        ] {
+            assert_eq!(CubeFace::of(p), face);
+        }
+    }
+}
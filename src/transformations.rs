@@ -1,6 +1,6 @@
-use crate::{matrix::Mat4, tuple::Tuple};
+use crate::{matrix::Mat4, quaternion::Quaternion, tuple::Tuple, util::Float};
 
-pub fn translation(x: f64, y: f64, z: f64) -> Mat4 {
+pub fn translation(x: Float, y: Float, z: Float) -> Mat4 {
     let mut m = Mat4::identity();
     m[(0, 3)] = x;
     m[(1, 3)] = y;
@@ -8,7 +8,7 @@ pub fn translation(x: f64, y: f64, z: f64) -> Mat4 {
     m
 }
 
-pub fn scaling(x: f64, y: f64, z: f64) -> Mat4 {
+pub fn scaling(x: Float, y: Float, z: Float) -> Mat4 {
     let mut m = Mat4::identity();
     m[(0, 0)] = x;
     m[(1, 1)] = y;
@@ -16,7 +16,7 @@ pub fn scaling(x: f64, y: f64, z: f64) -> Mat4 {
     m
 }
 
-pub fn rot_x(angle: f64) -> Mat4 {
+pub fn rot_x(angle: Float) -> Mat4 {
     let mut m = Mat4::identity();
     m[(1, 1)] = angle.cos();
     m[(1, 2)] = -angle.sin();
@@ -25,7 +25,7 @@ pub fn rot_x(angle: f64) -> Mat4 {
     m
 }
 
-pub fn rot_y(angle: f64) -> Mat4 {
+pub fn rot_y(angle: Float) -> Mat4 {
     let mut m = Mat4::identity();
     m[(0, 0)] = angle.cos();
     m[(0, 2)] = angle.sin();
@@ -33,7 +33,7 @@ pub fn rot_y(angle: f64) -> Mat4 {
     m[(2, 2)] = angle.cos();
     m
 }
-pub fn rot_z(angle: f64) -> Mat4 {
+pub fn rot_z(angle: Float) -> Mat4 {
     let mut m = Mat4::identity();
     m[(0, 0)] = angle.cos();
     m[(0, 1)] = -angle.sin();
@@ -42,7 +42,31 @@ pub fn rot_z(angle: f64) -> Mat4 {
     m
 }
 
-pub fn shearing(xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Mat4 {
+/// Rotation by `angle` radians around `axis`, a vector through the origin.
+pub fn rotation_axis_angle(axis: Tuple, angle: Float) -> Mat4 {
+    Quaternion::from_axis_angle(axis, angle).to_matrix()
+}
+
+/// Shorthand for [`rotation_axis_angle`].
+pub fn rotation(axis: Tuple, angle: Float) -> Mat4 {
+    rotation_axis_angle(axis, angle)
+}
+
+/// Mirror image through the plane with normal `plane_normal` going through
+/// the origin.
+pub fn reflect_across(plane_normal: Tuple) -> Mat4 {
+    let n = plane_normal.norm();
+    let n = [n.x, n.y, n.z];
+    let mut m = Mat4::identity();
+    for r in 0..3 {
+        for c in 0..3 {
+            m[(r, c)] -= 2.0 * n[r] * n[c];
+        }
+    }
+    m
+}
+
+pub fn shearing(xy: Float, xz: Float, yx: Float, yz: Float, zx: Float, zy: Float) -> Mat4 {
     let mut m = Mat4::identity();
     m[(0, 1)] = xy;
     m[(0, 2)] = xz;
@@ -77,9 +101,11 @@ pub fn view_transform(from: Tuple, to: Tuple, up: Tuple) -> Mat4 {
 
 #[cfg(test)]
 mod tests {
-    use std::f64::consts::{PI, SQRT_2};
-
-    use crate::{matrix::MatBase, tuple::*};
+    use crate::{
+        matrix::MatBase,
+        tuple::*,
+        util::consts::{PI, SQRT_2},
+    };
 
     use super::*;
 
@@ -148,6 +174,26 @@ mod tests {
         assert_eq!(full_quarter * p, point(-1.0, 0.0, 0.0));
     }
 
+    #[test]
+    fn rotating_a_point_around_an_arbitrary_axis() {
+        let r = rotation_axis_angle(vector(1.0, 1.0, 1.0), 2.0 * PI / 3.0);
+        assert_eq!(r * point(1.0, 0.0, 0.0), point(0.0, 1.0, 0.0));
+        assert_eq!(r * point(0.0, 0.0, 1.0), point(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn reflecting_across_a_plane() {
+        assert_eq!(
+            reflect_across(vector(2.0, 0.0, 0.0)),
+            scaling(-1.0, 1.0, 1.0)
+        );
+        let t = reflect_across(vector(1.0, 1.0, 0.0));
+        assert_eq!(t * point(1.0, 0.0, 0.0), point(0.0, -1.0, 0.0));
+        assert_eq!(t * point(1.0, -1.0, 5.0), point(1.0, -1.0, 5.0));
+        assert_eq!(t * t, Mat4::identity());
+        assert_eq!(rotation(vector(0.0, 0.0, 1.0), PI / 3.0), rot_z(PI / 3.0));
+    }
+
     #[test]
     fn a_shearing_transformation_moves_x_in_proportion_to_y() {
         let transform = shearing(1.0, 0.0, 0.0, 0.0, 0.0, 0.0);
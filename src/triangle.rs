@@ -0,0 +1,157 @@
+use crate::{
+    bounds::Bounds,
+    object::{LocalHits, LocalIntersect},
+    ray::Ray,
+    tuple::{point, Tuple},
+    util::{Float, EPSILON},
+};
+
+/// Flat triangle, the building block of tessellated surfaces.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Triangle {
+    pub p1: Tuple,
+    pub p2: Tuple,
+    pub p3: Tuple,
+    pub e1: Tuple,
+    pub e2: Tuple,
+    pub normal: Tuple,
+}
+
+impl Triangle {
+    pub fn new(p1: Tuple, p2: Tuple, p3: Tuple) -> Self {
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+        Self {
+            p1,
+            p2,
+            p3,
+            e1,
+            e2,
+            normal: e2.cross(e1).norm(),
+        }
+    }
+
+    /// Distance from `p`, a point of the triangle, to its nearest edge.
+    pub fn edge_distance(&self, p: Tuple) -> Float {
+        [(self.p1, self.p2), (self.p2, self.p3), (self.p3, self.p1)]
+            .into_iter()
+            .map(|(a, b)| (p - a).cross(b - a).mag() / (b - a).mag())
+            .fold(Float::INFINITY, Float::min)
+    }
+}
+
+impl LocalIntersect for Triangle {
+    fn local_intersect(&self, r: Ray) -> LocalHits {
+        let dir_cross_e2 = r.direction.cross(self.e2);
+        let det = self.e1 ^ dir_cross_e2;
+        if det.abs() < EPSILON {
+            return LocalHits::new();
+        }
+        let f = 1.0 / det;
+        let p1_to_origin = r.origin - self.p1;
+        let u = f * (p1_to_origin ^ dir_cross_e2);
+        if !(0.0..=1.0).contains(&u) {
+            return LocalHits::new();
+        }
+        let origin_cross_e1 = p1_to_origin.cross(self.e1);
+        let v = f * (r.direction ^ origin_cross_e1);
+        if v < 0.0 || u + v > 1.0 {
+            return LocalHits::new();
+        }
+        LocalHits::from([f * (self.e2 ^ origin_cross_e1)])
+    }
+
+    fn local_normal_at(&self, _: &Tuple) -> Tuple {
+        self.normal
+    }
+
+    fn local_bounds(&self) -> Bounds {
+        let (a, b, c) = (self.p1, self.p2, self.p3);
+        Bounds::new(
+            point(
+                a.x.min(b.x).min(c.x),
+                a.y.min(b.y).min(c.y),
+                a.z.min(b.z).min(c.z),
+            ),
+            point(
+                a.x.max(b.x).max(c.x),
+                a.y.max(b.y).max(c.y),
+                a.z.max(b.z).max(c.z),
+            ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tuple::vector, util::flt_eq};
+
+    use super::*;
+
+    fn triangle() -> Triangle {
+        Triangle::new(
+            point(0.0, 1.0, 0.0),
+            point(-1.0, 0.0, 0.0),
+            point(1.0, 0.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn constructing_a_triangle() {
+        let t = triangle();
+        assert_eq!(t.e1, vector(-1.0, -1.0, 0.0));
+        assert_eq!(t.e2, vector(1.0, -1.0, 0.0));
+        assert_eq!(t.normal, vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn normal_is_the_same_everywhere() {
+        let t = triangle();
+        assert_eq!(t.local_normal_at(&point(0.0, 0.5, 0.0)), t.normal);
+        assert_eq!(t.local_normal_at(&point(-0.5, 0.75, 0.0)), t.normal);
+    }
+
+    #[test]
+    fn ray_parallel_to_triangle() {
+        let r = Ray::new(point(0.0, -1.0, -2.0), vector(0.0, 1.0, 0.0));
+        assert!(triangle().local_intersect(r).as_slice().is_empty());
+    }
+
+    #[test]
+    fn ray_misses_the_edges() {
+        for origin in [
+            point(1.0, 1.0, -2.0),
+            point(-1.0, 1.0, -2.0),
+            point(0.0, -1.0, -2.0),
+        ] {
+            let r = Ray::new(origin, vector(0.0, 0.0, 1.0));
+            assert!(triangle().local_intersect(r).as_slice().is_empty());
+        }
+    }
+
+    #[test]
+    fn ray_strikes_a_triangle() {
+        let r = Ray::new(point(0.0, 0.5, -2.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(triangle().local_intersect(r).as_slice(), &[2.0]);
+    }
+
+    #[test]
+    fn distance_to_the_nearest_edge() {
+        let t = triangle();
+        assert_eq!(t.edge_distance(point(0.0, 0.25, 0.0)), 0.25);
+        assert_eq!(t.edge_distance(point(0.0, 1.0, 0.0)), 0.0);
+        assert!(flt_eq(
+            t.edge_distance(point(0.5, 0.25, 0.0)),
+            0.25 / Float::sqrt(2.0)
+        ));
+    }
+
+    #[test]
+    fn bounds_of_a_triangle() {
+        assert_eq!(
+            triangle().local_bounds(),
+            Bounds::new(point(-1.0, 0.0, 0.0), point(1.0, 1.0, 0.0))
+        );
+    }
+}
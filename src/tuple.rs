@@ -1,29 +1,43 @@
-use std::ops::{Add, AddAssign, BitXor, Div, Mul, Neg, Sub};
+use std::ops::{
+    Add, AddAssign, BitXor, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign,
+};
 
-use crate::util::flt_eq;
+use crate::util::{flt_eq, Float};
 
 #[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tuple {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
-    pub w: f64,
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+    pub w: Float,
 }
 
-impl From<[f64; 4]> for Tuple {
-    fn from(value: [f64; 4]) -> Self {
+impl From<[Float; 4]> for Tuple {
+    fn from(value: [Float; 4]) -> Self {
         Tuple::new(value[0], value[1], value[2], value[3])
     }
 }
 
+impl From<Tuple> for [Float; 4] {
+    fn from(t: Tuple) -> Self {
+        t.to_array()
+    }
+}
+
 impl Tuple {
-    pub const fn new(x: f64, y: f64, z: f64, w: f64) -> Self {
+    pub const fn new(x: Float, y: Float, z: Float, w: Float) -> Self {
         Self { x, y, z, w }
     }
 
-    pub fn mag(&self) -> f64 {
+    /// `[x, y, z, w]`.
+    pub const fn to_array(&self) -> [Float; 4] {
+        [self.x, self.y, self.z, self.w]
+    }
+
+    pub fn mag(&self) -> Float {
         assert!(self.w == 0.0);
-        f64::sqrt(self.x * self.x + self.y * self.y + self.z * self.z)
+        Float::sqrt(self.x * self.x + self.y * self.y + self.z * self.z)
     }
 
     pub fn norm(&self) -> Self {
@@ -31,7 +45,7 @@ impl Tuple {
         *self / self.mag()
     }
 
-    pub fn dot(&self, rhs: Self) -> f64 {
+    pub fn dot(&self, rhs: Self) -> Float {
         assert!(self.w == 0.0, "{:?}", self);
         self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
     }
@@ -93,28 +107,28 @@ impl Sub for Tuple {
     }
 }
 
-impl Mul<f64> for Tuple {
+impl Mul<Float> for Tuple {
     type Output = Self;
-    fn mul(self, rhs: f64) -> Self::Output {
+    fn mul(self, rhs: Float) -> Self::Output {
         Tuple::new(self.x * rhs, self.y * rhs, self.z * rhs, self.w * rhs)
     }
 }
 impl Mul<Tuple> for Tuple {
-    type Output = f64;
+    type Output = Float;
 
     fn mul(self, rhs: Tuple) -> Self::Output {
         self.x * rhs.x + self.y * rhs.y + self.z * rhs.z + self.w * rhs.w
     }
 }
-impl Div<f64> for Tuple {
+impl Div<Float> for Tuple {
     type Output = Self;
-    fn div(self, rhs: f64) -> Self::Output {
+    fn div(self, rhs: Float) -> Self::Output {
         Tuple::new(self.x / rhs, self.y / rhs, self.z / rhs, self.w / rhs)
     }
 }
 
 impl BitXor<Tuple> for Tuple {
-    type Output = f64;
+    type Output = Float;
 
     fn bitxor(self, rhs: Tuple) -> Self::Output {
         self.dot(rhs)
@@ -127,19 +141,97 @@ impl AddAssign for Tuple {
     }
 }
 
-pub fn point(x: f64, y: f64, z: f64) -> Tuple {
+impl SubAssign for Tuple {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs
+    }
+}
+
+impl MulAssign<Float> for Tuple {
+    fn mul_assign(&mut self, rhs: Float) {
+        *self = *self * rhs
+    }
+}
+
+impl DivAssign<Float> for Tuple {
+    fn div_assign(&mut self, rhs: Float) {
+        *self = *self / rhs
+    }
+}
+
+/// Components in the order `x`, `y`, `z`, `w`.
+impl Index<usize> for Tuple {
+    type Output = Float;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        match index {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            3 => &self.w,
+            _ => panic!("tuple index out of bounds: {index}"),
+        }
+    }
+}
+
+impl IndexMut<usize> for Tuple {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        match index {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            3 => &mut self.w,
+            _ => panic!("tuple index out of bounds: {index}"),
+        }
+    }
+}
+
+impl IntoIterator for Tuple {
+    type Item = Float;
+    type IntoIter = std::array::IntoIter<Float, 4>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.to_array().into_iter()
+    }
+}
+
+pub fn point(x: Float, y: Float, z: Float) -> Tuple {
     Tuple::new(x, y, z, 1.0)
 }
 
-pub const fn vector(x: f64, y: f64, z: f64) -> Tuple {
+pub const fn vector(x: Float, y: Float, z: Float) -> Tuple {
     Tuple::new(x, y, z, 0.0)
 }
 
 #[cfg(test)]
 mod tests {
-    use std::f64::consts::SQRT_2;
+    use crate::util::consts::SQRT_2;
 
     use super::*;
+    #[test]
+    fn components_by_index() {
+        let mut t = point(1.0, 2.0, 3.0);
+        assert_eq!(t[2], 3.0);
+        t[0] = 4.0;
+        assert_eq!(t.to_array(), [4.0, 2.0, 3.0, 1.0]);
+        assert_eq!(t.into_iter().sum::<Float>(), 10.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn indexing_past_w_panics() {
+        let _ = vector(1.0, 2.0, 3.0)[4];
+    }
+
+    #[test]
+    fn assign_operators() {
+        let mut t = vector(2.0, 4.0, 6.0);
+        t *= 2.0;
+        t /= 4.0;
+        t -= vector(1.0, 1.0, 1.0);
+        assert_eq!(t, vector(0.0, 1.0, 2.0));
+    }
+
     #[test]
     fn reflect_vector_45() {
         let v = vector(1.0, -1.0, 0.0);
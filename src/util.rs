@@ -1,7 +1,21 @@
-pub const EPSILON: f64 = 0.00001;
+/// Floating point type of coordinates, matrices and colors, `f32` with the
+/// `f32` feature to halve the memory they take.
+#[cfg(not(feature = "f32"))]
+pub type Float = f64;
+#[cfg(feature = "f32")]
+pub type Float = f32;
 
-pub fn flt_eq(a: f64, b: f64) -> bool {
-    f64::abs(a - b) < EPSILON
+/// Constants of [`Float`].
+#[cfg(not(feature = "f32"))]
+pub use std::f64::consts;
+
+#[cfg(feature = "f32")]
+pub use std::f32::consts;
+
+pub const EPSILON: Float = 0.00001;
+
+pub fn flt_eq(a: Float, b: Float) -> bool {
+    Float::abs(a - b) < EPSILON
 }
 
 pub const MAX_REFLECTIONS: usize = 10;
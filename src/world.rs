@@ -1,18 +1,180 @@
-use std::vec;
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fmt::{self, Display},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, OnceLock,
+    },
+    vec,
+};
 
 use crate::{
+    bounds::Bounds,
+    bvh::Bvh,
+    checkpoint,
+    clip::ClipPlane,
     color::Color,
-    intersection::{self, Computations, Intersectable, Intersections},
+    intersection::{
+        self, hit_sorted, sort_by_time, Bias, Computations, Intersectable, Intersection,
+        Intersections,
+    },
     lights::Light,
+    material::{BackFace, Material, Surface},
+    matrix::Mat4,
+    medium::{segment, MEDIUM_STEPS},
     object::Shape,
-    ray::Ray,
-    transformations::scaling,
-    tuple::{point, Tuple},
+    pattern::Pattern,
+    ray::{Ray, RayKind},
+    sampler::Rng,
+    sampling::{cosine_hemisphere, phong_lobe},
+    scenes, stats,
+    transformations::{scaling, translation},
+    tuple::{point, vector, Tuple},
+    util::{consts::PI, Float, MAX_REFLECTIONS},
 };
 
+/// Handle to an object of a [`World`], which stays valid as other objects
+/// are added and removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObjectId(u64);
+
+/// Source of the generations of the worlds, unique across worlds so that a
+/// BVH built for one world is refused by the others.
+static GENERATIONS: AtomicU64 = AtomicU64::new(0);
+
+fn next_generation() -> u64 {
+    GENERATIONS.fetch_add(1, Ordering::Relaxed)
+}
+
+/// What a ray hits first, see [`World::cast_ray`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HitInfo {
+    pub point: Tuple,
+    /// Unit normal at `point`, facing the ray.
+    pub normal: Tuple,
+    /// From the origin of the ray to `point`.
+    pub distance: Float,
+    pub object: ObjectId,
+    pub material: Material,
+}
+
+/// What a world holds, see [`World::describe`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    /// Number of objects of each kind, by name.
+    pub objects: BTreeMap<&'static str, usize>,
+    /// The different materials, in the order they are first used.
+    pub materials: Vec<Material>,
+    pub lights: Vec<Light>,
+    pub bounds: Bounds,
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = |n: usize, name: &str| match n {
+            1 => format!("1 {name}"),
+            n => format!("{n} {name}s"),
+        };
+        let triple = |x: Float, y: Float, z: Float| format!("({x}, {y}, {z})");
+        let kinds: Vec<_> = self.objects.iter().map(|(k, n)| plural(*n, k)).collect();
+        write!(f, "{}", plural(self.objects.values().sum(), "object"))?;
+        if !kinds.is_empty() {
+            write!(f, ": {}", kinds.join(", "))?;
+        }
+        writeln!(f)?;
+        writeln!(f, "{}", plural(self.materials.len(), "material"))?;
+        writeln!(f, "{}", plural(self.lights.len(), "light"))?;
+        for light in &self.lights {
+            let (p, i) = (light.position, light.intensity);
+            writeln!(
+                f,
+                "  at {}, intensity {}",
+                triple(p.x, p.y, p.z),
+                triple(i.r(), i.g(), i.b())
+            )?;
+        }
+        let (min, max) = (self.bounds.min, self.bounds.max);
+        if self.objects.is_empty() {
+            return write!(f, "no bounds");
+        }
+        write!(
+            f,
+            "bounds {} to {}",
+            triple(min.x, min.y, min.z),
+            triple(max.x, max.y, max.z)
+        )
+    }
+}
+
+/// A ray traced by [`World::color_at`] and its bounces, as reported to the
+/// hook set with [`World::set_ray_hook`].
+#[derive(Debug, Clone)]
+pub struct RaySegment {
+    pub ray: Ray,
+    /// Bounces left when the ray was cast.
+    pub depth: usize,
+    pub kind: RayKind,
+    /// First surface hit, none when the ray missed everything.
+    pub hit: Option<Intersection>,
+    /// Color the ray brought back.
+    pub color: Color,
+}
+
+type RayHook = Arc<dyn Fn(&RaySegment) + Send + Sync>;
+
+/// Saved without its BVH, call [`World::build_bvh`] again once loaded. A
+/// world is `Send + Sync`: render threads share it by reference, and its
+/// clones share the BVH.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct World {
     lights: Vec<Light>,
-    pub objects: Vec<Shape>,
+    /// Only changed through [`World::add`], [`World::remove`] and
+    /// [`World::get_mut`], which keep `ids`, `bvh`, `media` and `generation`
+    /// up to date. Shared with the intersections found, so that a hit does not
+    /// copy its shape.
+    objects: Vec<Arc<Shape>>,
+    /// Ids of the first objects, the others, added since or loaded without
+    /// ids, have the ids following `next_id` in order.
+    #[cfg_attr(feature = "serde", serde(default))]
+    ids: Vec<ObjectId>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    next_id: u64,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    bvh: Option<Arc<Bvh>>,
+    /// Indices of the objects filled with a medium, found when first needed.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    media: OnceLock<Vec<usize>>,
+    /// Changes whenever the objects do, the hierarchies built before are
+    /// refused then.
+    #[cfg_attr(feature = "serde", serde(skip, default = "next_generation"))]
+    generation: u64,
+    /// How many times reflected and refracted rays may bounce.
+    max_depth: usize,
+    clip_planes: Vec<ClipPlane>,
+    /// Seen by the rays that miss every object, looked up by direction.
+    background: Option<Pattern>,
+    /// Tints the ambient term of every material, white by default.
+    #[cfg_attr(feature = "serde", serde(default = "Color::white"))]
+    ambient: Color,
+    #[cfg_attr(feature = "serde", serde(default))]
+    bias: Bias,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    ray_hook: Option<RayHook>,
+}
+
+impl Display for World {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe().fmt(f)
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl World {
@@ -20,6 +182,17 @@ impl World {
         Self {
             lights: vec![],
             objects: vec![],
+            ids: vec![],
+            next_id: 0,
+            bvh: None,
+            media: OnceLock::new(),
+            generation: next_generation(),
+            max_depth: MAX_REFLECTIONS,
+            clip_planes: vec![],
+            background: None,
+            ambient: Color::white(),
+            bias: Bias::default(),
+            ray_hook: None,
         }
     }
     pub fn ch7_default() -> Self {
@@ -35,106 +208,960 @@ impl World {
         s2.set_transform(scaling(0.5, 0.5, 0.5));
         Self {
             lights: vec![light],
-            objects: vec![s1, s2],
+            objects: vec![Arc::new(s1), Arc::new(s2)],
+            ids: vec![],
+            next_id: 0,
+            bvh: None,
+            media: OnceLock::new(),
+            generation: next_generation(),
+            max_depth: MAX_REFLECTIONS,
+            clip_planes: vec![],
+            background: None,
+            ambient: Color::white(),
+            bias: Bias::default(),
+            ray_hook: None,
         }
     }
 
-    pub fn shade_hit(&self, comps: Computations, depth: usize) -> Color {
-        let surface = comps.i.object.material.lighting(
-            self.lights[0],
-            comps.i.object,
-            comps.over_point,
-            comps.eye_v,
-            comps.normal_v,
-            self.is_shadowed(comps.over_point),
-        );
-        let reflected = self.reflect_color(comps, depth);
-        let refracted = self.refracted_color(comps, depth);
-        let material = comps.i.object.material;
-        if material.reflective > 0.0 && material.transparency > 0.0 {
+    /// The world of chapter 7 standing on a half reflective, half
+    /// transparent floor at `y = -1`, with a red ball below it, as in the
+    /// reflection and refraction tests of chapter 11.
+    pub fn ch11_default() -> Self {
+        let floor = Shape::plane()
+            .with_material(
+                Material::default()
+                    .transparency(0.5)
+                    .reflective(0.5)
+                    .refractive_index(1.5),
+            )
+            .with_transform(translation(0.0, -1.0, 0.0));
+        let ball = Shape::sphere()
+            .with_material(
+                Material::default()
+                    .ambient(0.5)
+                    .color(Color::new(1.0, 0.0, 0.0)),
+            )
+            .with_transform(translation(0.0, -3.5, -0.5));
+        let mut world = Self::ch7_default();
+        world.add(floor);
+        world.add(ball);
+        world
+    }
+
+    /// The closed box of [`scenes::cornell_box`], without its camera.
+    pub fn cornell_box() -> Self {
+        scenes::cornell_box_world()
+    }
+
+    pub fn shade_hit(&self, comps: &Computations, depth: usize) -> Color {
+        let material = comps.material();
+        let surface = material.surface_at(&comps.i.object, comps.over_point, comps.footprint);
+        let mut lit = Color::black();
+        for &light in &self.lights {
+            // light coming through a translucent surface is blocked by what
+            // lies behind it, not by the surface itself
+            let behind = ((light.position - comps.over_point) ^ comps.normal_v) < 0.0;
+            let shadow_from = if behind && material.translucency > 0.0 {
+                comps.under_point
+            } else {
+                comps.over_point
+            };
+            // the ambient term, tinted by the ambient light of the world
+            let ambient = surface.color * light.intensity * surface.ambient * self.ambient;
+            lit = lit
+                + ambient
+                + material.shade(
+                    light,
+                    &Surface {
+                        ambient: 0.0,
+                        ..surface
+                    },
+                    comps.over_point,
+                    comps.eye_v,
+                    comps.normal_v,
+                    self.light_visibility(light.position, shadow_from),
+                );
+        }
+        let reflected = self.reflected(comps, depth, surface.reflective);
+        let refracted = self.refracted(comps, depth, surface.transparency);
+        if surface.reflective > 0.0 && surface.transparency > 0.0 {
             let reflectance = comps.schlick();
-            return surface + reflected * reflectance + refracted * (1.0 - reflectance);
+            return lit + reflected * reflectance + refracted * (1.0 - reflectance);
         }
-        surface + reflected + refracted
+        lit + reflected + refracted
     }
 
-    pub fn reflect_color(&self, comps: Computations, depth: usize) -> Color {
+    pub fn reflect_color(&self, comps: &Computations, depth: usize) -> Color {
+        let shape = &comps.i.object;
+        let surface = comps
+            .material()
+            .surface_at(shape, comps.over_point, comps.footprint);
+        self.reflected(comps, depth, surface.reflective)
+    }
+
+    fn reflected(&self, comps: &Computations, depth: usize, reflective: Float) -> Color {
         if depth == 0 {
             return Color::black();
         }
-        if comps.i.object.material.reflective == 0.0 {
+        if reflective == 0.0 {
             Color::black()
         } else {
             let reflect_ray = Ray::new(comps.over_point, comps.reflect_v);
-            let color = self.color_at(reflect_ray, depth - 1);
-            color * comps.i.object.material.reflective
+            // reflected back inside the object
+            let absorption = if comps.inside {
+                comps.i.object.material.absorption
+            } else {
+                Color::black()
+            };
+            let color =
+                self.trace_absorbed(reflect_ray, depth - 1, RayKind::Reflection, absorption);
+            color * reflective
         }
     }
 
+    /// Builds the hierarchy used by [`World::intersects`]. Adding, removing
+    /// or editing objects drops it, it has to be built again after.
+    pub fn build_bvh(&mut self) {
+        let all = (0..self.objects.len()).collect::<Vec<_>>();
+        self.bvh = Some(Arc::new(self.bvh_over(&all)));
+    }
+
+    /// Hierarchy over the objects at `indices`, for [`World::intersects_bvh`]
+    /// and the like. It is refused once objects are added, removed or
+    /// edited.
+    pub fn bvh_over(&self, indices: &[usize]) -> Bvh {
+        Bvh::build(&self.objects, indices).stamped(self.generation)
+    }
+
+    /// Intersections of `r` with every object, sorted. The list is allocated
+    /// for each call, [`World::intersects_into`] reuses a buffer instead.
     pub fn intersects(&self, r: crate::ray::Ray) -> intersection::Intersections {
-        let mut i = self
+        let mut xs = vec![];
+        self.intersects_into(r, &mut xs);
+        Intersections::new(xs)
+    }
+
+    /// Appends the intersections of `r` to `xs` and sorts them, so callers can
+    /// reuse the same buffer for every ray. The only way to intersect the
+    /// world without allocating.
+    pub fn intersects_into(&self, r: Ray, xs: &mut Vec<Intersection>) {
+        match &self.bvh {
+            Some(bvh) => self.gather(r, bvh, xs),
+            None => (0..self.objects.len()).for_each(|i| self.intersect_object(i, r, xs)),
+        }
+        sort_by_time(xs);
+    }
+
+    /// Same as [`World::intersects`], restricted to the objects at `indices`.
+    /// Allocates the list like [`World::intersects`].
+    pub fn intersects_only(&self, r: Ray, indices: &[usize]) -> Intersections {
+        let mut xs = vec![];
+        for &idx in indices {
+            self.intersect_object(idx, r, &mut xs);
+        }
+        Intersections::new(xs)
+    }
+
+    /// Same as [`World::intersects`], testing only the objects `bvh` lets
+    /// through. `bvh` must have been built over `self.objects`. Allocates the
+    /// list like [`World::intersects`].
+    pub fn intersects_bvh(&self, r: Ray, bvh: &Bvh) -> Intersections {
+        let mut xs = vec![];
+        self.gather(r, bvh, &mut xs);
+        Intersections::new(xs)
+    }
+
+    fn gather(&self, r: Ray, bvh: &Bvh, xs: &mut Vec<Intersection>) {
+        self.check_bvh(bvh);
+        bvh.for_each_candidate(r, |i| self.intersect_object(i, r, xs));
+    }
+
+    /// Panics if `bvh` was not built by [`World::bvh_over`] since the last
+    /// time objects were added, removed or edited, rather than missing the
+    /// new ones or indexing past the end.
+    fn check_bvh(&self, bvh: &Bvh) {
+        assert!(
+            bvh.generation() == Some(self.generation),
+            "the BVH was built over other objects, build it again"
+        );
+    }
+
+    /// Pushes the intersections of `r` with the object at `index` at the end
+    /// of `xs`.
+    fn intersect_object(&self, index: usize, r: Ray, xs: &mut Vec<Intersection>) {
+        let o = &self.objects[index];
+        xs.extend(
+            o.hit_times(r)
+                .as_slice()
+                .iter()
+                .map(|&t| Intersection::with_index(t, Arc::clone(o), index)),
+        );
+    }
+
+    /// Adds `shape` and returns its handle. The BVH is dropped, build it
+    /// again once the edits are done.
+    pub fn add(&mut self, shape: Shape) -> ObjectId {
+        self.objects.push(Arc::new(shape));
+        self.objects_changed();
+        self.id_at(self.objects.len() - 1)
+    }
+
+    /// Removes the object `id` stands for, if it is still there. The other
+    /// handles stay valid. The BVH is dropped, build it again once the
+    /// edits are done.
+    pub fn remove(&mut self, id: ObjectId) -> Option<Shape> {
+        let index = self.index_of(id)?;
+        self.assign_ids();
+        self.ids.remove(index);
+        self.objects_changed();
+        Some(Arc::unwrap_or_clone(self.objects.remove(index)))
+    }
+
+    /// Adds the objects and lights of `other`, moved by `offset`, and
+    /// returns the handles of the objects. The settings of `other`, such as
+    /// its background, are left behind. The BVH is dropped.
+    pub fn merge(&mut self, other: World, offset: Mat4) -> Vec<ObjectId> {
+        for light in other.lights {
+            self.lights
+                .push(Light::new(offset * light.position, light.intensity));
+        }
+        other
             .objects
+            .into_iter()
+            .map(|o| {
+                let transform = offset * o.transform;
+                self.add(Arc::unwrap_or_clone(o).with_transform(transform))
+            })
+            .collect()
+    }
+
+    /// Removes every object, keeping the lights and settings. Handles given
+    /// out before stay invalid.
+    pub fn clear_objects(&mut self) {
+        self.assign_ids();
+        self.ids.clear();
+        self.objects.clear();
+        self.objects_changed();
+    }
+
+    /// The objects, in the order they were added.
+    pub fn objects(&self) -> &[Arc<Shape>] {
+        &self.objects
+    }
+
+    pub fn get(&self, id: ObjectId) -> Option<&Shape> {
+        self.index_of(id).map(|i| &*self.objects[i])
+    }
+
+    /// The object `id` stands for, to be edited in place. The BVH is
+    /// dropped, build it again once the edits are done.
+    pub fn get_mut(&mut self, id: ObjectId) -> Option<&mut Shape> {
+        let index = self.index_of(id)?;
+        self.objects_changed();
+        Some(Arc::make_mut(&mut self.objects[index]))
+    }
+
+    /// Drops what was derived from the objects.
+    fn objects_changed(&mut self) {
+        self.bvh = None;
+        self.media = OnceLock::new();
+        self.generation = next_generation();
+    }
+
+    /// Indices of the objects filled with a medium.
+    fn media(&self) -> &[usize] {
+        self.media.get_or_init(|| {
+            (0..self.objects.len())
+                .filter(|&i| self.objects[i].material.medium.is_some())
+                .collect()
+        })
+    }
+
+    /// Handle of the object at `index` in [`World::objects`].
+    pub fn id_at(&self, index: usize) -> ObjectId {
+        match self.ids.get(index) {
+            Some(&id) => id,
+            None => ObjectId(self.next_id + (index - self.ids.len()) as u64),
+        }
+    }
+
+    /// Index in [`World::objects`] of the object `id` stands for.
+    pub fn index_of(&self, id: ObjectId) -> Option<usize> {
+        let index = match self.ids.binary_search(&id) {
+            Ok(index) => index,
+            Err(_) if id.0 >= self.next_id => self.ids.len() + (id.0 - self.next_id) as usize,
+            Err(_) => return None,
+        };
+        (index < self.objects.len()).then_some(index)
+    }
+
+    /// Gives their ids to the objects added since the last removal.
+    fn assign_ids(&mut self) {
+        self.ids.truncate(self.objects.len());
+        while self.ids.len() < self.objects.len() {
+            self.ids.push(ObjectId(self.next_id));
+            self.next_id += 1;
+        }
+    }
+
+    /// Hash of the objects, lights and settings, telling whether a
+    /// [`Checkpoint`](crate::checkpoint::Checkpoint) was saved for this
+    /// world.
+    pub fn fingerprint(&self) -> u64 {
+        checkpoint::fingerprint(&[
+            &self.objects,
+            &self.lights,
+            &self.max_depth,
+            &self.clip_planes,
+            &self.background,
+            &self.ambient,
+            &self.bias,
+        ])
+    }
+
+    /// Counts of what the world holds, to check what a loaded scene
+    /// contains. Also printed by the `Display` impl of the world.
+    pub fn describe(&self) -> Summary {
+        let mut objects = BTreeMap::new();
+        let mut materials: Vec<Material> = vec![];
+        for o in &self.objects {
+            *objects.entry(o.object().name()).or_insert(0) += 1;
+            if !materials.contains(&o.material) {
+                materials.push(o.material.clone());
+            }
+        }
+        Summary {
+            objects,
+            materials,
+            lights: self.lights.clone(),
+            bounds: self.bounds(),
+        }
+    }
+
+    /// Box around every object, infinite as soon as one of them is.
+    pub fn bounds(&self) -> Bounds {
+        self.objects
             .iter()
-            .map(|o| o.intersects(r).into_inner())
-            .flatten()
-            .collect::<Vec<_>>();
-        i.sort_by(|a, b| a.time.total_cmp(&b.time));
-        Intersections::new(i)
+            .fold(Bounds::empty(), |b, o| b.merge(&o.bounds()))
+    }
+
+    /// Box around the objects with finite bounds, the extent of the scene
+    /// when it stands on an infinite plane.
+    pub fn finite_bounds(&self) -> Bounds {
+        self.objects
+            .iter()
+            .map(|o| o.bounds())
+            .filter(|b| !b.is_infinite())
+            .fold(Bounds::empty(), |b, o| b.merge(&o))
+    }
+
+    /// Objects whose bounds overlap `region`, the infinite ones included.
+    pub fn objects_in_bounds(&self, region: &Bounds) -> Vec<ObjectId> {
+        self.overlapping(region, |_| true)
+    }
+
+    /// Objects whose bounds come within `radius` of `center`, the infinite
+    /// ones included.
+    pub fn objects_within(&self, center: Tuple, radius: Float) -> Vec<ObjectId> {
+        let reach = vector(radius, radius, radius);
+        let region = Bounds::new(center - reach, center + reach);
+        self.overlapping(&region, |b| b.distance_to(center) <= radius)
     }
 
-    pub fn color_at(&self, r: crate::ray::Ray, depth: usize) -> Color {
-        let xs = self.intersects(r);
-        let hit = xs.hit();
-        match hit {
-            Some(h) => self.shade_hit(h.prepare_computations(r, &xs), depth),
-            None => Color::black(),
+    /// Objects whose bounds overlap `region` and pass `keep`, in order.
+    fn overlapping(&self, region: &Bounds, keep: impl Fn(&Bounds) -> bool) -> Vec<ObjectId> {
+        let mut found = vec![];
+        let add = |i: usize| {
+            let b = self.objects[i].bounds();
+            if b.overlaps(region) && keep(&b) {
+                found.push(i);
+            }
+        };
+        match &self.bvh {
+            Some(bvh) => bvh.for_each_overlapping(region, add),
+            None => (0..self.objects.len()).for_each(add),
+        }
+        found.sort_unstable();
+        found.into_iter().map(|i| self.id_at(i)).collect()
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn add_light(&mut self, light: Light) {
+        self.lights.push(light);
+    }
+
+    pub fn with_light(mut self, light: Light) -> Self {
+        self.lights.push(light);
+        self
+    }
+
+    /// Adds `shapes`, as [`World::add`] does.
+    pub fn with_objects(mut self, shapes: impl IntoIterator<Item = Shape>) -> Self {
+        for shape in shapes {
+            self.add(shape);
+        }
+        self
+    }
+
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    /// Sets the color multiplied into the ambient term of every material,
+    /// to brighten or darken the whole scene in one place.
+    pub fn set_ambient(&mut self, ambient: Color) {
+        self.ambient = ambient;
+    }
+
+    pub fn with_ambient(mut self, ambient: Color) -> Self {
+        self.set_ambient(ambient);
+        self
+    }
+
+    pub fn ambient(&self) -> Color {
+        self.ambient
+    }
+
+    /// Sets how far shading points are moved off the surfaces they lie on,
+    /// to tune for the scale of the scene.
+    pub fn set_bias(&mut self, bias: Bias) {
+        self.bias = bias;
+    }
+
+    pub fn with_bias(mut self, bias: Bias) -> Self {
+        self.set_bias(bias);
+        self
+    }
+
+    pub fn bias(&self) -> Bias {
+        self.bias
+    }
+
+    /// Calls `hook` for every ray [`World::color_at`] traces, bounces
+    /// included, to dump the whole tree of rays behind a pixel. A ray is
+    /// reported once its color is known, so after its bounces.
+    pub fn set_ray_hook(&mut self, hook: impl Fn(&RaySegment) + Send + Sync + 'static) {
+        self.ray_hook = Some(Arc::new(hook));
+    }
+
+    pub fn with_ray_hook(mut self, hook: impl Fn(&RaySegment) + Send + Sync + 'static) -> Self {
+        self.set_ray_hook(hook);
+        self
+    }
+
+    pub fn clear_ray_hook(&mut self) {
+        self.ray_hook = None;
+    }
+
+    /// Cuts away, for every ray, the geometry on the outer side of `plane`.
+    pub fn add_clip_plane(&mut self, plane: ClipPlane) {
+        self.clip_planes.push(plane);
+    }
+
+    pub fn with_clip_plane(mut self, plane: ClipPlane) -> Self {
+        self.clip_planes.push(plane);
+        self
+    }
+
+    /// Sets what the rays missing every object see instead of black: a
+    /// [`Pattern::solid`] color, a [`Pattern::vertical_gradient`] or a
+    /// [`Pattern::sky`]. It also lights the scene in path tracing.
+    pub fn set_background(&mut self, background: Pattern) {
+        self.background = Some(background);
+    }
+
+    pub fn with_background(mut self, background: Pattern) -> Self {
+        self.set_background(background);
+        self
+    }
+
+    pub fn background(&self) -> Option<&Pattern> {
+        self.background.as_ref()
+    }
+
+    /// Color seen along `r` when it hits nothing.
+    fn miss_color(&self, r: Ray) -> Color {
+        self.background
+            .as_ref()
+            .map_or(Color::black(), |b| b.color_at_direction(r.direction))
+    }
+
+    pub fn clip_planes(&self) -> &[ClipPlane] {
+        &self.clip_planes
+    }
+
+    fn clipped(&self, p: Tuple) -> bool {
+        self.clip_planes.iter().any(|c| c.clips(p))
+    }
+
+    pub fn color_at(&self, r: crate::ray::Ray) -> Color {
+        self.color_at_depth(r, self.max_depth)
+    }
+
+    /// Color seen along `r`, letting secondary rays bounce `depth` more times.
+    pub fn color_at_depth(&self, r: Ray, depth: usize) -> Color {
+        self.trace(r, depth, RayKind::Camera)
+    }
+
+    fn trace(&self, r: Ray, depth: usize, kind: RayKind) -> Color {
+        self.trace_absorbed(r, depth, kind, Color::black())
+    }
+
+    /// Same as [`World::trace`], for a ray travelling inside an object that
+    /// absorbs light along the way: the color is dimmed by Beer's law over
+    /// the distance to the next surface.
+    fn trace_absorbed(&self, r: Ray, depth: usize, kind: RayKind, absorption: Color) -> Color {
+        stats::count_ray();
+        let comps = with_scratch(|xs| {
+            self.intersects_into(r, xs);
+            self.surface_hit(r, xs, kind)
+        });
+        let end = comps.as_ref().map_or(Float::INFINITY, |c| c.i.time);
+        let hit = self
+            .ray_hook
+            .as_ref()
+            .and_then(|_| comps.as_ref().map(|c| c.i.clone()));
+        let mut color = self.resolve(r, comps, |c| self.shade_hit(&c, depth));
+        if absorption != Color::black() {
+            let distance = end * r.direction.mag();
+            let transmitted = |a: Float| (-a * distance).exp();
+            color = color
+                * Color::new(
+                    transmitted(absorption.r()),
+                    transmitted(absorption.g()),
+                    transmitted(absorption.b()),
+                );
         }
+        if let Some(hook) = &self.ray_hook {
+            hook(&RaySegment {
+                ray: r,
+                depth,
+                kind,
+                hit,
+                color,
+            });
+        }
+        color
+    }
+
+    /// Same as [`World::color_at`], only looking for the first hit among the
+    /// objects `bvh` lets through. Secondary rays still see the whole world.
+    pub fn color_at_bvh(&self, r: Ray, bvh: &Bvh) -> Color {
+        stats::count_ray();
+        let comps = with_scratch(|xs| {
+            self.gather(r, bvh, xs);
+            sort_by_time(xs);
+            self.surface_hit(r, xs, RayKind::Camera)
+        });
+        self.resolve(r, comps, |c| self.shade_hit(&c, self.max_depth))
+    }
+
+    /// Index in `objects` and time of the first object hit by `r` among those
+    /// `bvh` lets through. Volumes are seen through.
+    pub fn first_hit_bvh(&self, r: Ray, bvh: &Bvh) -> Option<(usize, Float)> {
+        self.check_bvh(bvh);
+        let mut best = None;
+        bvh.for_each_candidate(r, |i| self.keep_nearest(r, i, &mut best));
+        best
     }
 
-    pub fn refracted_color(&self, comps: Computations, depth: usize) -> Color {
-        if comps.i.object.material.transparency == 0.0 || depth == 0 {
+    /// Surface `r` hits first, as seen by the camera, for picking or line of
+    /// sight checks: nothing is shaded.
+    pub fn cast_ray(&self, r: Ray) -> Option<HitInfo> {
+        let mut best = None;
+        match &self.bvh {
+            Some(bvh) => bvh.for_each_candidate(r, |i| self.keep_nearest(r, i, &mut best)),
+            None => (0..self.objects.len()).for_each(|i| self.keep_nearest(r, i, &mut best)),
+        }
+        let (index, t) = best?;
+        let object = &self.objects[index];
+        let point = r.position(t);
+        let normal = object.normal_at(&point);
+        Some(HitInfo {
+            point,
+            normal: if (normal ^ r.direction) > 0.0 {
+                -normal
+            } else {
+                normal
+            },
+            distance: t * r.direction.mag(),
+            object: self.id_at(index),
+            material: object.material.clone(),
+        })
+    }
+
+    /// Replaces `best` by the first hit of the object at `index`, if it is
+    /// nearer.
+    fn keep_nearest(&self, r: Ray, index: usize, best: &mut Option<(usize, Float)>) {
+        let o = &self.objects[index];
+        if o.material.medium.is_some() || !o.visible_to_camera {
+            return;
+        }
+        for &t in o.hit_times(r).as_slice() {
+            if t > 0.0 && best.is_none_or(|(_, b)| t < b) && !self.clipped(r.position(t)) {
+                *best = Some((index, t));
+            }
+        }
+    }
+
+    /// Shades the hit among already computed intersections of `r`.
+    pub fn color_for(&self, r: Ray, xs: &[Intersection]) -> Color {
+        let mut xs = xs.to_vec();
+        sort_by_time(&mut xs);
+        let comps = self.surface_hit(r, &mut xs, RayKind::Camera);
+        self.resolve(r, comps, |c| self.shade_hit(&c, self.max_depth))
+    }
+
+    /// Color seen along `r`, whose first surface hit is `comps` and is lit
+    /// by `shade`, once the caps of the clip planes and the volumes on the
+    /// way are accounted for.
+    fn resolve(
+        &self,
+        r: Ray,
+        comps: Option<Computations>,
+        shade: impl FnOnce(Computations) -> Color,
+    ) -> Color {
+        let end = comps.as_ref().map_or(Float::INFINITY, |c| c.i.time);
+        let (end, color) = match self.cap_hit(r, end) {
+            Some(cap) => cap,
+            None => (end, comps.map_or_else(|| self.miss_color(r), shade)),
+        };
+        self.through_media(r, end, color)
+    }
+
+    /// Time and color of the first cap `r` meets before `end`: a point of a
+    /// capped clip plane lying inside a closed object.
+    fn cap_hit(&self, r: Ray, end: Float) -> Option<(Float, Color)> {
+        let mut best: Option<(Float, Color)> = None;
+        for (i, plane) in self.clip_planes.iter().enumerate() {
+            let (Some(color), Some(t)) = (plane.cap, plane.crossing(r)) else {
+                continue;
+            };
+            if t <= 0.0 || t >= end || best.is_some_and(|(b, _)| b <= t) {
+                continue;
+            }
+            let p = r.position(t);
+            let cut = self
+                .clip_planes
+                .iter()
+                .enumerate()
+                .any(|(j, other)| j != i && other.clips(p));
+            // inside a closed shape when an odd number of its surfaces lie
+            // behind the point along the ray
+            let inside = self.any_along(r, |o| {
+                o.material.medium.is_none()
+                    && o.hit_times(r).as_slice().iter().filter(|&&h| h < t).count() % 2 == 1
+            });
+            if !cut && inside {
+                best = Some((t, color));
+            }
+        }
+        best
+    }
+
+    pub fn refracted_color(&self, comps: &Computations, depth: usize) -> Color {
+        let shape = &comps.i.object;
+        let transparency = comps.material().transparency_at(shape, comps.over_point);
+        self.refracted(comps, depth, transparency)
+    }
+
+    fn refracted(&self, comps: &Computations, depth: usize, transparency: Float) -> Color {
+        if transparency == 0.0 || depth == 0 {
             Color::black()
         } else {
             // compute snell's law
-            let (n1, n2) = comps.n;
-            let n_ratio = n1 / n2;
-            let cos_i = comps.eye_v ^ comps.normal_v;
-            let sin2_t = n_ratio * n_ratio * (1.0 - (cos_i * cos_i));
-            if sin2_t > 1.0 {
-                Color::black()
+            match comps.refracted_direction() {
+                None => Color::black(),
+                Some(direction) => {
+                    let refracted_ray = Ray::new(comps.under_point, direction);
+                    // going into the object
+                    let absorption = if comps.inside {
+                        Color::black()
+                    } else {
+                        comps.i.object.material.absorption
+                    };
+                    self.trace_absorbed(refracted_ray, depth - 1, RayKind::Refraction, absorption)
+                        * transparency
+                }
+            }
+        }
+    }
+
+    /// Monte Carlo estimate of the light coming along `r`, following one
+    /// random path of at most `depth` bounces. Unlike [`World::color_at`] it
+    /// accounts for light bounced off diffuse surfaces and for emissive
+    /// materials, so it needs many samples per pixel to converge.
+    pub fn trace_path(&self, r: Ray, rng: &mut Rng, depth: usize) -> Color {
+        self.follow_path(r, rng, depth, RayKind::Camera)
+    }
+
+    fn follow_path(&self, r: Ray, rng: &mut Rng, depth: usize, kind: RayKind) -> Color {
+        if depth == 0 {
+            return Color::black();
+        }
+        stats::count_ray();
+        let comps = with_scratch(|xs| {
+            self.intersects_into(r, xs);
+            self.surface_hit(r, xs, kind)
+        });
+        self.resolve(r, comps, |c| self.path_vertex(&c, rng, depth))
+    }
+
+    /// Light leaving the surface hit in `comps` towards the previous vertex
+    /// of the path.
+    fn path_vertex(&self, comps: &Computations, rng: &mut Rng, depth: usize) -> Color {
+        let shape = &comps.i.object;
+        let m = comps.material();
+        let surface = m.surface_at(shape, comps.point, comps.footprint);
+        let emitted = surface.emissive;
+
+        // pick a single lobe with the probability of its weight, so the
+        // estimate needs no further weighting
+        let u = rng.next_f64();
+        if u < surface.reflective {
+            let reflected = Ray::new(comps.over_point, comps.reflect_v);
+            return emitted + self.follow_path(reflected, rng, depth - 1, RayKind::Reflection);
+        }
+        if u < surface.reflective + surface.transparency {
+            let (next, kind) = match comps.refracted_direction() {
+                Some(direction) if rng.next_f64() >= comps.schlick() => {
+                    (Ray::new(comps.under_point, direction), RayKind::Refraction)
+                }
+                _ => (
+                    Ray::new(comps.over_point, comps.reflect_v),
+                    RayKind::Reflection,
+                ),
+            };
+            return emitted + self.follow_path(next, rng, depth - 1, kind);
+        }
+
+        let albedo = surface.color;
+        let mut direct = Color::black();
+        for light in &self.lights {
+            let visibility = self.light_visibility(light.position, comps.over_point);
+            if visibility <= 0.0 {
+                continue;
+            }
+            let light_v = (light.position - comps.over_point).norm();
+            let light_dot_normal = light_v ^ comps.normal_v;
+            if light_dot_normal <= 0.0 {
+                continue;
+            }
+            let intensity = light.intensity * visibility;
+            direct = direct + albedo * intensity * (surface.diffuse * light_dot_normal);
+            let reflect_dot_eye = (-light_v).reflect(&comps.normal_v) ^ comps.eye_v;
+            if reflect_dot_eye > 0.0 {
+                direct = direct
+                    + intensity * (surface.specular * reflect_dot_eye.powf(surface.shininess));
+            }
+        }
+
+        // bounce: cosine weighted for the diffuse lobe, importance sampled
+        // Phong lobe for the specular one, the rest of the energy is absorbed
+        let scale = 1.0 / (surface.diffuse + surface.specular).max(1.0);
+        let (kd, ks) = (surface.diffuse * scale, surface.specular * scale);
+        let sample = rng.next_2d();
+        let lobe = rng.next_f64();
+        let indirect = if lobe < kd {
+            let direction = cosine_hemisphere(comps.normal_v, sample);
+            let bounce = Ray::new(comps.over_point, direction);
+            albedo * self.follow_path(bounce, rng, depth - 1, RayKind::Reflection)
+        } else if lobe < kd + ks {
+            let direction = phong_lobe(comps.reflect_v, surface.shininess, sample);
+            if (direction ^ comps.normal_v) > 0.0 {
+                let bounce = Ray::new(comps.over_point, direction);
+                self.follow_path(bounce, rng, depth - 1, RayKind::Reflection)
             } else {
-                let cos_t = f64::sqrt(1.0 - sin2_t);
-                let direction = comps.normal_v * (n_ratio * cos_i - cos_t) - comps.eye_v * n_ratio;
-                let refracted_ray = Ray::new(comps.under_point, direction);
-                self.color_at(refracted_ray, depth - 1) * comps.i.object.material.transparency
+                Color::black()
             }
+        } else {
+            Color::black()
+        };
+        emitted + direct + indirect
+    }
+
+    /// Attenuates `behind`, the light reaching the origin of `r` from the
+    /// surface it hits at time `end`, through the volumes `r` crosses on the
+    /// way, and adds the light they scatter towards the origin (single
+    /// scattering, ray marched).
+    fn through_media(&self, r: Ray, end: Float, behind: Color) -> Color {
+        if self.media().is_empty() {
+            return behind;
         }
+        let mut segments = self
+            .media()
+            .iter()
+            .filter_map(|&i| {
+                let o = &self.objects[i];
+                Some((o.material.medium?, segment(o, r, end)?))
+            })
+            .collect::<Vec<_>>();
+        if segments.is_empty() {
+            return behind;
+        }
+        segments.sort_by(|a, b| a.1 .0.total_cmp(&b.1 .0));
+        // times are in units of the direction, which may not be normalized
+        let speed = r.direction.mag();
+        let mut transmittance = 1.0;
+        let mut scattered = Color::black();
+        for (medium, (enter, exit)) in segments {
+            let dt = (exit - enter) / MEDIUM_STEPS as Float;
+            for step in 0..MEDIUM_STEPS {
+                let t = enter + (step as Float + 0.5) * dt;
+                let seen = transmittance * medium.transmittance((t - enter) * speed);
+                let weight = medium.density * dt * speed * seen;
+                scattered = scattered + self.in_scattered(r.position(t)) * medium.albedo * weight;
+            }
+            transmittance *= medium.transmittance((exit - enter) * speed);
+        }
+        behind * transmittance + scattered
+    }
+
+    /// Light from every light source reaching `p` inside a volume and
+    /// scattered isotropically.
+    fn in_scattered(&self, p: Tuple) -> Color {
+        let mut light = Color::black();
+        for l in &self.lights {
+            let v = l.position - p;
+            let distance = v.mag();
+            let r = Ray::new(p, v.norm());
+            if self.occluded(r, distance) {
+                continue;
+            }
+            light = light + l.intensity * (self.media_transmittance(r, distance) / (4.0 * PI));
+        }
+        light
+    }
+
+    /// Fraction of light getting through the volumes along `r` up to
+    /// `distance`.
+    fn media_transmittance(&self, r: Ray, distance: Float) -> Float {
+        self.media()
+            .iter()
+            .filter_map(|&i| {
+                let o = &self.objects[i];
+                let medium = o.material.medium?;
+                let (enter, exit) = segment(o, r, distance)?;
+                Some(medium.transmittance(exit - enter))
+            })
+            .product()
     }
 
-    fn is_shadowed(&self, p: Tuple) -> bool {
-        let v = self.lights[0].position - p;
+    /// Computations for the first hit of `r` on a surface. The boundaries of
+    /// volumes, handled by [`World::through_media`], the clipped parts of the
+    /// surfaces and the shapes hidden from rays of this `kind` are dropped
+    /// from `xs`.
+    fn surface_hit(
+        &self,
+        r: Ray,
+        xs: &mut Vec<Intersection>,
+        kind: RayKind,
+    ) -> Option<Computations> {
+        xs.retain(|i| {
+            i.object.material.medium.is_none()
+                && i.object.visible_to(kind)
+                && !self.clipped(r.position(i.time))
+                && !(i.object.material.back_face == BackFace::Cull
+                    && (i.object.normal_at(&r.position(i.time)) ^ r.direction) > 0.0)
+        });
+        hit_sorted(xs).map(|h| h.prepare_computations_with_bias(r, xs, self.bias))
+    }
+
+    /// Whether `p` is in the full shadow of the light at `light_position`,
+    /// a point light or a sample point on an area light.
+    pub fn is_shadowed(&self, light_position: Tuple, p: Tuple) -> bool {
+        self.light_visibility(light_position, p) <= 0.0
+    }
+
+    /// Fraction of the light from `light_position` getting to `p`: 0 behind
+    /// an opaque object, more behind the ones with a lower shadow opacity.
+    pub fn light_visibility(&self, light_position: Tuple, p: Tuple) -> Float {
+        let v = light_position - p;
         let distance = v.mag();
-        let direction = v.norm();
-        let r = Ray::new(p, direction);
-        let xs = self.intersects(r);
-        let h = xs.hit();
-        if h.is_some_and(|h| h.time < distance) {
-            true
-        } else {
-            false
+        let r = Ray::new(p, v.norm());
+        let mut visibility = 1.0;
+        // stops at the first opaque object found
+        let blocks = |o: &Shape| {
+            let opacity = o.material.shadow_opacity.min(1.0);
+            if o.material.medium.is_some() || opacity <= 0.0 {
+                return false;
+            }
+            let hit = o
+                .hit_times(r)
+                .as_slice()
+                .iter()
+                .any(|&t| t > 0.0 && t < distance && !self.clipped(r.position(t)));
+            if hit {
+                visibility *= 1.0 - opacity;
+            }
+            visibility <= 0.0
+        };
+        self.any_along(r, blocks);
+        visibility
+    }
+
+    /// Any-hit query: whether something lies on `r` in front of its origin
+    /// and closer than `distance`. Stops at the first such object found.
+    /// Volumes do not block rays.
+    pub fn occluded(&self, r: Ray, distance: Float) -> bool {
+        let blocks = |o: &Shape| {
+            o.material.medium.is_none()
+                && o.hit_times(r)
+                    .as_slice()
+                    .iter()
+                    .any(|&t| t > 0.0 && t < distance && !self.clipped(r.position(t)))
+        };
+        self.any_along(r, blocks)
+    }
+
+    /// Whether `f` holds for an object `r` may hit, trying the candidates
+    /// of the BVH when there is one and stopping at the first match.
+    fn any_along(&self, r: Ray, mut f: impl FnMut(&Shape) -> bool) -> bool {
+        match &self.bvh {
+            Some(bvh) => bvh.any_candidate(r, |i| f(&self.objects[i])),
+            None => self.objects.iter().any(|o| f(o)),
         }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use std::f64::consts::SQRT_2;
+thread_local! {
+    /// Intersection buffer reused by every ray traced on the thread.
+    static SCRATCH: RefCell<Vec<Intersection>> = const { RefCell::new(Vec::new()) };
+}
 
-    use intersection::Intersection;
+/// Runs `f` with the cleared scratch buffer of the current thread. The buffer
+/// is released when `f` returns, so `f` must not trace other rays itself.
+fn with_scratch<T>(f: impl FnOnce(&mut Vec<Intersection>) -> T) -> T {
+    SCRATCH.with(|scratch| {
+        let mut xs = scratch.borrow_mut();
+        xs.clear();
+        f(&mut xs)
+    })
+}
 
+#[cfg(test)]
+mod tests {
     use crate::{
-        material::Material, pattern::Pattern, ray::Ray, transformations::translation,
-        tuple::vector, util::MAX_REFLECTIONS,
+        clip::ClipPlane,
+        material::Material,
+        medium::Medium,
+        pattern::{Pattern, Sky},
+        ray::Ray,
+        transformations::{rot_x, translation},
+        tuple::vector,
+        util::{consts::SQRT_2, flt_eq},
     };
 
     use super::*;
@@ -154,10 +1181,10 @@ mod tests {
     fn shading_intersection() {
         let w = World::ch7_default();
         let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
-        let s = w.objects[0];
-        let i = Intersection::new(4.0, s);
-        let comps = i.prepare_computations(r, &Intersections::new(vec![i]));
-        let c = w.shade_hit(comps, MAX_REFLECTIONS);
+        let s = &w.objects()[0];
+        let i = Intersection::new(4.0, s.clone());
+        let comps = i.prepare_computations(r, &Intersections::new(vec![i.clone()]));
+        let c = w.shade_hit(&comps, MAX_REFLECTIONS);
         assert_eq!(c, Color::new(0.38066, 0.47583, 0.2855))
     }
 
@@ -166,10 +1193,10 @@ mod tests {
         let mut w = World::ch7_default();
         w.lights = vec![Light::new(point(0.0, 0.25, 0.0), Color::new(1.0, 1.0, 1.0))];
         let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
-        let s = w.objects[1];
-        let i = Intersection::new(0.5, s);
-        let comps = i.prepare_computations(r, &Intersections::new(vec![i]));
-        let c = w.shade_hit(comps, MAX_REFLECTIONS);
+        let s = &w.objects()[1];
+        let i = Intersection::new(0.5, s.clone());
+        let comps = i.prepare_computations(r, &Intersections::new(vec![i.clone()]));
+        let c = w.shade_hit(&comps, MAX_REFLECTIONS);
         assert_eq!(c, Color::new(0.90498, 0.90498, 0.90498))
     }
 
@@ -177,54 +1204,498 @@ mod tests {
     fn ray_misses() {
         let w = World::ch7_default();
         let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 1.0, 0.0));
-        let c = w.color_at(r, MAX_REFLECTIONS);
+        let c = w.color_at(r);
         assert_eq!(c, Color::black())
     }
 
+    #[test]
+    fn rays_that_miss_see_the_background() {
+        let sky = Sky {
+            coverage: 0.0,
+            ..Default::default()
+        };
+        let w = World::ch7_default().with_background(Pattern::sky(sky));
+        let up = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 1.0, 0.0));
+        assert_eq!(w.color_at(up), sky.zenith);
+        let down = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, -1.0, 0.0));
+        assert_eq!(w.color_at(down), sky.ground);
+        // objects still hide it
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(r), World::ch7_default().color_at(r));
+    }
+
+    #[test]
+    fn solid_and_gradient_backgrounds() {
+        let blue = Color::new(0.5, 0.7, 1.0);
+        let (up, down, ahead) = (
+            Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 1.0, 0.0)),
+            Ray::new(point(0.0, 0.0, -5.0), vector(0.0, -1.0, 0.0)),
+            Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, -1.0)),
+        );
+        let w = World::ch7_default().with_background(Pattern::solid(blue));
+        assert_eq!(w.color_at(up), blue);
+        let w =
+            World::ch7_default().with_background(Pattern::vertical_gradient(Color::white(), blue));
+        assert_eq!(w.color_at(up), blue);
+        assert_eq!(w.color_at(down), Color::white());
+        assert_eq!(w.color_at(ahead), (Color::white() + blue) * 0.5);
+    }
+
+    #[test]
+    fn the_ambient_light_tints_every_ambient_term() {
+        let w = World::ch7_default();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let lit = w.color_at(r);
+        let ambient = Color::new(0.8, 1.0, 0.6) * 0.1;
+        let w = w.with_ambient(Color::new(0.0, 2.0, 1.0));
+        assert_eq!(w.color_at(r), lit + ambient * Color::new(-1.0, 1.0, 0.0));
+    }
+
     #[test]
     fn ray_hits() {
         let w = World::ch7_default();
         let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
-        let c = w.color_at(r, MAX_REFLECTIONS);
+        let c = w.color_at(r);
         assert_eq!(c, Color::new(0.38066, 0.47583, 0.2855))
     }
 
     #[test]
     fn color_with_intersection_behind_the_ray() {
         let mut w = World::ch7_default();
-        w.objects[0].material.ambient = 1.0;
-        w.objects[1].material.ambient = 1.0;
+        w.get_mut(w.id_at(0)).unwrap().material.ambient = 1.0;
+        w.get_mut(w.id_at(1)).unwrap().material.ambient = 1.0;
         let r = Ray::new(point(0.0, 0.0, 0.75), vector(0.0, 0.0, -1.0));
-        let c = w.color_at(r, MAX_REFLECTIONS);
-        assert_eq!(c, w.objects[1].material.color);
+        let c = w.color_at(r);
+        assert_eq!(c, w.objects()[1].material.color);
+    }
+
+    #[test]
+    fn intersecting_through_the_bvh_finds_the_same_hits() {
+        let mut w = World::ch7_default();
+        w.add(Shape::plane().with_transform(translation(0.0, -1.0, 0.0)));
+        for i in 0..10 {
+            w.add(Shape::sphere().with_transform(translation(i as Float, 0.0, 3.0)));
+        }
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.1, -0.05, 1.0).norm());
+        let expected = w.intersects(r).into_inner();
+        w.build_bvh();
+        assert_eq!(w.intersects(r).into_inner(), expected);
+    }
+
+    #[test]
+    fn editing_objects_drops_the_bvh() {
+        let mut w = World::ch7_default();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        w.build_bvh();
+        let outer = w.id_at(0);
+        w.get_mut(outer)
+            .unwrap()
+            .set_transform(translation(0.0, 10.0, 0.0));
+        assert!(w.bvh.is_none());
+        assert_eq!(w.intersects(r).len(), 2);
+        w.build_bvh();
+        w.remove(outer);
+        assert!(w.bvh.is_none());
+        w.build_bvh();
+        w.add(Shape::sphere().with_transform(translation(0.0, 0.0, 5.0)));
+        assert_eq!(w.intersects(r).len(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "the BVH was built over other objects")]
+    fn stale_hierarchies_are_refused() {
+        let mut w = World::ch7_default();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let bvh = w.bvh_over(&[0, 1]);
+        // as many objects as before, but not the same
+        w.remove(w.id_at(1));
+        w.add(Shape::sphere());
+        w.color_at_bvh(r, &bvh);
+    }
+
+    #[test]
+    #[should_panic(expected = "the BVH was built over other objects")]
+    fn hierarchies_of_other_worlds_are_refused() {
+        let w = World::ch7_default();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let bvh = World::ch7_default().bvh_over(&[0, 1]);
+        w.color_at_bvh(r, &bvh);
+    }
+
+    #[test]
+    fn hits_share_the_shapes_of_the_world() {
+        let mut w = World::ch7_default();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = w.intersects(r);
+        assert!(Arc::ptr_eq(&xs.data()[0].object, &w.objects()[0]));
+        // editing the object leaves the hits found before alone
+        w.get_mut(w.id_at(0)).unwrap().material.ambient = 1.0;
+        assert_ne!(xs.data()[0].object.material, w.objects()[0].material);
+    }
+
+    #[test]
+    fn media_are_found_again_after_edits() {
+        let mut w = World::ch7_default();
+        assert!(w.media().is_empty());
+        let inner = w.id_at(1);
+        w.get_mut(inner).unwrap().material =
+            Material::default().medium(Medium::new(1.0, Color::white()));
+        assert_eq!(w.media(), &[1]);
+        w.remove(w.id_at(0));
+        assert_eq!(w.media(), &[0]);
+    }
+
+    #[test]
+    fn handles_survive_the_removal_of_other_objects() {
+        let mut w = World::ch7_default();
+        let (outer, inner) = (w.id_at(0), w.id_at(1));
+        let plane = w.add(Shape::plane());
+        assert_eq!(
+            w.remove(outer),
+            Some((*World::ch7_default().objects()[0]).clone())
+        );
+        assert_eq!(w.remove(outer), None);
+        assert_eq!(w.get(inner), Some(&*World::ch7_default().objects()[1]));
+        w.get_mut(plane).unwrap().material.reflective = 0.5;
+        assert_eq!(w.objects()[1].material.reflective, 0.5);
+        w.add(Shape::sphere());
+        let pushed = w.id_at(2);
+        assert_eq!(
+            w.remove(inner).map(|s| s.transform),
+            Some(scaling(0.5, 0.5, 0.5))
+        );
+        assert_eq!(w.index_of(plane), Some(0));
+        assert_eq!(w.index_of(pushed), Some(1));
+        assert_ne!(w.add(Shape::sphere()), pushed);
+    }
+
+    #[test]
+    fn casting_a_ray_reports_the_first_hit() {
+        let mut w = World::ch7_default();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 2.0));
+        let hit = w.cast_ray(r).unwrap();
+        assert_eq!(hit.point, point(0.0, 0.0, -1.0));
+        assert_eq!(hit.normal, vector(0.0, 0.0, -1.0));
+        assert_eq!(hit.distance, 4.0);
+        assert_eq!(hit.object, w.id_at(0));
+        assert_eq!(hit.material, w.objects()[0].material);
+        // from inside, the normal still faces the ray
+        w.remove(w.id_at(0));
+        let hit = w.cast_ray(Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0)));
+        assert_eq!(hit.unwrap().normal, vector(0.0, 0.0, -1.0));
+        assert_eq!(
+            w.cast_ray(Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 1.0, 0.0))),
+            None
+        );
+    }
+
+    #[test]
+    fn bounds_of_the_world() {
+        let mut w = World::ch7_default();
+        let unit = Bounds::new(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0));
+        assert_eq!(w.bounds(), unit);
+        w.add(Shape::plane());
+        assert!(w.bounds().is_infinite());
+        assert_eq!(w.finite_bounds(), unit);
+    }
+
+    #[test]
+    fn threads_trace_the_same_world() {
+        let mut w = World::ch11_default();
+        w.build_bvh();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, -0.1, 1.0));
+        let colors: Vec<Color> = std::thread::scope(|s| {
+            let threads: Vec<_> = (0..4).map(|_| s.spawn(|| w.color_at(r))).collect();
+            threads.into_iter().map(|t| t.join().unwrap()).collect()
+        });
+        assert!(colors.iter().all(|&c| c == w.color_at(r)));
+        // clones share the hierarchy instead of copying it
+        let copy = w.clone();
+        assert!(Arc::ptr_eq(
+            w.bvh.as_ref().unwrap(),
+            copy.bvh.as_ref().unwrap()
+        ));
+    }
+
+    #[test]
+    fn objects_found_around_a_region() {
+        let mut w = World::new();
+        for i in 0..10 {
+            w.add(Shape::sphere().with_transform(translation(3.0 * i as Float, 0.0, 0.0)));
+        }
+        w.add(Shape::plane());
+        let ids = |indices: &[usize]| indices.iter().map(|&i| w.id_at(i)).collect::<Vec<_>>();
+        let region = Bounds::new(point(5.5, -1.0, -1.0), point(9.5, 1.0, 1.0));
+        let expected = ids(&[2, 3, 10]);
+        assert_eq!(w.objects_in_bounds(&region), expected);
+        let expected_within = ids(&[1, 2, 10]);
+        assert_eq!(w.objects_within(point(4.5, 0.0, 0.0), 0.6), expected_within);
+        w.build_bvh();
+        assert_eq!(w.objects_in_bounds(&region), expected);
+        assert_eq!(w.objects_within(point(4.5, 0.0, 0.0), 0.6), expected_within);
+    }
+
+    #[test]
+    fn describing_a_world() {
+        let w = World::ch11_default();
+        let summary = w.describe();
+        assert_eq!(
+            summary.objects,
+            BTreeMap::from([("plane", 1), ("sphere", 3)])
+        );
+        assert_eq!(summary.materials.len(), 4);
+        assert_eq!(summary.lights, w.lights);
+        assert!(summary.bounds.is_infinite());
+        assert_eq!(
+            World::ch7_default().to_string(),
+            "2 objects: 2 spheres\n\
+             2 materials\n\
+             1 light\n  at (-10, 10, -10), intensity (1, 1, 1)\n\
+             bounds (-1, -1, -1) to (1, 1, 1)"
+        );
+        assert_eq!(
+            World::new().to_string(),
+            "0 objects\n0 materials\n0 lights\nno bounds"
+        );
+    }
+
+    #[test]
+    fn the_ray_hook_sees_every_bounce() {
+        let segments = Arc::new(std::sync::Mutex::new(vec![]));
+        let seen = segments.clone();
+        let w = World::ch11_default()
+            .with_max_depth(2)
+            .with_ray_hook(move |s| seen.lock().unwrap().push(s.clone()));
+        let r = Ray::new(
+            point(0.0, 0.0, -3.0),
+            vector(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
+        );
+        let color = w.color_at(r);
+        let segments = segments.lock().unwrap();
+        let camera = segments.last().unwrap();
+        assert_eq!(
+            (camera.kind, camera.depth, camera.color),
+            (RayKind::Camera, 2, color)
+        );
+        assert_eq!(camera.hit.as_ref().unwrap().object, w.objects()[2]);
+        let kinds: Vec<_> = segments
+            .iter()
+            .filter(|s| s.depth == 1)
+            .map(|s| s.kind)
+            .collect();
+        assert_eq!(kinds, vec![RayKind::Reflection, RayKind::Refraction]);
+        assert!(segments.iter().all(|s| s.depth >= 1));
+    }
+
+    #[test]
+    fn merging_moves_the_other_world() {
+        let mut w = World::ch7_default();
+        let old = w.id_at(0);
+        let ids = w.merge(World::ch7_default(), translation(5.0, 0.0, 0.0));
+        assert_eq!(w.objects().len(), 4);
+        assert_eq!(w.lights()[1].position, point(-5.0, 10.0, -10.0));
+        let inner = w.get(ids[1]).unwrap();
+        assert_eq!(
+            inner.transform,
+            translation(5.0, 0.0, 0.0) * scaling(0.5, 0.5, 0.5)
+        );
+        w.clear_objects();
+        assert!(w.objects().is_empty());
+        assert_eq!(w.lights().len(), 2);
+        assert_eq!(w.get(old), None);
+        assert!(!ids.contains(&w.add(Shape::sphere())));
+    }
+
+    #[test]
+    fn merged_lights_light_the_scene() {
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let mut w = World::ch7_default();
+        let one = w.color_at(r);
+        w.merge(
+            World::new().with_light(Light::new(point(-10.0, 10.0, -10.0), Color::white())),
+            Mat4::identity(),
+        );
+        assert_eq!(w.color_at(r), one * 2.0);
+    }
+
+    #[test]
+    fn max_depth_defaults_to_max_reflections() {
+        assert_eq!(World::new().max_depth(), MAX_REFLECTIONS);
+        assert_eq!(World::ch7_default().with_max_depth(3).max_depth(), 3);
+    }
+
+    #[test]
+    fn a_world_without_bounces_ignores_reflections() {
+        let mut w = World::ch7_default().with_max_depth(0);
+        let shape = Shape::plane()
+            .with_material(Material::default().reflective(0.5))
+            .with_transform(translation(0.0, -1.0, 0.0));
+        w.add(shape);
+        let r = Ray::new(
+            point(0.0, 0.0, -3.0),
+            vector(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
+        );
+        let flat = w.color_at(r);
+        w.set_max_depth(MAX_REFLECTIONS);
+        assert_ne!(w.color_at(r), flat);
+        assert_eq!(w.color_at_depth(r, 0), flat);
+    }
+
+    #[test]
+    fn path_tracing_a_miss_is_black() {
+        let w = World::ch7_default();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 1.0, 0.0));
+        assert_eq!(w.trace_path(r, &mut Rng::new(0), 5), Color::black());
+    }
+
+    #[test]
+    fn path_tracing_sees_emissive_surfaces() {
+        let mut w = World::new();
+        w.add(Shape::sphere().with_material(Material {
+            diffuse: 0.0,
+            specular: 0.0,
+            emissive: Color::new(2.0, 1.0, 0.5),
+            ..Default::default()
+        }));
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(
+            w.trace_path(r, &mut Rng::new(1), 5),
+            Color::new(2.0, 1.0, 0.5)
+        );
+    }
+
+    #[test]
+    fn path_tracing_picks_up_light_bounced_off_other_objects() {
+        // the floor is out of reach of the light, it can only be lit by the
+        // glowing ball above it
+        let mut w = World::new();
+        let floor = Shape::plane().with_material(Material {
+            specular: 0.0,
+            ..Default::default()
+        });
+        let lamp = Shape::sphere()
+            .with_material(Material::default().emissive(Color::white()))
+            .with_transform(translation(0.0, 2.0, 0.0));
+        w.add(floor);
+        w.add(lamp);
+        let r = Ray::new(point(0.0, 1.0, -5.0), vector(0.0, -1.0, 5.0).norm());
+        let mut rng = Rng::new(7);
+        let sum = (0..256).fold(Color::black(), |acc, _| acc + w.trace_path(r, &mut rng, 3));
+        assert!(sum.r() > 0.0);
+    }
+
+    #[test]
+    fn occlusion_only_counts_objects_within_the_distance() {
+        let mut w = World::ch7_default();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert!(w.occluded(r, 10.0));
+        assert!(!w.occluded(r, 3.5));
+        w.build_bvh();
+        assert!(w.occluded(r, 10.0));
+        assert!(!w.occluded(r, 3.5));
+        let behind = Ray::new(point(0.0, 0.0, 5.0), vector(0.0, 0.0, 1.0));
+        assert!(!w.occluded(behind, 100.0));
     }
 
     #[test]
     fn no_shadow_when_nothing_is_collinear() {
         let w = World::ch7_default();
         let p = point(0.0, 10.0, 0.0);
-        assert_eq!(w.is_shadowed(p), false);
+        assert!(!w.is_shadowed(w.lights[0].position, p));
     }
 
     #[test]
     fn shadow_when_point_behind_object() {
         let w = World::ch7_default();
         let p = point(10.0, -10.0, 10.0);
-        assert_eq!(w.is_shadowed(p), true);
+        assert!(w.is_shadowed(w.lights[0].position, p));
     }
 
     #[test]
     fn no_shadow_when_object_behind_light() {
         let w = World::ch7_default();
         let p = point(-20.0, 20.0, -20.0);
-        assert_eq!(w.is_shadowed(p), false);
+        assert!(!w.is_shadowed(w.lights[0].position, p));
     }
 
     #[test]
     fn no_shadow_when_object_behind_the_point() {
         let w = World::ch7_default();
         let p = point(-2.0, 2.0, -2.0);
-        assert_eq!(w.is_shadowed(p), false);
+        assert!(!w.is_shadowed(w.lights[0].position, p));
+    }
+
+    #[test]
+    fn shadows_are_cast_per_light() {
+        let w =
+            World::ch7_default().with_light(Light::new(point(20.0, -20.0, 20.0), Color::white()));
+        let p = point(10.0, -10.0, 10.0);
+        assert!(w.is_shadowed(w.lights()[0].position, p));
+        assert!(!w.is_shadowed(w.lights()[1].position, p));
+    }
+
+    #[test]
+    fn back_faces_can_be_culled_or_shaded_differently() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let mut w = World::new();
+        w.lights
+            .push(Light::new(point(0.0, 0.0, -10.0), Color::white()));
+        w.add(Shape::sphere().with_material(Material::default().back_face(BackFace::Cull)));
+        let from_inside = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
+        let from_outside = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(from_inside), Color::black());
+        assert_ne!(w.color_at(from_outside), Color::black());
+
+        let front = w.color_at(from_outside);
+        let back = Material {
+            color: red,
+            ambient: 1.0,
+            diffuse: 0.0,
+            specular: 0.0,
+            ..Default::default()
+        };
+        w.get_mut(w.id_at(0)).unwrap().material =
+            Material::default().back_face(BackFace::Material(Arc::new(back)));
+        assert_eq!(w.color_at(from_inside), red);
+        assert_eq!(w.color_at(from_outside), front);
+    }
+
+    #[test]
+    fn shadow_opacity_lets_some_light_through() {
+        let mut w = World::new();
+        let light = Light::new(point(0.0, 0.0, -10.0), Color::white());
+        w.lights.push(light);
+        let glass = Shape::glass_sphere().with_transform(translation(0.0, 0.0, -5.0));
+        w.add(glass.with_material(Material::default().transparency(1.0).shadow_opacity(0.4)));
+        let p = point(0.0, 0.0, 0.0);
+        assert!(flt_eq(w.light_visibility(light.position, p), 0.6));
+        assert!(!w.is_shadowed(w.lights[0].position, p));
+        w.add(
+            Shape::sphere()
+                .with_material(Material::default().shadow_opacity(0.5))
+                .with_transform(translation(0.0, 0.0, -2.0)),
+        );
+        assert!(flt_eq(w.light_visibility(light.position, p), 0.3));
+        w.get_mut(w.id_at(0)).unwrap().material.shadow_opacity = 0.0;
+        assert!(flt_eq(w.light_visibility(light.position, p), 0.5));
+        w.get_mut(w.id_at(1)).unwrap().material.shadow_opacity = 1.0;
+        assert!(w.is_shadowed(w.lights[0].position, p));
+    }
+
+    #[test]
+    fn translucent_planes_are_lit_from_behind() {
+        let mut w = World::new();
+        w.lights
+            .push(Light::new(point(0.0, -10.0, 0.0), Color::white()));
+        let leaf = Shape::plane().with_material(Material::default().translucency(0.5));
+        w.add(leaf.clone());
+        let r = Ray::new(point(0.0, 5.0, 0.0), vector(0.0, -1.0, 0.0));
+        let i = Intersection::new(5.0, leaf);
+        let comps = i.prepare_computations(r, &Intersections::new(vec![i.clone()]));
+        assert_eq!(w.shade_hit(&comps, 0), Color::new(0.6, 0.6, 0.6));
+        // something between the light and the back of the plane
+        w.add(Shape::sphere().with_transform(translation(0.0, -5.0, 0.0)));
+        assert_eq!(w.shade_hit(&comps, 0), Color::new(0.1, 0.1, 0.1));
     }
 
     #[test]
@@ -235,14 +1706,14 @@ mod tests {
             point(0.0, 0.0, -10.0),
             Color::new(1.0, 1.0, 1.0),
         ));
-        w.objects.push(Shape::sphere());
+        w.add(Shape::sphere());
         let mut s2 = Shape::sphere();
         s2.set_transform(translation(0.0, 0.0, 10.0));
-        w.objects.push(s2);
+        w.add(s2.clone());
         let r = Ray::new(point(0.0, 0.0, 5.0), vector(0.0, 0.0, 1.0));
-        let i = Intersection::new(4.0, s2);
-        let comps = i.prepare_computations(r, &Intersections::new(vec![i]));
-        let c = w.shade_hit(comps, MAX_REFLECTIONS);
+        let i = Intersection::new(4.0, s2.clone());
+        let comps = i.prepare_computations(r, &Intersections::new(vec![i.clone()]));
+        let c = w.shade_hit(&comps, MAX_REFLECTIONS);
         assert_eq!(c, Color::new(0.1, 0.1, 0.1));
     }
 
@@ -250,10 +1721,10 @@ mod tests {
     fn reflected_color_on_non_relfective_surface() {
         let mut w = World::ch7_default();
         let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
-        w.objects[1].material.ambient = 1.0;
-        let i = Intersection::new(1.0, w.objects[1]);
-        let comps = i.prepare_computations(r, &Intersections::new(vec![i]));
-        let color = w.reflect_color(comps, MAX_REFLECTIONS);
+        w.get_mut(w.id_at(1)).unwrap().material.ambient = 1.0;
+        let i = Intersection::new(1.0, w.objects()[1].clone());
+        let comps = i.prepare_computations(r, &Intersections::new(vec![i.clone()]));
+        let color = w.reflect_color(&comps, MAX_REFLECTIONS);
         assert_eq!(color, Color::black())
     }
 
@@ -266,11 +1737,11 @@ mod tests {
         );
         let mut p = Shape::plane().with_transform(translation(0.0, -1.0, 0.0));
         p.material.reflective = 0.5;
-        w.objects.push(p);
+        w.add(p.clone());
 
-        let i = Intersection::new(SQRT_2, p);
-        let comps = i.prepare_computations(r, &Intersections::new(vec![i]));
-        let color = w.reflect_color(comps, MAX_REFLECTIONS);
+        let i = Intersection::new(SQRT_2, p.clone());
+        let comps = i.prepare_computations(r, &Intersections::new(vec![i.clone()]));
+        let color = w.reflect_color(&comps, MAX_REFLECTIONS);
         assert_eq!(color, Color::new(0.19033, 0.237915, 0.142749))
     }
 
@@ -283,14 +1754,95 @@ mod tests {
         );
         let mut p = Shape::plane().with_transform(translation(0.0, -1.0, 0.0));
         p.material.reflective = 0.5;
-        w.objects.push(p);
+        w.add(p.clone());
 
-        let i = Intersection::new(SQRT_2, p);
-        let comps = i.prepare_computations(r, &Intersections::new(vec![i]));
-        let color = w.shade_hit(comps, MAX_REFLECTIONS);
+        let i = Intersection::new(SQRT_2, p.clone());
+        let comps = i.prepare_computations(r, &Intersections::new(vec![i.clone()]));
+        let color = w.shade_hit(&comps, MAX_REFLECTIONS);
         assert_eq!(color, Color::new(0.87675, 0.92434, 0.82917))
     }
 
+    #[test]
+    fn volumes_attenuate_what_lies_behind_them() {
+        let mut w = World::new();
+        w.lights
+            .push(Light::new(point(0.0, 0.0, -10.0), Color::white()));
+        let ink = Material::default().medium(Medium::new(0.5, Color::black()));
+        w.add(Shape::sphere().with_material(ink));
+        let flat = Material {
+            ambient: 1.0,
+            diffuse: 0.0,
+            specular: 0.0,
+            ..Default::default()
+        };
+        w.add(
+            Shape::plane()
+                .with_material(flat)
+                .with_transform(rot_x(PI / 2.0).translation(0.0, 0.0, 3.0)),
+        );
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        // the ink only absorbs, over its 2 units of thickness
+        let c = w.color_at(r);
+        assert_eq!(c, Color::white() * Float::exp(-1.0));
+    }
+
+    #[test]
+    fn lit_volumes_scatter_light_and_cast_no_hard_shadow() {
+        let mut w = World::new();
+        w.lights
+            .push(Light::new(point(0.0, 10.0, 0.0), Color::white()));
+        let smoke = Material::default().medium(Medium::new(1.0, Color::white()));
+        w.add(Shape::sphere().with_material(smoke));
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let c = w.color_at(r);
+        assert!(c.r() > 0.0 && c.r() < 1.0, "{:?}", c);
+        let down = Ray::new(point(0.0, 5.0, 0.0), vector(0.0, -1.0, 0.0));
+        assert!(!w.occluded(down, 10.0));
+    }
+
+    #[test]
+    fn clip_planes_cut_geometry_away() {
+        let mut w = World::ch7_default();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let lit = w.color_at(r);
+        // cut away everything in front of z = 0, the ray goes through the
+        // opened spheres and sees nothing lit from the inside
+        w.add_clip_plane(ClipPlane::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, -1.0)));
+        assert_ne!(w.color_at(r), lit);
+        assert!(!w.occluded(r, 4.5));
+    }
+
+    #[test]
+    fn capped_clip_planes_close_the_cut() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let w = World::ch7_default().with_clip_plane(
+            ClipPlane::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, -1.0)).with_cap(red),
+        );
+        let through = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(through), red);
+        let beside = Ray::new(point(0.0, 2.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(beside), Color::black());
+    }
+
+    #[test]
+    fn objects_hidden_from_the_camera_still_show_in_reflections() {
+        let mut w = World::ch7_default();
+        w.get_mut(w.id_at(0)).unwrap().material = w.objects()[0].material.clone().reflective(1.0);
+        *w.get_mut(w.id_at(1)).unwrap() = (*w.objects()[1]).clone().hidden_from(RayKind::Camera);
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        // the mirror sphere reflects the camera ray back, towards a backdrop
+        // behind the camera
+        let backdrop = Shape::plane()
+            .with_material(Material::default().ambient(1.0))
+            .with_transform(rot_x(PI / 2.0).translation(0.0, 0.0, -10.0))
+            .hidden_from(RayKind::Camera);
+        w.add(backdrop);
+        let with_backdrop = w.color_at(r);
+        *w.get_mut(w.id_at(2)).unwrap() =
+            (*w.objects()[2]).clone().hidden_from(RayKind::Reflection);
+        assert_ne!(w.color_at(r), with_backdrop);
+    }
+
     #[test]
     fn mutually_reflective_surfaces() {
         let mut w = World::ch7_default();
@@ -302,10 +1854,10 @@ mod tests {
             .with_transform(translation(0.0, 1.0, 0.0));
         let light = Light::new(point(0.0, 0.0, 0.0), Color::white());
         w.lights.push(light);
-        w.objects.push(lower);
-        w.objects.push(upper);
+        w.add(lower);
+        w.add(upper);
         let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 1.0, 0.0));
-        let c = w.color_at(r, MAX_REFLECTIONS);
+        let c = w.color_at(r);
         assert!(c != Color::black())
     }
 
@@ -318,80 +1870,123 @@ mod tests {
         );
         let mut p = Shape::plane().with_transform(translation(0.0, -1.0, 0.0));
         p.material.reflective = 0.5;
-        w.objects.push(p);
+        w.add(p.clone());
 
-        let i = Intersection::new(SQRT_2, p);
-        let comps = i.prepare_computations(r, &Intersections::new(vec![i]));
-        let color = w.reflect_color(comps, 0);
+        let i = Intersection::new(SQRT_2, p.clone());
+        let comps = i.prepare_computations(r, &Intersections::new(vec![i.clone()]));
+        let color = w.reflect_color(&comps, 0);
         assert_eq!(color, Color::black())
     }
 
     #[test]
     fn refracted_color_with_opaque_surface() {
         let w = World::ch7_default();
-        let s = w.objects[0];
+        let s = &w.objects()[0];
         let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
-        let xs = Intersections::new(vec![Intersection::new(4.0, s), Intersection::new(6.0, s)]);
+        let xs = Intersections::new(vec![
+            Intersection::new(4.0, s.clone()),
+            Intersection::new(6.0, s.clone()),
+        ]);
         let comps = xs.data()[0].prepare_computations(r, &xs);
-        let c = w.refracted_color(comps, 5);
+        let c = w.refracted_color(&comps, 5);
         assert_eq!(c, Color::black());
     }
 
     #[test]
     fn refracted_color_at_max_recursive_depth() {
         let mut w = World::ch7_default();
-        let s = &mut w.objects[0];
-        s.material = s.material.transparency(1.0).refractive_index(1.5);
+        let s = w.get_mut(w.id_at(0)).unwrap();
+        s.material = s.material.clone().transparency(1.0).refractive_index(1.5);
         let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
-        let xs = Intersections::new(vec![Intersection::new(4.0, *s), Intersection::new(6.0, *s)]);
+        let xs = Intersections::new(vec![
+            Intersection::new(4.0, s.clone()),
+            Intersection::new(6.0, s.clone()),
+        ]);
         let comps = xs.data()[0].prepare_computations(r, &xs);
-        let c = w.refracted_color(comps, 0);
+        let c = w.refracted_color(&comps, 0);
         assert_eq!(c, Color::black());
     }
 
     #[test]
     fn refracted_color_total_internal_reflection() {
         let mut w = World::ch7_default();
-        let s = &mut w.objects[0];
-        s.material = s.material.transparency(1.0).refractive_index(1.5);
+        let s = w.get_mut(w.id_at(0)).unwrap();
+        s.material = s.material.clone().transparency(1.0).refractive_index(1.5);
         let r = Ray::new(point(0.0, 0.0, SQRT_2 / 2.0), vector(0.0, 1.0, 0.0));
         let xs = Intersections::new(vec![
-            Intersection::new(-SQRT_2 / 2.0, *s),
-            Intersection::new(SQRT_2 / 2.0, *s),
+            Intersection::new(-SQRT_2 / 2.0, s.clone()),
+            Intersection::new(SQRT_2 / 2.0, s.clone()),
         ]);
         let comps = xs.data()[1].prepare_computations(r, &xs);
-        let c = w.refracted_color(comps, 5);
+        let c = w.refracted_color(&comps, 5);
         assert_eq!(c, Color::black());
     }
 
     #[test]
     fn refracted_color_with_refracted_ray() {
         let mut w = World::ch7_default();
-        let a = &mut w.objects[0];
+        let a = w.get_mut(w.id_at(0)).unwrap();
         a.material.ambient = 1.0;
         a.set_pattern(Pattern::test_pattern());
-        let a = w.objects[0];
-        let b = &mut w.objects[1];
-        b.material = b.material.transparency(1.0).refractive_index(1.5);
+        let a = w.objects()[0].clone();
+        let b = w.get_mut(w.id_at(1)).unwrap();
+        b.material = b.material.clone().transparency(1.0).refractive_index(1.5);
         let r = Ray::new(point(0.0, 0.0, 0.1), vector(0.0, 1.0, 0.0));
         let xs = Intersections::new(vec![
-            Intersection::new(-0.9899, a),
-            Intersection::new(-0.4899, *b),
-            Intersection::new(0.4899, *b),
-            Intersection::new(0.9899, a),
+            Intersection::new(-0.9899, a.clone()),
+            Intersection::new(-0.4899, b.clone()),
+            Intersection::new(0.4899, b.clone()),
+            Intersection::new(0.9899, a.clone()),
         ]);
         let comps = xs.data()[2].prepare_computations(r, &xs);
-        let c = w.refracted_color(comps, 5);
+        let c = w.refracted_color(&comps, 5);
         assert_eq!(c, Color::new(0.0, 0.998874, 0.047218));
     }
 
+    #[test]
+    fn light_is_absorbed_along_the_way_through_the_object() {
+        let mut w = World::new();
+        w.lights
+            .push(Light::new(point(0.0, 0.0, -10.0), Color::white()));
+        let wall = Material {
+            ambient: 1.0,
+            diffuse: 0.0,
+            specular: 0.0,
+            ..Default::default()
+        };
+        w.add(
+            Shape::plane()
+                .with_transform(translation(0.0, 0.0, 5.0) * rot_x(PI / 2.0))
+                .with_material(wall),
+        );
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let glass = |absorption| {
+            Shape::glass_sphere().with_material(
+                Material::default()
+                    .ambient(0.0)
+                    .diffuse(0.0)
+                    .specular(0.0)
+                    .transparency(1.0)
+                    .refractive_index(1.5)
+                    .absorption(absorption),
+            )
+        };
+        w.add(glass(Color::black()));
+        let clear = w.color_at(r);
+        *w.get_mut(w.id_at(1)).unwrap() = glass(Color::new(0.5, 0.0, 0.0));
+        let tinted = w.color_at(r);
+        assert!(clear.r() > 0.5);
+        assert!((tinted.r() / clear.r() - Float::exp(-1.0)).abs() < 1e-3);
+        assert!(flt_eq(tinted.g(), clear.g()));
+    }
+
     #[test]
     fn shade_hit_with_transparent_material() {
         let mut w = World::ch7_default();
         let floor = Shape::plane()
             .with_material(Material::default().transparency(0.5).refractive_index(1.5))
             .with_transform(translation(0.0, -1.0, 0.0));
-        w.objects.push(floor);
+        w.add(floor.clone());
         let ball = Shape::sphere()
             .with_material(
                 Material::default()
@@ -399,45 +1994,42 @@ mod tests {
                     .color(Color::new(1.0, 0.0, 0.0)),
             )
             .with_transform(translation(0.0, -3.5, -0.5));
-        w.objects.push(ball);
+        w.add(ball);
 
         let r = Ray::new(
             point(0.0, 0.0, -3.0),
-            vector(0.0, -f64::sqrt(2.0) / 2.0, f64::sqrt(2.0) / 2.0),
+            vector(0.0, -Float::sqrt(2.0) / 2.0, Float::sqrt(2.0) / 2.0),
         );
-        let xs = Intersections::new(vec![Intersection::new(f64::sqrt(2.0), floor)]);
+        let xs = Intersections::new(vec![Intersection::new(Float::sqrt(2.0), floor.clone())]);
         let comps = xs.data()[0].prepare_computations(r, &xs);
-        let c = w.shade_hit(comps, 5);
+        let c = w.shade_hit(&comps, 5);
         assert_eq!(c, Color::new(0.93642, 0.68642, 0.68642));
     }
     #[test]
     fn shade_hit_with_reflective_transparent_material() {
-        let mut w = World::ch7_default();
-        let floor = Shape::plane()
-            .with_material(
-                Material::default()
-                    .transparency(0.5)
-                    .reflective(0.5)
-                    .refractive_index(1.5),
-            )
-            .with_transform(translation(0.0, -1.0, 0.0));
-        w.objects.push(floor);
-        let ball = Shape::sphere()
-            .with_material(
-                Material::default()
-                    .ambient(0.5)
-                    .color(Color::new(1.0, 0.0, 0.0)),
-            )
-            .with_transform(translation(0.0, -3.5, -0.5));
-        w.objects.push(ball);
-
+        let w = World::ch11_default();
+        let floor = w.objects()[2].clone();
         let r = Ray::new(
             point(0.0, 0.0, -3.0),
-            vector(0.0, -f64::sqrt(2.0) / 2.0, f64::sqrt(2.0) / 2.0),
+            vector(0.0, -Float::sqrt(2.0) / 2.0, Float::sqrt(2.0) / 2.0),
         );
-        let xs = Intersections::new(vec![Intersection::new(f64::sqrt(2.0), floor)]);
+        let xs = Intersections::new(vec![Intersection::new(Float::sqrt(2.0), floor.clone())]);
         let comps = xs.data()[0].prepare_computations(r, &xs);
-        let c = w.shade_hit(comps, 5);
+        let c = w.shade_hit(&comps, 5);
         assert_eq!(c, Color::new(0.93391, 0.69643, 0.69243));
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn worlds_round_trip_through_serde() {
+        let mut w = World::ch7_default();
+        w.build_bvh();
+        let json = serde_json::to_string(&w).unwrap();
+        let back: World = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.lights, w.lights);
+        assert_eq!(back.objects(), w.objects());
+        assert!(back.bvh.is_none());
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(back.color_at(r), w.color_at(r));
+    }
 }